@@ -0,0 +1,49 @@
+//!Selection of VNDB API.
+//!
+//!Backends accept requests of legacy TCP API and respond in its format,
+//!therefore users are not aware which one is used.
+
+extern crate actix;
+
+use self::actix::prelude::*;
+
+use ::vndb;
+use ::kana;
+use ::time;
+
+///Client of VNDB regardless of backend.
+pub type Client = Recipient<vndb::Request>;
+
+///VNDB API to use.
+#[derive(Clone, Debug)]
+pub enum Backend {
+    ///Legacy TCP API.
+    Tcp(vndb::Endpoint),
+    ///JSON HTTP API at URL.
+    Kana(String),
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        Backend::Tcp(vndb::Endpoint::default())
+    }
+}
+
+impl Backend {
+    ///Starts client with default request timeout.
+    pub fn start(&self) -> Client {
+        self.start_with_timeout(time::Duration::from_secs(vndb::DEFAULT_REQUEST_TIMEOUT_SECS))
+    }
+
+    ///Starts client with specified time to wait for response.
+    pub fn start_with_timeout(&self, timeout: time::Duration) -> Client {
+        match self {
+            &Backend::Tcp(ref endpoint) => {
+                let endpoint = endpoint.clone();
+                let vndb: Addr<_> = Supervisor::start(move |_| vndb::Vndb::with_endpoint(endpoint).request_timeout(timeout));
+                vndb.recipient()
+            },
+            &Backend::Kana(ref url) => kana::Kana::with_url(url.as_str()).request_timeout(timeout).start().recipient(),
+        }
+    }
+}
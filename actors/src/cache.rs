@@ -0,0 +1,241 @@
+//!Bounded cache with expiration of entries.
+//!
+//!When cache is full, least recently used entry is evicted.
+//!Besides values, cache remembers keys that are known to have no value, so that
+//!repeated lookups of missing objects are answered without request too.
+
+use ::collections::{HashMap, BTreeMap};
+use ::hash::Hash;
+use ::time::{Duration, Instant};
+use ::fmt;
+
+///Default maximum number of entries.
+const DEFAULT_CAPACITY: usize = 512;
+///Default time to live of found values.
+const DEFAULT_TTL_SECS: u64 = 60 * 60;
+///Default time to live of not found entries.
+const DEFAULT_NOT_FOUND_TTL_SECS: u64 = 5 * 60;
+
+///Cache settings.
+#[derive(Clone, Copy, Debug)]
+pub struct Config {
+    ///Maximum number of entries. Zero disables cache.
+    pub capacity: usize,
+    ///Time to live of found values.
+    pub ttl: Duration,
+    ///Time to live of not found entries.
+    pub not_found_ttl: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_CAPACITY,
+            ttl: Duration::from_secs(DEFAULT_TTL_SECS),
+            not_found_ttl: Duration::from_secs(DEFAULT_NOT_FOUND_TTL_SECS),
+        }
+    }
+}
+
+///Cached outcome of lookup.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Cached<V> {
+    Found(V),
+    NotFound,
+}
+
+///Usage counters.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Stats {
+    pub hits: u64,
+    pub misses: u64,
+    ///Number of stored entries.
+    pub len: usize,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} hits, {} misses, {} entries", self.hits, self.misses, self.len)
+    }
+}
+
+struct Entry<V> {
+    value: Cached<V>,
+    expires: Instant,
+    ///Last time of use, as position in `Cache::order`.
+    used: u64,
+}
+
+pub struct Cache<K, V> {
+    config: Config,
+    entries: HashMap<K, Entry<V>>,
+    ///Keys by last time of use, the least recently used first.
+    order: BTreeMap<u64, K>,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Cache<K, V> {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    #[inline]
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    ///Looks up entry, returning `None` if there is no entry or it is expired.
+    pub fn get(&mut self, key: &K) -> Option<Cached<V>> {
+        let expired = match self.entries.get(key) {
+            Some(entry) => Instant::now() >= entry.expires,
+            None => {
+                self.misses += 1;
+                return None;
+            }
+        };
+
+        if expired {
+            self.remove(key);
+            self.misses += 1;
+            return None;
+        }
+
+        let used = self.tick();
+        let entry = self.entries.get_mut(key).unwrap();
+        self.order.remove(&entry.used);
+        self.order.insert(used, key.clone());
+        entry.used = used;
+
+        self.hits += 1;
+        Some(entry.value.clone())
+    }
+
+    ///Stores found value.
+    pub fn put(&mut self, key: K, value: V) {
+        let ttl = self.config.ttl;
+        self.insert(key, Cached::Found(value), ttl);
+    }
+
+    ///Remembers that there is no value for the key.
+    pub fn put_not_found(&mut self, key: K) {
+        let ttl = self.config.not_found_ttl;
+        self.insert(key, Cached::NotFound, ttl);
+    }
+
+    fn insert(&mut self, key: K, value: Cached<V>, ttl: Duration) {
+        if self.config.capacity == 0 {
+            return;
+        }
+
+        self.remove(&key);
+
+        while self.entries.len() >= self.config.capacity {
+            let oldest = match self.order.keys().next() {
+                Some(used) => *used,
+                None => break,
+            };
+
+            if let Some(oldest) = self.order.remove(&oldest) {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        let used = self.tick();
+        self.order.insert(used, key.clone());
+        self.entries.insert(key, Entry {
+            value,
+            expires: Instant::now() + ttl,
+            used,
+        });
+    }
+
+    pub fn remove(&mut self, key: &K) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.order.remove(&entry.used);
+        }
+    }
+
+    pub fn stats(&self) -> Stats {
+        Stats {
+            hits: self.hits,
+            misses: self.misses,
+            len: self.entries.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cache, Cached, Config, Stats};
+    use ::time::Duration;
+
+    fn config(capacity: usize) -> Config {
+        Config {
+            capacity,
+            ttl: Duration::from_secs(60),
+            not_found_ttl: Duration::from_secs(0),
+        }
+    }
+
+    #[test]
+    fn should_cache_values() {
+        let mut cache = Cache::new(config(2));
+
+        assert_eq!(cache.get(&1), None);
+        cache.put(1, "one");
+        assert_eq!(cache.get(&1), Some(Cached::Found("one")));
+        cache.put(1, "uno");
+        assert_eq!(cache.get(&1), Some(Cached::Found("uno")));
+
+        assert_eq!(cache.stats(), Stats { hits: 2, misses: 1, len: 1 });
+    }
+
+    #[test]
+    fn should_evict_least_recently_used() {
+        let mut cache = Cache::new(config(2));
+
+        cache.put(1, "one");
+        cache.put(2, "two");
+        assert!(cache.get(&1).is_some());
+        cache.put(3, "three");
+
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&1), Some(Cached::Found("one")));
+        assert_eq!(cache.get(&3), Some(Cached::Found("three")));
+        assert_eq!(cache.stats().len, 2);
+    }
+
+    #[test]
+    fn should_expire_entries() {
+        let mut cache: Cache<u64, &str> = Cache::new(config(2));
+
+        cache.put_not_found(1);
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.stats().len, 0);
+
+        let mut cache = Cache::new(Config { not_found_ttl: Duration::from_secs(60), ..config(2) });
+        cache.put_not_found(1);
+        assert_eq!(cache.get(&1), Some(Cached::NotFound));
+        cache.put(1, "one");
+        assert_eq!(cache.get(&1), Some(Cached::Found("one")));
+    }
+
+    #[test]
+    fn should_not_store_when_disabled() {
+        let mut cache = Cache::new(config(0));
+
+        cache.put(1, "one");
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.stats(), Stats { hits: 0, misses: 1, len: 0 });
+    }
+}
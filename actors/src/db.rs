@@ -5,7 +5,11 @@ extern crate db;
 use self::actix::prelude::*;
 
 use self::db::Db as InnerDb;
+use self::db::result;
 pub use self::db::models;
+pub use self::db::Restore;
+pub use self::db::search;
+pub use self::db::hook_code;
 
 use ::fmt;
 
@@ -20,19 +24,120 @@ impl Db {
         }
     }
 
+    pub fn open(path: &str) -> Self {
+        Self {
+            inner: InnerDb::open(path).expect("Actor DB to start")
+        }
+    }
+
     pub fn start_threaded(threads: usize) -> Addr<Self> {
         SyncArbiter::start(threads, || Self::new())
     }
+
+    ///Starts DB actors with custom path to DB.
+    ///
+    ///Note that each thread opens its own connection,
+    ///so in-memory DB requires single thread.
+    pub fn start_threaded_at(threads: usize, path: String) -> Addr<Self> {
+        SyncArbiter::start(threads, move || Self::open(&path))
+    }
 }
 
 impl Actor for Db {
     type Context = SyncContext<Self>;
 }
 
+///Error of DB operation.
+///
+///It is described in a way that is safe to show to users,
+///while details of underlying failure are only logged.
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    ///Data that operation relies on doesn't exist.
+    NotFound,
+    ///Data conflicts with already stored one.
+    Constraint,
+    ///DB is locked by another connection.
+    Busy,
+    ///DB file cannot be accessed.
+    Io,
+    ///Request is rejected before reaching DB. Contains reason.
+    Invalid(String),
+    ///Any other failure of DB.
+    Internal,
+}
+
+impl Error {
+    fn from_message(message: &str) -> Self {
+        let message = message.to_lowercase();
+
+        if message.contains("constraint failed") {
+            Error::Constraint
+        } else if message.contains("locked") || message.contains("busy") {
+            Error::Busy
+        } else if message.contains("i/o") || message.contains("unable to open") || message.contains("disk is full") || message.contains("readonly") {
+            Error::Io
+        } else {
+            Error::Internal
+        }
+    }
+}
+
+impl From<result::Error> for Error {
+    fn from(error: result::Error) -> Self {
+        let kind = match error {
+            result::Error::NotFound => Error::NotFound,
+            result::Error::DatabaseError(result::DatabaseErrorKind::UniqueViolation, _) => Error::Constraint,
+            result::Error::DatabaseError(result::DatabaseErrorKind::ForeignKeyViolation, _) => Error::Constraint,
+            result::Error::DatabaseError(_, ref info) => Error::from_message(info.message()),
+            _ => Error::Internal,
+        };
+
+        match kind {
+            Error::NotFound => warn!("DB: {}", error),
+            _ => error!("DB: {}", error),
+        }
+
+        kind
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut ::fmt::Formatter) -> fmt::Result {
+        match self {
+            &Error::NotFound => write!(f, "Requested data doesn't exist."),
+            &Error::Constraint => write!(f, "It conflicts with already stored data."),
+            &Error::Busy => write!(f, "Database is busy. Try again later."),
+            &Error::Io => write!(f, "Database is unavailable. Try again later."),
+            &Error::Invalid(ref reason) => write!(f, "{}", reason),
+            &Error::Internal => write!(f, "Database failed to process request."),
+        }
+    }
+}
+
 ///Result of `GetVnData` Command
 pub struct VnData {
     pub data: models::Vn,
-    pub hooks: Vec<models::Hook>
+    pub hooks: Vec<models::Hook>,
+    ///Releases that hooks are set for.
+    pub releases: Vec<models::Release>,
+    ///Executables of VN's game.
+    pub executables: Vec<models::Executable>,
+}
+
+impl VnData {
+    ///Returns release that hook is set for.
+    pub fn release(&self, hook: &models::Hook) -> Option<&models::Release> {
+        hook.release_id.and_then(|id| self.releases.iter().find(|release| release.id == id))
+    }
+
+    ///Describes version of hook, using its release if any.
+    fn version(&self, hook: &models::Hook) -> String {
+        match self.release(hook) {
+            Some(release) => format!("{}", release),
+            None => hook.version.clone()
+        }
+    }
 }
 
 impl fmt::Display for VnData {
@@ -41,13 +146,19 @@ impl fmt::Display for VnData {
             0 => write!(f, "No hook exists for VN '{}'", self.data.title),
             1 => {
                 let hook = unsafe { self.hooks.get_unchecked(0) };
-                write!(f, "{} - {}", self.data.title, hook.code)
+                match hook.submitter() {
+                    Some(submitter) => write!(f, "{} - {} (by {})", self.data.title, hook.code, submitter),
+                    None => write!(f, "{} - {}", self.data.title, hook.code)
+                }
             },
             _ => {
                 let mut text = format!("{} - ", self.data.title);
 
                 for hook in self.hooks.iter() {
-                    text.push_str(&format!("{}: {} | ", hook.version, hook.code));
+                    match hook.submitter() {
+                        Some(submitter) => text.push_str(&format!("{}: {} (by {}) | ", self.version(hook), hook.code, submitter)),
+                        None => text.push_str(&format!("{}: {} | ", self.version(hook), hook.code)),
+                    }
                 }
 
                 write!(f, "{}", &text[..text.len()-3])
@@ -57,38 +168,44 @@ impl fmt::Display for VnData {
     }
 }
 
+fn vn_data(db: &InnerDb, vn: models::Vn) -> Result<VnData, Error> {
+    let hooks = db.get_hooks(&vn)?;
+    let releases = db.get_releases(&vn)?;
+    let executables = db.get_executables(&vn)?;
+
+    Ok(VnData { data: vn, hooks, releases, executables })
+}
 
 ///Retrieves all information about VN
 pub struct GetVnData(pub u64);
 impl Message for GetVnData {
-    type Result = Result<Option<VnData>, String>;
+    type Result = Result<Option<VnData>, Error>;
 }
 impl Handler<GetVnData> for Db {
     type Result = <GetVnData as Message>::Result;
 
     fn handle(&mut self, msg: GetVnData, _: &mut Self::Context) -> Self::Result {
-        let vn = self.inner.get_vn(msg.0 as i64).map_err(|err| format!("{}", err))?;
+        let vn = self.inner.get_vn(msg.0 as i64)?;
 
         let vn = match vn {
             Some(vn) => vn,
             None => return Ok(None)
         };
 
-        self.inner.get_hooks(&vn).map(|hooks| Some(VnData { data: vn, hooks }))
-                                 .map_err(|err| format!("{}", err))
+        vn_data(&self.inner, vn).map(Some)
     }
 }
 
 ///Retrieves VN
 pub struct GetVn(pub u64);
 impl Message for GetVn {
-    type Result = Result<Option<models::Vn>, String>;
+    type Result = Result<Option<models::Vn>, Error>;
 }
 impl Handler<GetVn> for Db {
     type Result = <GetVn as Message>::Result;
 
     fn handle(&mut self, msg: GetVn, _: &mut Self::Context) -> Self::Result {
-        self.inner.get_vn(msg.0 as i64).map_err(|err| format!("{}", err))
+        self.inner.get_vn(msg.0 as i64).map_err(Error::from)
     }
 }
 
@@ -96,78 +213,346 @@ impl Handler<GetVn> for Db {
 ///Retrieves hooks for VN.
 pub struct GetHooks(pub models::Vn);
 impl Message for GetHooks {
-    type Result = Result<VnData, String>;
+    type Result = Result<VnData, Error>;
 }
 impl Handler<GetHooks> for Db {
     type Result = <GetHooks as Message>::Result;
 
     fn handle(&mut self, msg: GetHooks, _: &mut Self::Context) -> Self::Result {
-        let vn = msg.0;
-        self.inner.get_hooks(&vn).map(|hooks| VnData { data: vn, hooks })
-                                 .map_err(|err| format!("{}", err))
+        vn_data(&self.inner, msg.0)
+    }
+}
+
+///Retrieves all VNs that have hooks, along with their hooks.
+pub struct GetAllVnData;
+impl Message for GetAllVnData {
+    type Result = Result<Vec<VnData>, Error>;
+}
+impl Handler<GetAllVnData> for Db {
+    type Result = <GetAllVnData as Message>::Result;
+
+    fn handle(&mut self, _: GetAllVnData, _: &mut Self::Context) -> Self::Result {
+        let data = self.inner.get_hooked_vns_data()?;
+
+        Ok(data.into_iter().map(|(vn, hooks, releases, executables)| VnData { data: vn, hooks, releases, executables }).collect())
     }
 }
 
 ///Adds/update VN
+///
+///Original title and aliases are stored only for new VN.
 pub struct PutVn {
     pub id: u64,
-    pub title: String
+    pub title: String,
+    pub original: Option<String>,
+    pub aliases: Vec<String>,
 }
 impl Message for PutVn {
-    type Result = Result<models::Vn, String>;
+    type Result = Result<models::Vn, Error>;
 }
 impl Handler<PutVn> for Db {
     type Result = <PutVn as Message>::Result;
 
     fn handle(&mut self, msg: PutVn, _: &mut Self::Context) -> Self::Result {
-        let PutVn{id, title} = msg;
-        self.inner.put_vn(id as i64, title).map_err(|err| format!("{}", err))
+        let PutVn{id, title, original, aliases} = msg;
+        self.inner.put_vn_aliased(id as i64, title, original.as_ref().map(|original| original.as_str()), &aliases).map_err(Error::from)
+    }
+}
+
+///Retrieves VNDB release stored in DB.
+pub struct GetRelease(pub u64);
+impl Message for GetRelease {
+    type Result = Result<Option<models::Release>, Error>;
+}
+impl Handler<GetRelease> for Db {
+    type Result = <GetRelease as Message>::Result;
+
+    fn handle(&mut self, msg: GetRelease, _: &mut Self::Context) -> Self::Result {
+        self.inner.get_release(msg.0 as i64).map_err(Error::from)
+    }
+}
+
+///Adds/update VNDB release.
+pub struct PutRelease(pub models::Release);
+impl Message for PutRelease {
+    type Result = Result<models::Release, Error>;
+}
+impl Handler<PutRelease> for Db {
+    type Result = <PutRelease as Message>::Result;
+
+    fn handle(&mut self, msg: PutRelease, _: &mut Self::Context) -> Self::Result {
+        let release = msg.0;
+        self.inner.put_release(&release).map(|_| release).map_err(Error::from)
     }
 }
 
 ///Adds/update hook for VN
+///
+///Code is validated and normalized before being stored.
 pub struct PutHook {
     pub vn: models::Vn,
     pub version: String,
     pub code: String,
+    pub author: models::Author,
 }
 impl Message for PutHook {
-    type Result = Result<models::HookView, String>;
+    type Result = Result<models::HookView, Error>;
 }
 impl Handler<PutHook> for Db {
     type Result = <PutHook as Message>::Result;
 
     fn handle(&mut self, msg: PutHook, _: &mut Self::Context) -> Self::Result {
-        let PutHook{ vn, version, code } = msg;
-        self.inner.put_hook(&vn, version, code).map_err(|err| format!("{}", err))
+        let PutHook{ vn, version, code, author } = msg;
+        let code = hook_code::normalize(&code).map_err(Error::Invalid)?;
+        self.inner.put_hook(&vn, version, code, &author).map_err(Error::from)
     }
 }
 
-///Search VNs by title in DB.
+///Search VNs by title in DB, best matches first.
 pub struct SearchVn(pub String);
 impl Message for SearchVn {
-    type Result = Result<Vec<models::Vn>, String>;
+    type Result = Result<Vec<models::Vn>, Error>;
 }
 
 impl Handler<SearchVn> for Db {
     type Result = <SearchVn as Message>::Result;
 
     fn handle(&mut self, msg: SearchVn, _: &mut Self::Context) -> Self::Result {
-        self.inner.search_vn(&msg.0).map_err(|err| format!("{}", err))
+        self.inner.search_vn(&msg.0).map_err(Error::from)
+    }
+}
+
+///Looks up VN in DB by title, original title or alias.
+pub struct FindVn(pub String);
+impl Message for FindVn {
+    type Result = Result<Vec<models::Vn>, Error>;
+}
+
+impl Handler<FindVn> for Db {
+    type Result = <FindVn as Message>::Result;
+
+    fn handle(&mut self, msg: FindVn, _: &mut Self::Context) -> Self::Result {
+        self.inner.find_vn(&msg.0).map_err(Error::from)
+    }
+}
+
+///Retrieves aliases of VN.
+pub struct GetAliases(pub models::Vn);
+impl Message for GetAliases {
+    type Result = Result<Vec<models::Alias>, Error>;
+}
+impl Handler<GetAliases> for Db {
+    type Result = <GetAliases as Message>::Result;
+
+    fn handle(&mut self, msg: GetAliases, _: &mut Self::Context) -> Self::Result {
+        self.inner.get_aliases(&msg.0).map_err(Error::from)
+    }
+}
+
+///Adds alias to VN.
+pub struct PutAlias {
+    pub vn: models::Vn,
+    pub alias: String,
+    pub author: models::Author,
+}
+impl Message for PutAlias {
+    type Result = Result<models::Alias, Error>;
+}
+impl Handler<PutAlias> for Db {
+    type Result = <PutAlias as Message>::Result;
+
+    fn handle(&mut self, msg: PutAlias, _: &mut Self::Context) -> Self::Result {
+        let PutAlias{vn, alias, author} = msg;
+        self.inner.put_alias(&vn, &alias, &author).map_err(Error::from)
+    }
+}
+
+///Removes alias of VN.
+pub struct DelAlias {
+    pub vn: models::Vn,
+    pub alias: String,
+    pub author: models::Author,
+}
+impl Message for DelAlias {
+    type Result = Result<usize, Error>;
+}
+impl Handler<DelAlias> for Db {
+    type Result = <DelAlias as Message>::Result;
+
+    fn handle(&mut self, msg: DelAlias, _: &mut Self::Context) -> Self::Result {
+        let DelAlias{vn, alias, author} = msg;
+        self.inner.delete_alias(&vn, &alias, &author).map_err(Error::from)
+    }
+}
+
+///Normalizes name and optional hash of executable.
+fn executable_key(name: &str, hash: Option<&String>) -> Result<(String, Option<String>), String> {
+    let name = models::executable_name(name)?;
+    let hash = match hash {
+        Some(hash) => Some(models::file_hash(hash)?),
+        None => None
+    };
+
+    Ok((name, hash))
+}
+
+///Result of `FindExecutable`.
+pub struct ExecutableData {
+    pub executable: models::Executable,
+    pub data: VnData,
+}
+
+impl fmt::Display for ExecutableData {
+    fn fmt(&self, f: &mut ::fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.executable.name, self.data)
+    }
+}
+
+///Looks up VNs by name and optional hash of executable.
+pub struct FindExecutable {
+    pub name: String,
+    pub hash: Option<String>,
+}
+impl Message for FindExecutable {
+    type Result = Result<Vec<ExecutableData>, Error>;
+}
+impl Handler<FindExecutable> for Db {
+    type Result = <FindExecutable as Message>::Result;
+
+    fn handle(&mut self, msg: FindExecutable, _: &mut Self::Context) -> Self::Result {
+        let (name, hash) = executable_key(&msg.name, msg.hash.as_ref()).map_err(Error::Invalid)?;
+        let executables = self.inner.find_executables(&name, hash.as_ref().map(|hash| hash.as_str()))?;
+
+        let mut result: Vec<ExecutableData> = Vec::with_capacity(executables.len());
+        for executable in executables {
+            //Executables that differ only by hash may point to the same VN.
+            if result.iter().any(|data| data.data.data.id == executable.vn_id) {
+                continue;
+            }
+
+            let vn = match self.inner.get_vn(executable.vn_id)? {
+                Some(vn) => vn,
+                None => continue
+            };
+
+            result.push(ExecutableData { executable, data: vn_data(&self.inner, vn)? });
+        }
+
+        Ok(result)
+    }
+}
+
+///Adds executable of VN, optionally linked to its release.
+pub struct PutExecutable {
+    pub vn: models::Vn,
+    pub name: String,
+    pub hash: Option<String>,
+    pub release_id: Option<i64>,
+    pub author: models::Author,
+}
+impl Message for PutExecutable {
+    type Result = Result<models::Executable, Error>;
+}
+impl Handler<PutExecutable> for Db {
+    type Result = <PutExecutable as Message>::Result;
+
+    fn handle(&mut self, msg: PutExecutable, _: &mut Self::Context) -> Self::Result {
+        let PutExecutable{vn, name, hash, release_id, author} = msg;
+        let (name, hash) = executable_key(&name, hash.as_ref()).map_err(Error::Invalid)?;
+
+        if let Some(release_id) = release_id {
+            match self.inner.get_release(release_id)? {
+                Some(ref release) if release.vn_id == vn.id => (),
+                _ => return Err(Error::Invalid(format!("r{} is not a known release of v{}", release_id, vn.id))),
+            }
+        }
+
+        self.inner.put_executable(&vn, &name, hash.as_ref().map(|hash| hash.as_str()), release_id, &author).map_err(Error::from)
+    }
+}
+
+///Removes executable of VN.
+pub struct DelExecutable {
+    pub vn: models::Vn,
+    pub name: String,
+    pub hash: Option<String>,
+    pub author: models::Author,
+}
+impl Message for DelExecutable {
+    type Result = Result<usize, Error>;
+}
+impl Handler<DelExecutable> for Db {
+    type Result = <DelExecutable as Message>::Result;
+
+    fn handle(&mut self, msg: DelExecutable, _: &mut Self::Context) -> Self::Result {
+        let DelExecutable{vn, name, hash, author} = msg;
+        let (name, hash) = executable_key(&name, hash.as_ref()).map_err(Error::Invalid)?;
+
+        self.inner.delete_executable(&vn, &name, hash.as_ref().map(|hash| hash.as_str()), &author).map_err(Error::from)
+    }
+}
+
+///Retrieves VNs that have not been synchronized with VNDB for `age` seconds.
+pub struct GetUnsyncedVns {
+    pub age: i64,
+    pub limit: i64,
+}
+impl Message for GetUnsyncedVns {
+    type Result = Result<Vec<models::Vn>, Error>;
+}
+impl Handler<GetUnsyncedVns> for Db {
+    type Result = <GetUnsyncedVns as Message>::Result;
+
+    fn handle(&mut self, msg: GetUnsyncedVns, _: &mut Self::Context) -> Self::Result {
+        let GetUnsyncedVns{age, limit} = msg;
+        self.inner.get_unsynced_vns(age, limit).map_err(Error::from)
+    }
+}
+
+///Updates VN with VNDB's data.
+pub struct SyncVn {
+    pub vn: models::Vn,
+    pub title: String,
+    pub original: Option<String>,
+    pub aliases: Vec<String>,
+}
+impl Message for SyncVn {
+    type Result = Result<models::VnChanges, Error>;
+}
+impl Handler<SyncVn> for Db {
+    type Result = <SyncVn as Message>::Result;
+
+    fn handle(&mut self, msg: SyncVn, _: &mut Self::Context) -> Self::Result {
+        let SyncVn{vn, title, original, aliases} = msg;
+        self.inner.sync_vn(&vn, title, original.as_ref().map(|original| original.as_str()), &aliases).map_err(Error::from)
+    }
+}
+
+///Flags VN as no longer known to VNDB.
+pub struct MarkVnMissing(pub models::Vn);
+impl Message for MarkVnMissing {
+    type Result = Result<bool, Error>;
+}
+impl Handler<MarkVnMissing> for Db {
+    type Result = <MarkVnMissing as Message>::Result;
+
+    fn handle(&mut self, msg: MarkVnMissing, _: &mut Self::Context) -> Self::Result {
+        self.inner.mark_vn_missing(&msg.0).map_err(Error::from)
     }
 }
 
 ///Deletes VN alongside all hooks
-pub struct DelVnData(pub u64);
+pub struct DelVnData(pub u64, pub models::Author);
 impl Message for DelVnData {
-    type Result = Result<usize, String>;
+    type Result = Result<usize, Error>;
 }
 
 impl Handler<DelVnData> for Db {
     type Result = <DelVnData as Message>::Result;
 
     fn handle(&mut self, msg: DelVnData, _: &mut Self::Context) -> Self::Result {
-        self.inner.delete_vn(msg.0 as i64).map_err(|err| format!("{}", err))
+        let DelVnData(id, author) = msg;
+        self.inner.delete_vn(id as i64, &author).map_err(Error::from)
     }
 }
 
@@ -175,15 +560,340 @@ impl Handler<DelVnData> for Db {
 pub struct DelHook {
     pub vn: models::Vn,
     pub version: String,
+    pub author: models::Author,
 }
 impl Message for DelHook {
-    type Result = Result<usize, String>;
+    type Result = Result<usize, Error>;
 }
 impl Handler<DelHook> for Db {
     type Result = <DelHook as Message>::Result;
 
     fn handle(&mut self, msg: DelHook, _: &mut Self::Context) -> Self::Result {
-        let DelHook{vn, version} = msg;
-        self.inner.delete_hook(&vn, &version).map_err(|err| format!("{}", err))
+        let DelHook{vn, version, author} = msg;
+        self.inner.delete_hook(&vn, &version, &author).map_err(Error::from)
+    }
+}
+
+///Retrieves latest hook revisions, optionally only for particular VN.
+pub struct GetRevisions {
+    pub vn: Option<u64>,
+    pub limit: i64,
+}
+impl Message for GetRevisions {
+    type Result = Result<Vec<models::HookRevision>, Error>;
+}
+impl Handler<GetRevisions> for Db {
+    type Result = <GetRevisions as Message>::Result;
+
+    fn handle(&mut self, msg: GetRevisions, _: &mut Self::Context) -> Self::Result {
+        let GetRevisions{vn, limit} = msg;
+        self.inner.get_revisions(vn.map(|vn| vn as i64), limit).map_err(Error::from)
+    }
+}
+
+///Restores hook to the state before revision.
+pub struct RestoreRevision {
+    pub id: u64,
+    pub author: models::Author,
+}
+impl Message for RestoreRevision {
+    type Result = Result<Restore, Error>;
+}
+impl Handler<RestoreRevision> for Db {
+    type Result = <RestoreRevision as Message>::Result;
+
+    fn handle(&mut self, msg: RestoreRevision, _: &mut Self::Context) -> Self::Result {
+        let RestoreRevision{id, author} = msg;
+        self.inner.restore_revision(id as i64, &author).map_err(Error::from)
+    }
+}
+
+///Retrieves ignore list.
+pub struct GetIgnores;
+impl Message for GetIgnores {
+    type Result = Result<Vec<models::Ignore>, Error>;
+}
+impl Handler<GetIgnores> for Db {
+    type Result = <GetIgnores as Message>::Result;
+
+    fn handle(&mut self, _: GetIgnores, _: &mut Self::Context) -> Self::Result {
+        self.inner.get_ignores().map_err(Error::from)
+    }
+}
+
+///Adds mask to ignore list.
+pub struct PutIgnore {
+    pub pattern: String,
+    pub reason: Option<String>,
+    pub author: models::Author,
+}
+impl Message for PutIgnore {
+    type Result = Result<models::Ignore, Error>;
+}
+impl Handler<PutIgnore> for Db {
+    type Result = <PutIgnore as Message>::Result;
+
+    fn handle(&mut self, msg: PutIgnore, _: &mut Self::Context) -> Self::Result {
+        let PutIgnore{pattern, reason, author} = msg;
+        self.inner.put_ignore(&pattern, reason.as_ref().map(|reason| reason.as_str()), &author).map_err(Error::from)
+    }
+}
+
+///Removes mask from ignore list.
+pub struct DelIgnore {
+    pub pattern: String,
+    pub author: models::Author,
+}
+impl Message for DelIgnore {
+    type Result = Result<usize, Error>;
+}
+impl Handler<DelIgnore> for Db {
+    type Result = <DelIgnore as Message>::Result;
+
+    fn handle(&mut self, msg: DelIgnore, _: &mut Self::Context) -> Self::Result {
+        let DelIgnore{pattern, author} = msg;
+        self.inner.delete_ignore(&pattern, &author).map_err(Error::from)
+    }
+}
+
+///Retrieves web user by name.
+pub struct GetUser(pub String);
+impl Message for GetUser {
+    type Result = Result<Option<models::User>, Error>;
+}
+impl Handler<GetUser> for Db {
+    type Result = <GetUser as Message>::Result;
+
+    fn handle(&mut self, msg: GetUser, _: &mut Self::Context) -> Self::Result {
+        self.inner.get_user(&msg.0).map_err(Error::from)
+    }
+}
+
+///Adds web user or updates existing one.
+///
+///`password` must be already hashed.
+pub struct PutUser {
+    pub name: String,
+    pub password: String,
+    pub role: models::Role,
+}
+impl Message for PutUser {
+    type Result = Result<models::User, Error>;
+}
+impl Handler<PutUser> for Db {
+    type Result = <PutUser as Message>::Result;
+
+    fn handle(&mut self, msg: PutUser, _: &mut Self::Context) -> Self::Result {
+        let PutUser{name, password, role} = msg;
+        self.inner.put_user(&name, &password, role).map_err(Error::from)
+    }
+}
+
+///Removes web user.
+pub struct DelUser(pub String);
+impl Message for DelUser {
+    type Result = Result<usize, Error>;
+}
+impl Handler<DelUser> for Db {
+    type Result = <DelUser as Message>::Result;
+
+    fn handle(&mut self, msg: DelUser, _: &mut Self::Context) -> Self::Result {
+        self.inner.delete_user(&msg.0).map_err(Error::from)
+    }
+}
+
+///Stores new session, removing expired ones.
+pub struct PutSession(pub models::Session);
+impl Message for PutSession {
+    type Result = Result<models::Session, Error>;
+}
+impl Handler<PutSession> for Db {
+    type Result = <PutSession as Message>::Result;
+
+    fn handle(&mut self, msg: PutSession, _: &mut Self::Context) -> Self::Result {
+        let session = msg.0;
+
+        if let Err(error) = self.inner.delete_expired_sessions() {
+            warn!("DB: unable to remove expired sessions: {}", error);
+        }
+
+        self.inner.put_session(&session).map(|_| session).map_err(Error::from)
+    }
+}
+
+///Retrieves active session and its user.
+pub struct GetSession(pub String);
+impl Message for GetSession {
+    type Result = Result<Option<(models::Session, models::User)>, Error>;
+}
+impl Handler<GetSession> for Db {
+    type Result = <GetSession as Message>::Result;
+
+    fn handle(&mut self, msg: GetSession, _: &mut Self::Context) -> Self::Result {
+        self.inner.get_session(&msg.0).map_err(Error::from)
+    }
+}
+
+///Removes session.
+pub struct DelSession(pub String);
+impl Message for DelSession {
+    type Result = Result<usize, Error>;
+}
+impl Handler<DelSession> for Db {
+    type Result = <DelSession as Message>::Result;
+
+    fn handle(&mut self, msg: DelSession, _: &mut Self::Context) -> Self::Result {
+        self.inner.delete_session(&msg.0).map_err(Error::from)
+    }
+}
+
+///Retrieves API token by its hash alongside with its user.
+pub struct GetApiToken(pub String);
+impl Message for GetApiToken {
+    type Result = Result<Option<(models::ApiToken, models::User)>, Error>;
+}
+impl Handler<GetApiToken> for Db {
+    type Result = <GetApiToken as Message>::Result;
+
+    fn handle(&mut self, msg: GetApiToken, _: &mut Self::Context) -> Self::Result {
+        self.inner.get_api_token(&msg.0).map_err(Error::from)
+    }
+}
+
+///Queues hook for moderator's review.
+pub struct PutPendingHook {
+    pub vn: models::Vn,
+    pub version: String,
+    pub code: String,
+    pub author: models::Author,
+    ///Address to notify submitter about outcome.
+    pub contact: Option<String>,
+}
+impl Message for PutPendingHook {
+    type Result = Result<models::PendingHook, Error>;
+}
+impl Handler<PutPendingHook> for Db {
+    type Result = <PutPendingHook as Message>::Result;
+
+    fn handle(&mut self, msg: PutPendingHook, _: &mut Self::Context) -> Self::Result {
+        let PutPendingHook{vn, version, code, author, contact} = msg;
+        let code = hook_code::normalize(&code).map_err(Error::Invalid)?;
+        self.inner.put_pending_hook(&vn, &version, &code, &author, contact.as_ref().map(|contact| contact.as_str())).map_err(Error::from)
+    }
+}
+
+///Retrieves submissions awaiting review.
+pub struct GetPendingHooks;
+impl Message for GetPendingHooks {
+    type Result = Result<Vec<models::PendingHook>, Error>;
+}
+impl Handler<GetPendingHooks> for Db {
+    type Result = <GetPendingHooks as Message>::Result;
+
+    fn handle(&mut self, _: GetPendingHooks, _: &mut Self::Context) -> Self::Result {
+        self.inner.get_pending_hooks().map_err(Error::from)
+    }
+}
+
+///Retrieves latest submissions of author.
+pub struct GetSubmissions {
+    pub author: models::Author,
+    pub limit: i64,
+}
+impl Message for GetSubmissions {
+    type Result = Result<Vec<models::PendingHook>, Error>;
+}
+impl Handler<GetSubmissions> for Db {
+    type Result = <GetSubmissions as Message>::Result;
+
+    fn handle(&mut self, msg: GetSubmissions, _: &mut Self::Context) -> Self::Result {
+        let GetSubmissions{author, limit} = msg;
+        self.inner.get_submissions(&author, limit).map_err(Error::from)
+    }
+}
+
+///Accepts submission, optionally replacing its code.
+pub struct ApprovePendingHook {
+    pub id: u64,
+    pub code: Option<String>,
+    pub reviewer: models::Author,
+}
+impl Message for ApprovePendingHook {
+    type Result = Result<Option<(models::PendingHook, models::HookView)>, Error>;
+}
+impl Handler<ApprovePendingHook> for Db {
+    type Result = <ApprovePendingHook as Message>::Result;
+
+    fn handle(&mut self, msg: ApprovePendingHook, _: &mut Self::Context) -> Self::Result {
+        let ApprovePendingHook{id, code, reviewer} = msg;
+        let code = match code {
+            Some(code) => Some(hook_code::normalize(&code).map_err(Error::Invalid)?),
+            None => None,
+        };
+        self.inner.approve_pending_hook(id as i64, code, &reviewer).map_err(Error::from)
+    }
+}
+
+///Rejects submission.
+pub struct RejectPendingHook {
+    pub id: u64,
+    pub reason: Option<String>,
+    pub reviewer: models::Author,
+}
+impl Message for RejectPendingHook {
+    type Result = Result<Option<models::PendingHook>, Error>;
+}
+impl Handler<RejectPendingHook> for Db {
+    type Result = <RejectPendingHook as Message>::Result;
+
+    fn handle(&mut self, msg: RejectPendingHook, _: &mut Self::Context) -> Self::Result {
+        let RejectPendingHook{id, reason, reviewer} = msg;
+        self.inner.reject_pending_hook(id as i64, reason.as_ref().map(|reason| reason.as_str()), &reviewer).map_err(Error::from)
+    }
+}
+
+///Retrieves reviewed submissions, which authors are not yet notified about.
+///
+///Only submissions made via `frontends` are returned and they are considered notified afterwards.
+pub struct TakeReviewedSubmissions(pub Vec<models::Frontend>);
+impl Message for TakeReviewedSubmissions {
+    type Result = Result<Vec<models::PendingHook>, Error>;
+}
+impl Handler<TakeReviewedSubmissions> for Db {
+    type Result = <TakeReviewedSubmissions as Message>::Result;
+
+    fn handle(&mut self, msg: TakeReviewedSubmissions, _: &mut Self::Context) -> Self::Result {
+        self.inner.take_reviewed_submissions(&msg.0).map_err(Error::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{result, Error};
+
+    #[test]
+    fn should_classify_db_errors() {
+        assert_eq!(Error::from(result::Error::NotFound), Error::NotFound);
+        assert_eq!(Error::from(result::Error::DatabaseError(result::DatabaseErrorKind::UniqueViolation, Box::new("UNIQUE constraint failed: aliases.alias".to_owned()))), Error::Constraint);
+        assert_eq!(Error::from(result::Error::DatabaseError(result::DatabaseErrorKind::ForeignKeyViolation, Box::new("FOREIGN KEY constraint failed".to_owned()))), Error::Constraint);
+    }
+
+    #[test]
+    fn should_classify_sqlite_messages() {
+        //SQLite reports errors other than unique and foreign key violations without specific kind.
+        assert_eq!(Error::from_message("NOT NULL constraint failed: vns.title"), Error::Constraint);
+        assert_eq!(Error::from_message("database is locked"), Error::Busy);
+        assert_eq!(Error::from_message("disk I/O error"), Error::Io);
+        assert_eq!(Error::from_message("unable to open database file"), Error::Io);
+        assert_eq!(Error::from_message("no such table: vns"), Error::Internal);
+    }
+
+    #[test]
+    fn should_not_describe_db_details() {
+        let error = Error::from_message("no such column: vns.original");
+        assert!(!format!("{}", error).contains("vns"));
+
+        let error = Error::Invalid("Executable name is empty".to_owned());
+        assert_eq!(format!("{}", error), "Executable name is empty");
     }
 }
@@ -3,9 +3,13 @@ extern crate futures;
 
 use ::vndb;
 use ::db;
+use ::cache::{self, Cache, Cached};
+use ::backend::{self, Backend};
 
-use ::mem;
 use ::io;
+use ::fmt;
+use ::rc::Rc;
+use ::cell::RefCell;
 
 use vndb::protocol::message::request::get::Type as VndbRequestType;
 use self::futures::{future, Future};
@@ -34,29 +38,78 @@ fn parse_vndb_ref(text: &str) -> Option<(VndbRequestType, u64)> {
     Some((kind, id))
 }
 
+///Caches of VNDB responses.
+struct VndbCache {
+    ///VNs by ID.
+    vns: Cache<u64, vndb::response::results::Vn>,
+    ///VNs by title.
+    titles: Cache<String, vndb::response::results::Vn>,
+    ///Arbitrary objects by kind and ID.
+    objects: Cache<(String, u64), vndb::response::Results>,
+    ///Search results by lowercase title.
+    searches: Cache<String, Vec<vndb::response::results::Vn>>,
+}
+
+impl VndbCache {
+    fn new(config: cache::Config) -> Self {
+        Self {
+            vns: Cache::new(config),
+            titles: Cache::new(config),
+            objects: Cache::new(config),
+            searches: Cache::new(config),
+        }
+    }
+
+    fn stats(&self) -> CacheStats {
+        CacheStats {
+            vns: self.vns.stats(),
+            titles: self.titles.stats(),
+            objects: self.objects.stats(),
+            searches: self.searches.stats(),
+        }
+    }
+}
+
 ///Performs execution of various commands
 ///that involves VNDB or DB
 pub struct Executor {
-    pub vndb: Addr<vndb::Vndb>,
+    pub vndb: backend::Client,
     pub db: Addr<db::Db>,
+    cache: Rc<RefCell<VndbCache>>,
 }
 
 impl Executor {
-    pub fn new(vndb: Addr<vndb::Vndb>, db: Addr<db::Db>) -> Self {
+    pub fn new(vndb: backend::Client, db: Addr<db::Db>) -> Self {
         Self {
             vndb,
-            db
+            db,
+            cache: Rc::new(RefCell::new(VndbCache::new(cache::Config::default())))
         }
     }
 
     ///Starts Executor with default vndb and db actors
     pub fn default_threads(threads: usize) -> Self {
+        Self::with_backend(threads, &Backend::default())
+    }
+
+    ///Starts Executor with default db actor and VNDB client of specified backend.
+    pub fn with_backend(threads: usize, backend: &Backend) -> Self {
         let db: Addr<_> = db::Db::start_threaded(threads);
-        let vndb: Addr<_> = Supervisor::start(|_| vndb::Vndb::new());
 
-        Self {
-            vndb,
-            db
+        Self::new(backend.start(), db)
+    }
+
+    ///Sets size and expiration of VNDB response cache.
+    pub fn cache(mut self, config: cache::Config) -> Self {
+        self.cache = Rc::new(RefCell::new(VndbCache::new(config)));
+        self
+    }
+
+    fn lookup(&self) -> Lookup {
+        Lookup {
+            vndb: self.vndb.clone(),
+            db: self.db.clone(),
+            cache: self.cache.clone(),
         }
     }
 }
@@ -65,20 +118,50 @@ impl Actor for Executor {
     type Context = Context<Self>;
 }
 
+///Handles of VNDB, DB and cache for lookups that take several steps.
+///
+///Lookup owns them instead of borrowing `Executor`, as its futures may outlive actor.
+#[derive(Clone)]
+struct Lookup {
+    vndb: backend::Client,
+    db: Addr<db::Db>,
+    cache: Rc<RefCell<VndbCache>>,
+}
+
 ///Possible errors
 pub enum ResponseError {
     ///Unable to send VNDB request
     BadVndb,
     ///Received bad response from VNDB.
     BadVndbResponse,
+    ///VNDB didn't respond in time.
+    VndbTimeout,
+    ///VNDB throttles requests. Contains seconds until throttling is lifted.
+    Throttled(u64),
+    ///VNDB cannot parse request.
+    VndbParse(String),
+    ///VNDB reports missing argument.
+    VndbMissing(String),
+    ///VNDB rejected request with other error. Contains error's id and message.
+    VndbRejected(String, String),
     ///Too many VNs have been found
     TooMany(usize, String),
     ///Too many matches in DB
     TooManyDb(usize),
     ///Couldn't find such VN.
     UnknownVn,
+    ///Couldn't find such release.
+    UnknownRelease,
     ///Invalid VNDB object ID.
     InvalidVnId(VndbRequestType, u64),
+    ///Couldn't find VNDB object.
+    UnknownObject(VndbRequestType, u64),
+    ///Hook code cannot be parsed.
+    InvalidCode(String),
+    ///Invalid name or hash of executable.
+    InvalidExecutable(String),
+    ///DB failed to process request.
+    Db(db::Error),
     ///Internal error that is not supposed to happen
     Internal(String)
 }
@@ -88,44 +171,75 @@ impl ::fmt::Display for ResponseError {
         match self {
             &ResponseError::BadVndb => write!(f, "Error with VNDB. Forgive me, I cannot execute your request"),
             &ResponseError::BadVndbResponse => write!(f, "Bad VNDB response. Forgive me."),
+            &ResponseError::VndbTimeout => write!(f, "VNDB doesn't respond. Try again later."),
+            &ResponseError::Throttled(ref wait) => write!(f, "VNDB is tired of my requests. Try again in {} seconds.", wait),
+            &ResponseError::VndbParse(ref msg) => write!(f, "VNDB cannot understand my request: {}", msg),
+            &ResponseError::VndbMissing(ref msg) => write!(f, "VNDB says my request is incomplete: {}", msg),
+            &ResponseError::VndbRejected(ref id, ref msg) => write!(f, "VNDB rejected my request ({}): {}", id, msg),
             &ResponseError::UnknownVn => write!(f, "No such VN could be found."),
+            &ResponseError::UnknownRelease => write!(f, "No such release could be found."),
             &ResponseError::TooMany(ref num, ref title) => write!(f, "There are too many hits>='{}'. Try yourself -> https://vndb.org/v/all?sq={}", num, title.replace(" ", "+")),
             &ResponseError::TooManyDb(ref num) => write!(f, "Found '{}' matches in DB. Try a better query.", num),
             &ResponseError::InvalidVnId(ref kind, ref num) => write!(f, "{}{} is not an VN ID", kind.short(), num),
+            &ResponseError::UnknownObject(ref kind, ref num) => write!(f, "{}{} could not be found.", kind.short(), num),
+            &ResponseError::InvalidCode(ref error) => write!(f, "{}", error),
+            &ResponseError::InvalidExecutable(ref error) => write!(f, "{}", error),
+            &ResponseError::Db(ref error) => write!(f, "{}", error),
             &ResponseError::Internal(ref error) => write!(f, "ごめんなさい、エラー: {}", error)
         }
     }
 }
 
+impl ResponseError {
+    ///Maps failure to get response from VNDB.
+    fn from_io(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::TimedOut => ResponseError::VndbTimeout,
+            _ => ResponseError::BadVndb,
+        }
+    }
+
+    ///Maps response that has no results.
+    fn from_vndb(response: vndb::Response) -> Self {
+        match vndb::VndbError::from_response(&response) {
+            Some(vndb::VndbError::Throttled { fullwait, .. }) => ResponseError::Throttled(fullwait.ceil() as u64),
+            Some(vndb::VndbError::Parse(msg)) => ResponseError::VndbParse(msg),
+            Some(vndb::VndbError::Missing(msg)) => ResponseError::VndbMissing(msg),
+            Some(vndb::VndbError::Other { id, msg }) => ResponseError::VndbRejected(id, msg),
+            None => {
+                error!("Unexpected VNDB response on get: {:?}", response);
+                ResponseError::BadVndbResponse
+            }
+        }
+    }
+}
+
 macro_rules! try_vndb_response {
-    ($resp:expr, $return_value:expr) => {{ match $resp {
+    ($resp:expr, $error:ident => $return_value:expr) => {{ match $resp {
         Ok(results) => results,
-        Err(error) => {
-            warn!("Error processing VNDB request: {}", error);
+        Err($error) => {
+            warn!("Error processing VNDB request: {}", $error);
             return $return_value
         }
     }}};
     (Err $resp:expr) => {{
-        try_vndb_response!($resp, Err(ResponseError::BadVndb))
+        try_vndb_response!($resp, error => Err(ResponseError::from_io(error)))
     }};
     ($resp:expr) => {{
-        try_vndb_response!($resp, Box::new(future::err(ResponseError::BadVndb)))
+        try_vndb_response!($resp, error => Box::new(future::err(ResponseError::from_io(error))))
     }};
 }
 
 macro_rules! try_vndb_results {
-    ($resp:expr, $return_value:expr) => {{ match $resp {
+    ($resp:expr, $other:ident => $return_value:expr) => {{ match $resp {
         vndb::Response::Results(result) => result,
-        other => {
-            error!("Unexpected VNDB response on get: {:?}", other);
-            return $return_value
-        }
+        $other => return $return_value
     }}};
     ($resp:expr) => {{
-        try_vndb_results!($resp, Box::new(future::err(ResponseError::BadVndbResponse)))
+        try_vndb_results!($resp, other => Box::new(future::err(ResponseError::from_vndb(other))))
     }};
     (Err $resp:expr) => {{
-        try_vndb_results!($resp, Err(ResponseError::BadVndbResponse))
+        try_vndb_results!($resp, other => Err(ResponseError::from_vndb(other)))
     }}
 }
 
@@ -164,25 +278,7 @@ impl Handler<GetVn> for Executor {
     type Result = GetVnResponseFuture;
 
     fn handle(&mut self, msg: GetVn, _ctx: &mut Self::Context) -> Self::Result {
-        let GetVn {id} = msg;
-
-        let get_vn = vndb::Get::vn_by_id(id);
-        let get_vn = self.vndb.send(get_vn.into()).map_err(|error| {
-            error!("Error processing GetVn: {}", error);
-            ResponseError::Internal(format!("{}", error))
-        }).and_then(|result| {
-            let result = try_vndb_response!(Err result);
-            let result = try_vndb_results!(Err result);
-            let mut result = try_vndb_result_type!(Err result.vn());
-            let mut items = result.items.drain(..);
-
-            match items.next() {
-                Some(vn) => Ok(vn),
-                None => Err(ResponseError::UnknownVn)
-            }
-        });
-
-        Box::new(get_vn)
+        self.lookup().get_vn(msg.id)
     }
 }
 ///Get VN by ID in DB
@@ -200,21 +296,23 @@ impl Handler<GetVnDb> for Executor {
     type Result = GetVnDbResponseFuture;
 
     fn handle(&mut self, msg: GetVnDb, _ctx: &mut Self::Context) -> Self::Result {
-        let id = msg.0.id;
-
-        let get_vn = db::GetVn(id);
-        let get_vn = self.db.send(get_vn).map_err(|error| {
-            error!("Error processing GetVnDb: {}", error);
-            ResponseError::Internal(format!("{}", error))
-        }).and_then(move |result| match result {
-            Ok(result) => Ok(result),
-            Err(error) => Err(ResponseError::Internal(error))
-        });
-
-        Box::new(get_vn)
+        get_vn_db(&self.db, msg.0.id)
     }
 }
 
+fn get_vn_db(db: &Addr<db::Db>, id: u64) -> GetVnDbResponseFuture {
+    let get_vn = db::GetVn(id);
+    let get_vn = db.send(get_vn).map_err(|error| {
+        error!("Error processing GetVnDb: {}", error);
+        ResponseError::Internal(format!("{}", error))
+    }).and_then(move |result| match result {
+        Ok(result) => Ok(result),
+        Err(error) => Err(ResponseError::Db(error))
+    });
+
+    Box::new(get_vn)
+}
+
 ///Get VN by title
 pub struct FindVn {
     title: String
@@ -234,8 +332,217 @@ impl Handler<FindVn> for Executor {
     type Result = FindVnResponseFuture;
 
     fn handle(&mut self, msg: FindVn, _ctx: &mut Self::Context) -> Self::Result {
-        let FindVn {title} = msg;
+        self.lookup().find_vn(msg.title)
+    }
+}
+///Get VN by title
+pub struct FindVnDb(pub FindVn);
+impl FindVnDb {
+    pub fn new(title: String) -> Self {
+        FindVnDb(FindVn::new(title))
+    }
+}
+impl Message for FindVnDb {
+    type Result = Result<Option<db::models::Vn>, ResponseError>;
+}
+type FindVnDbResponseFuture = Box<Future<Item=Option<db::models::Vn>, Error=ResponseError>>;
+impl Handler<FindVnDb> for Executor {
+    type Result = FindVnDbResponseFuture;
+
+    fn handle(&mut self, msg: FindVnDb, _ctx: &mut Self::Context) -> Self::Result {
+        find_vn_db(&self.db, msg.0.title)
+    }
+}
+
+///Finds single VN in DB by title, original title or alias.
+fn find_vn_db(db: &Addr<db::Db>, title: String) -> FindVnDbResponseFuture {
+    let find_vn = db::FindVn(title);
+    let find_vn = db.send(find_vn).map_err(|error| {
+        error!("Error processing FindVnDb: {}", error);
+        ResponseError::Internal(format!("{}", error))
+    }).and_then(move |result| {
+        let mut vns = match result {
+            Ok(vns) => vns,
+            Err(error) => return Err(ResponseError::Db(error)),
+        };
+
+        match vns.len() {
+            0 => Ok(None),
+            1 => Ok(vns.drain(..).next()),
+            num => Err(ResponseError::TooManyDb(num))
+        }
+    });
+
+    Box::new(find_vn)
+}
+
+type LookupVnFuture = Box<Future<Item=db::models::Vn, Error=ResponseError>>;
+///Looks up VN by ID or title only in DB.
+fn lookup_vn_db(db: &Addr<db::Db>, title: String) -> LookupVnFuture {
+    match parse_vndb_ref(&title) {
+        Some((kind, id)) => {
+            if kind.short() != "v" {
+                return Box::new(future::err(ResponseError::InvalidVnId(kind, id)));
+            }
+
+            let get_vn = db.send(db::GetVn(id)).map_err(|error| {
+                error!("Error processing GetVn: {}", error);
+                ResponseError::Internal(format!("{}", error))
+            }).and_then(|result| match result {
+                Ok(Some(vn)) => Ok(vn),
+                Ok(None) => Err(ResponseError::UnknownVn),
+                Err(error) => Err(ResponseError::Db(error))
+            });
+
+            Box::new(get_vn)
+        },
+        None => Box::new(find_vn_db(db, title).and_then(|result| match result {
+            Some(vn) => Ok(vn),
+            None => Err(ResponseError::UnknownVn)
+        }))
+    }
+}
+
+///Stores VN from VNDB alongside with its original title and aliases.
+fn put_vndb_vn(db: &Addr<db::Db>, vn: vndb::response::results::Vn) -> Box<Future<Item=db::models::Vn, Error=ResponseError>> {
+    let aliases = vndb::split_aliases(vn.aliases.as_ref().map(|aliases| aliases.as_str()));
+    let put_vn = db::PutVn { id: vn.id, title: vn.title.unwrap_or_default(), original: vn.original, aliases };
+
+    let put_vn = db.send(put_vn).map_err(|error| {
+        error!("Error processing PutVn: {}", error);
+        ResponseError::Internal(format!("{}", error))
+    }).and_then(|result| result.map_err(ResponseError::Db));
+
+    Box::new(put_vn)
+}
+
+pub struct GetHook(pub String);
+impl Message for GetHook {
+    type Result = Result<db::VnData, ResponseError>;
+}
+type GetHookResponseFuture = Box<Future<Item=db::VnData, Error=ResponseError>>;
+impl Handler<GetHook> for Executor {
+    type Result = GetHookResponseFuture;
+
+    fn handle(&mut self, msg: GetHook, _ctx: &mut Self::Context) -> Self::Result {
+        let title = msg.0;
+
+        match parse_vndb_ref(&title) {
+            Some((kind, id)) => {
+                if kind.short() != "v" {
+                    return Box::new(future::err(ResponseError::InvalidVnId(kind, id)));
+                }
+                let get_vn = db::GetVnData(id);
+                let get_vn = self.db.send(get_vn).map_err(|error| {
+                    error!("Error processing GetVnData: {}", error);
+                    ResponseError::Internal(format!("{}", error))
+                }).and_then(|result| match result {
+                    Ok(Some(result)) => Ok(result),
+                    Ok(None) => Err(ResponseError::UnknownVn),
+                    Err(error) => Err(ResponseError::Db(error))
+                });
+
+                Box::new(get_vn)
+            },
+            None => {
+                let db = self.db.clone();
+                let get_hooks = self.lookup().find_stored_vn(title).and_then(move |vn| {
+                    let get_hooks = db::GetHooks(vn);
+                    db.send(get_hooks).map_err(|error| {
+                        error!("Error processing GetHooks: {}", error);
+                        ResponseError::Internal(format!("{}", error))
+                    }).and_then(|result| result.map_err(ResponseError::Db))
+                });
+
+                Box::new(get_hooks)
+            }
+        }
+    }
+}
+
+///Retrieves release from VNDB.
+fn get_vndb_release(vndb: &backend::Client, id: u64) -> Box<Future<Item=db::models::Release, Error=ResponseError>> {
+    let get_release = vndb::Get::release_by_id(id);
+    let get_release = vndb.send(get_release.into()).map_err(|error| {
+        error!("Error processing GetRelease: {}", error);
+        ResponseError::Internal(format!("{}", error))
+    }).and_then(move |result| {
+        let result = try_vndb_response!(Err result);
+        let result = try_vndb_results!(Err result);
+
+        let item = match result.get("items").and_then(|items| items.get(0)) {
+            Some(item) => item,
+            None => return Err(ResponseError::UnknownRelease)
+        };
+        let list = |field: &str| item.get(field).and_then(|values| values.as_array())
+                                                .map(|values| values.iter().filter_map(|value| value.as_str()).collect::<Vec<_>>().join(","))
+                                                .unwrap_or_default();
+
+        //Release may belong to several VNs, the first one is taken.
+        let vn_id = match item.get("vn").and_then(|vns| vns.get(0)).and_then(|vn| vn.get("id")).and_then(|id| id.as_i64()) {
+            Some(vn_id) => vn_id,
+            None => return Err(ResponseError::UnknownRelease)
+        };
+
+        Ok(db::models::Release {
+            id: id as i64,
+            vn_id,
+            title: item.get("title").and_then(|title| title.as_str()).unwrap_or("").to_owned(),
+            platforms: list("platforms"),
+            languages: list("languages"),
+        })
+    });
+
+    Box::new(get_release)
+}
 
+impl Lookup {
+    ///Gets VN from VNDB by ID.
+    fn get_vn(&self, id: u64) -> GetVnResponseFuture {
+        match self.cache.borrow_mut().vns.get(&id) {
+            Some(Cached::Found(vn)) => return Box::new(future::ok(vn)),
+            Some(Cached::NotFound) => return Box::new(future::err(ResponseError::UnknownVn)),
+            None => (),
+        }
+
+        let cache = self.cache.clone();
+        let get_vn = vndb::Get::vn_by_id(id);
+        let get_vn = self.vndb.send(get_vn.into()).map_err(|error| {
+            error!("Error processing GetVn: {}", error);
+            ResponseError::Internal(format!("{}", error))
+        }).and_then(move |result| {
+            let result = try_vndb_response!(Err result);
+            let result = try_vndb_results!(Err result);
+            let mut result = try_vndb_result_type!(Err result.vn());
+            let mut items = result.items.drain(..);
+
+            match items.next() {
+                Some(vn) => {
+                    cache.borrow_mut().vns.put(id, vn.clone());
+                    Ok(vn)
+                },
+                None => {
+                    cache.borrow_mut().vns.put_not_found(id);
+                    Err(ResponseError::UnknownVn)
+                }
+            }
+        });
+
+        Box::new(get_vn)
+    }
+
+    ///Finds VN on VNDB by title.
+    ///
+    ///Exact match of title is preferred, otherwise search must give single VN.
+    fn find_vn(&self, title: String) -> FindVnResponseFuture {
+        match self.cache.borrow_mut().titles.get(&title) {
+            Some(Cached::Found(vn)) => return Box::new(future::ok(vn)),
+            Some(Cached::NotFound) => return Box::new(future::err(ResponseError::UnknownVn)),
+            None => (),
+        }
+
+        let cache = self.cache.clone();
+        let key = title.clone();
         let get_vn = vndb::Get::vn_by_exact_title(&title);
         let vndb = self.vndb.clone();
         let get_vn = self.vndb.send(get_vn.into()).map_err(|error| {
@@ -272,130 +579,120 @@ impl Handler<FindVn> for Executor {
                     Box::new(search_vn)
                 }
             }
+        }).then(move |result| {
+            let mut cache = cache.borrow_mut();
+            match result {
+                Ok(ref vn) => {
+                    cache.titles.put(key, vn.clone());
+                    cache.vns.put(vn.id, vn.clone());
+                },
+                Err(ResponseError::UnknownVn) => cache.titles.put_not_found(key),
+                Err(_) => (),
+            }
+
+            result
         });
 
         Box::new(get_vn)
     }
-}
-///Get VN by title
-pub struct FindVnDb(pub FindVn);
-impl FindVnDb {
-    pub fn new(title: String) -> Self {
-        FindVnDb(FindVn::new(title))
-    }
-}
-impl Message for FindVnDb {
-    type Result = Result<Option<db::models::Vn>, ResponseError>;
-}
-type FindVnDbResponseFuture = Box<Future<Item=Option<db::models::Vn>, Error=ResponseError>>;
-impl Handler<FindVnDb> for Executor {
-    type Result = FindVnDbResponseFuture;
 
-    fn handle(&mut self, msg: FindVnDb, _ctx: &mut Self::Context) -> Self::Result {
-        let title = msg.0.title;
-
-        let search_vn = db::SearchVn(title);
-        let search_vn = self.db.send(search_vn).map_err(|error| {
-            error!("Error processing FindVnDb: {}", error);
-            ResponseError::Internal(format!("{}", error))
-        }).and_then(move |result| {
-            let mut vns = match result {
-                Ok(vns) => vns,
-                Err(error) => return Err(ResponseError::Internal(error)),
-            };
+    ///Finds VN that is stored in DB, looking up its ID on VNDB if title is not known to DB.
+    fn find_stored_vn(self, title: String) -> LookupVnFuture {
+        let find_vn = find_vn_db(&self.db, title.clone()).and_then(move |result| match result {
+            Some(vn) => future::Either::A(future::ok(vn)),
+            None => {
+                let db = self.db.clone();
+                let find_vn = self.find_vn(title).and_then(move |vn| get_vn_db(&db, vn.id)).and_then(|result| match result {
+                    Some(vn) => Ok(vn),
+                    None => Err(ResponseError::UnknownVn)
+                });
 
-            match vns.len() {
-                0 => Ok(None),
-                1 => Ok(vns.drain(..).next()),
-                num => Err(ResponseError::TooManyDb(num))
+                future::Either::B(find_vn)
             }
         });
 
-        Box::new(search_vn)
+        Box::new(find_vn)
     }
-}
 
-pub struct GetHook(pub String);
-impl Message for GetHook {
-    type Result = Result<db::VnData, ResponseError>;
-}
-type GetHookResponseFuture = Box<Future<Item=db::VnData, Error=ResponseError>>;
-impl Handler<GetHook> for Executor {
-    type Result = GetHookResponseFuture;
+    ///Resolves VN of release, storing both in DB if necessary.
+    fn resolve_release(self, id: u64) -> LookupVnFuture {
+        let get_release = self.db.send(db::GetRelease(id)).map_err(|error| {
+            error!("Error processing GetRelease: {}", error);
+            ResponseError::Internal(format!("{}", error))
+        }).and_then(|result| result.map_err(ResponseError::Db));
 
-    fn handle(&mut self, msg: GetHook, ctx: &mut Self::Context) -> Self::Result {
-        let title = msg.0;
+        let resolve = get_release.and_then(move |release| match release {
+            Some(release) => future::Either::A(self.resolve_vn(format!("v{}", release.vn_id))),
+            None => {
+                let db = self.db.clone();
+                let resolve = get_vndb_release(&self.vndb, id).and_then(move |release| {
+                    self.resolve_vn(format!("v{}", release.vn_id)).and_then(move |vn| {
+                        db.send(db::PutRelease(release)).map_err(|error| {
+                            error!("Error processing PutRelease: {}", error);
+                            ResponseError::Internal(format!("{}", error))
+                        }).and_then(|result| result.map_err(ResponseError::Db)).map(move |_| vn)
+                    })
+                });
 
-        let this: &'static mut Self = unsafe { mem::transmute(self) };
-        let ctx: &'static mut Self::Context = unsafe { mem::transmute(ctx) };
+                future::Either::B(resolve)
+            }
+        });
 
-        let get_vn = match parse_vndb_ref(&title) {
+        Box::new(resolve)
+    }
+
+    ///Resolves VN by ID, release ID or title, storing it in DB if necessary.
+    fn resolve_vn(self, title: String) -> LookupVnFuture {
+        match parse_vndb_ref(&title) {
             Some((kind, id)) => {
+                if kind.short() == "r" {
+                    return self.resolve_release(id);
+                }
                 if kind.short() != "v" {
                     return Box::new(future::err(ResponseError::InvalidVnId(kind, id)));
                 }
-                let get_vn = db::GetVnData(id);
-                let get_vn = this.db.send(get_vn).map_err(|error| {
-                    error!("Error processing GetVnData: {}", error);
-                    ResponseError::Internal(format!("{}", error))
-                }).and_then(|result| match result {
-                    Ok(Some(result)) => Ok(result),
-                    Ok(None) => Err(ResponseError::UnknownVn),
-                    Err(error) => Err(ResponseError::Internal(error))
-                }).then(|result| result);
 
-                future::Either::A(get_vn)
-            },
-            None => {
-                let get_vn = FindVnDb::new(title.clone());
-                let get_vn = this.handle(get_vn, ctx).and_then(move |result| match result {
-                    Some(vn) => future::Either::A(future::ok((vn, this))),
+                let get_vn = get_vn_db(&self.db, id).and_then(move |result| match result {
+                    Some(vn) => future::Either::A(future::ok(vn)),
                     None => {
-                        let get_vn = FindVn::new(title);
-                        let get_vn = this.handle(get_vn, ctx).and_then(move |result| {
-                            let get_vn = GetVnDb::new(result.id);
-                            let get_vn = this.handle(get_vn, ctx).and_then(|result| match result {
-                                Some(vn) => Ok((vn, this)),
-                                None => Err(ResponseError::UnknownVn)
-                            });
-
-                            get_vn
-                        });
-
-                        future::Either::B(get_vn)
+                        let db = self.db.clone();
+                        future::Either::B(self.get_vn(id).and_then(move |vn| put_vndb_vn(&db, vn)))
                     }
-                }).and_then(|(vn, this)| {
-                    let get_hooks = db::GetHooks(vn);
-                    let get_hooks = this.db.send(get_hooks).map_err(|error| {
-                        error!("Error processing GetHooks: {}", error);
-                        ResponseError::Internal(format!("{}", error))
-                    }).map(|result| result.map_err(|error| {
-                        error!("Error processing getting hooks: {}", error);
-                        ResponseError::Internal(format!("{}", error))
-                    })).flatten();
+                });
 
-                    Box::new(get_hooks)
+                Box::new(get_vn)
+            },
+            None => {
+                //Aliases are known only to DB, so it is checked first.
+                let get_vn = find_vn_db(&self.db, title.clone()).then(move |result| match result {
+                    Ok(Some(vn)) => future::Either::A(future::ok(vn)),
+                    Ok(None) | Err(ResponseError::TooManyDb(_)) => {
+                        let db = self.db.clone();
+                        let get_vn = self.find_vn(title).and_then(move |vn| put_vndb_vn(&db, vn));
+                        future::Either::B(future::Either::A(get_vn))
+                    },
+                    Err(error) => future::Either::B(future::Either::B(future::err(error))),
                 });
 
-                future::Either::B(get_vn)
+                Box::new(get_vn)
             }
-        };
-
-        Box::new(get_vn)
+        }
     }
 }
 
 pub struct SetHook {
     title: String,
     version: String,
-    code: String
+    code: String,
+    author: db::models::Author,
 }
 impl SetHook {
-    pub fn new(title: String, version: String, code: String) -> Self {
+    pub fn new(title: String, version: String, code: String, author: db::models::Author) -> Self {
         Self {
             title,
             version,
-            code
+            code,
+            author
         }
     }
 }
@@ -406,79 +703,86 @@ type SetHookResponseFuture = Box<Future<Item=db::models::HookView, Error=Respons
 impl Handler<SetHook> for Executor {
     type Result = SetHookResponseFuture;
 
-    fn handle(&mut self, msg: SetHook, ctx: &mut Self::Context) -> Self::Result {
-        let SetHook{title, version, code} = msg;
+    fn handle(&mut self, msg: SetHook, _ctx: &mut Self::Context) -> Self::Result {
+        let SetHook{title, version, code, author} = msg;
+        let code = match db::hook_code::normalize(&code) {
+            Ok(code) => code,
+            Err(error) => return Box::new(future::err(ResponseError::InvalidCode(error))),
+        };
 
-        let this: &'static mut Self = unsafe { mem::transmute(self) };
-        let ctx: &'static mut Self::Context = unsafe { mem::transmute(ctx) };
+        let db = self.db.clone();
+        let put_hook = self.lookup().resolve_vn(title).and_then(move |vn| {
+            let put_hook = db::PutHook { vn, version, code, author };
+            db.send(put_hook).map_err(|error| {
+                error!("Error processing PutHook: {}", error);
+                ResponseError::Internal(format!("{}", error))
+            }).and_then(|result| result.map_err(ResponseError::Db))
+        });
 
-        let get_vn = match parse_vndb_ref(&title) {
-            Some((kind, id)) => {
-                if kind.short() != "v" {
-                    return Box::new(future::err(ResponseError::InvalidVnId(kind, id)));
-                }
-                let get_vn = GetVnDb::new(id);
-                let get_vn = this.handle(get_vn, ctx).and_then(move |result| match result {
-                    Some(vn) => future::Either::A(future::ok((vn, this))),
-                    None => {
-                        let get_vn = GetVn::new(id);
-                        let get_vn = this.handle(get_vn, ctx).and_then(move |vn| {
-                            let put_vn = db::PutVn { id: vn.id, title: vn.title.unwrap() };
-                            this.db.send(put_vn).map_err(|error| {
-                                error!("Error processing PutVn: {}", error);
-                                ResponseError::Internal(format!("{}", error))
-                            }).map(|result| result.map_err(|error| {
-                                error!("Error performing PutVn: {}", error);
-                                ResponseError::Internal(format!("{}", error))
-                            })).flatten().map(|vn| (vn, this))
-                        });
-                        future::Either::B(get_vn)
-                    }
-                });
+        Box::new(put_hook)
+    }
+}
 
-                future::Either::A(get_vn)
-            },
-            None => {
-                let get_vn = FindVn::new(title);
-                let get_vn = this.handle(get_vn, ctx).and_then(move |vn| {
-                    let put_vn = db::PutVn { id: vn.id, title: vn.title.unwrap() };
-                    this.db.send(put_vn).map_err(|error| {
-                        error!("Error processing PutVn: {}", error);
-                        ResponseError::Internal(format!("{}", error))
-                    }).map(|result| result.map_err(|error| {
-                        error!("Error performing PutVn: {}", error);
-                        ResponseError::Internal(format!("{}", error))
-                    })).flatten().map(|vn| (vn, this))
-                });
+///Queues hook for moderator's review instead of storing it.
+pub struct SubmitHook {
+    title: String,
+    version: String,
+    code: String,
+    author: db::models::Author,
+    contact: Option<String>,
+}
+impl SubmitHook {
+    ///Creates submission.
+    ///
+    ///`contact` is used to notify submitter once submission is reviewed.
+    pub fn new(title: String, version: String, code: String, author: db::models::Author, contact: Option<String>) -> Self {
+        Self {
+            title,
+            version,
+            code,
+            author,
+            contact
+        }
+    }
+}
+impl Message for SubmitHook {
+    type Result = Result<db::models::PendingHook, ResponseError>;
+}
+type SubmitHookResponseFuture = Box<Future<Item=db::models::PendingHook, Error=ResponseError>>;
+impl Handler<SubmitHook> for Executor {
+    type Result = SubmitHookResponseFuture;
+
+    fn handle(&mut self, msg: SubmitHook, _ctx: &mut Self::Context) -> Self::Result {
+        let SubmitHook{title, version, code, author, contact} = msg;
+        let code = match db::hook_code::normalize(&code) {
+            Ok(code) => code,
+            Err(error) => return Box::new(future::err(ResponseError::InvalidCode(error))),
+        };
 
-                future::Either::B(get_vn)
-            }
-        }.and_then(|(vn, this)| -> SetHookResponseFuture {
-            let put_hook = db::PutHook { vn, version, code };
-            let put_hook = this.db.send(put_hook).map_err(|error| {
-                error!("Error processing PutHook: {}", error);
+        let db = self.db.clone();
+        let put_pending = self.lookup().resolve_vn(title).and_then(move |vn| {
+            let put_pending = db::PutPendingHook { vn, version, code, author, contact };
+            db.send(put_pending).map_err(|error| {
+                error!("Error processing PutPendingHook: {}", error);
                 ResponseError::Internal(format!("{}", error))
-            }).map(|result| result.map_err(|error| {
-                error!("DB error: {}", error);
-                ResponseError::Internal(error)
-            })).flatten();
-
-            Box::new(put_hook)
+            }).and_then(|result| result.map_err(ResponseError::Db))
         });
 
-        Box::new(get_vn)
+        Box::new(put_pending)
     }
 }
 
 pub struct DelHook {
     title: String,
-    version: String
+    version: String,
+    author: db::models::Author,
 }
 impl DelHook {
-    pub fn new(title: String, version: String) -> Self {
+    pub fn new(title: String, version: String, author: db::models::Author) -> Self {
         Self {
             title,
-            version
+            version,
+            author
         }
     }
 }
@@ -489,67 +793,48 @@ type DelHookResponseFuture = Box<Future<Item=usize, Error=ResponseError>>;
 impl Handler<DelHook> for Executor {
     type Result = DelHookResponseFuture;
 
-    fn handle(&mut self, msg: DelHook, ctx: &mut Self::Context) -> Self::Result {
-        let DelHook{title, version} = msg;
-
-        let this: &'static mut Self = unsafe { mem::transmute(self) };
-        let ctx: &'static mut Self::Context = unsafe { mem::transmute(ctx) };
+    fn handle(&mut self, msg: DelHook, _ctx: &mut Self::Context) -> Self::Result {
+        let DelHook{title, version, author} = msg;
 
         let get_vn = match parse_vndb_ref(&title) {
             Some((kind, id)) => {
                 if kind.short() != "v" {
                     return Box::new(future::err(ResponseError::InvalidVnId(kind, id)));
                 }
-                let get_vn = db::GetVn(id);
-                let get_vn = this.db.send(get_vn).map_err(|error| {
-                    error!("Error processing GetVn: {}", error);
-                    ResponseError::Internal(format!("{}", error))
-                }).and_then(|result| match result {
-                    Ok(Some(result)) => Ok((result, this)),
-                    Ok(None) => Err(ResponseError::UnknownVn),
-                    Err(error) => Err(ResponseError::Internal(error))
-                }).then(|result| result);
 
-                future::Either::A(get_vn)
+                lookup_vn_db(&self.db, title)
             },
-            None => {
-                let get_vn = FindVnDb::new(title.clone());
-                let get_vn = this.handle(get_vn, ctx).and_then(move |result| match result {
-                    Some(vn) => future::Either::A(future::ok((vn, this))),
-                    None => {
-                        let get_vn = FindVn::new(title);
-                        let get_vn = this.handle(get_vn, ctx).and_then(move |result| {
-                            let get_vn = GetVnDb::new(result.id);
-                            let get_vn = this.handle(get_vn, ctx).and_then(|result| match result {
-                                Some(vn) => Ok((vn, this)),
-                                None => Err(ResponseError::UnknownVn)
-                            });
-
-                            get_vn
-                        });
-
-                        future::Either::B(get_vn)
-                    }
-                });
+            None => self.lookup().find_stored_vn(title)
+        };
 
-                future::Either::B(get_vn)
-            }
-        }.and_then(move |(vn, this)| {
-            let del_hook = db::DelHook { vn, version };
-            this.db.send(del_hook).map_err(|error| {
+        let db = self.db.clone();
+        let del_hook = get_vn.and_then(move |vn| {
+            let del_hook = db::DelHook { vn, version, author };
+            db.send(del_hook).map_err(|error| {
                 error!("Error processing DelHook: {}", error);
                 ResponseError::Internal(format!("{}", error))
             }).and_then(|result| match result {
                 Ok(num) => Ok(num),
-                Err(error) => Err(ResponseError::Internal(error))
+                Err(error) => Err(ResponseError::Db(error))
             })
         });
 
-        Box::new(get_vn)
+        Box::new(del_hook)
     }
 }
 
-pub struct DelVn(pub String);
+pub struct DelVn {
+    title: String,
+    author: db::models::Author,
+}
+impl DelVn {
+    pub fn new(title: String, author: db::models::Author) -> Self {
+        Self {
+            title,
+            author
+        }
+    }
+}
 impl Message for DelVn {
     type Result = Result<usize, ResponseError>;
 }
@@ -557,42 +842,37 @@ type DelVnResponseFuture = Box<Future<Item=usize, Error=ResponseError>>;
 impl Handler<DelVn> for Executor {
     type Result = DelVnResponseFuture;
 
-    fn handle(&mut self, msg: DelVn, ctx: &mut Self::Context) -> Self::Result {
-        let title = msg.0;
-
-        let this: &'static mut Self = unsafe { mem::transmute(self) };
-        let ctx: &'static mut Self::Context = unsafe { mem::transmute(ctx) };
+    fn handle(&mut self, msg: DelVn, _ctx: &mut Self::Context) -> Self::Result {
+        let DelVn{title, author} = msg;
 
-        let del_vn = match parse_vndb_ref(&title) {
+        let get_id = match parse_vndb_ref(&title) {
             Some((kind, id)) => {
                 if kind.short() != "v" {
                     return Box::new(future::err(ResponseError::InvalidVnId(kind, id)));
                 }
 
-                future::Either::A(future::ok((id as u64, this)))
+                future::Either::A(future::ok(id))
             },
             None => {
-                let get_vn = FindVnDb::new(title.clone());
-                let get_vn = this.handle(get_vn, ctx).and_then(move |result| match result {
-                    Some(vn) => future::Either::A(future::ok((vn.id as u64, this))),
-                    None => {
-                        let get_vn = FindVn::new(title);
-                        let get_vn = this.handle(get_vn, ctx).and_then(move |result| Ok((result.id, this)));
-
-                        future::Either::B(get_vn)
-                    }
+                let lookup = self.lookup();
+                let get_id = find_vn_db(&self.db, title.clone()).and_then(move |result| match result {
+                    Some(vn) => future::Either::A(future::ok(vn.id as u64)),
+                    None => future::Either::B(lookup.find_vn(title).map(|vn| vn.id))
                 });
 
-                future::Either::B(get_vn)
+                future::Either::B(get_id)
             }
-        }.and_then(|(id, this)| {
-            let del_vn = db::DelVnData(id);
-            this.db.send(del_vn).map_err(|error| {
+        };
+
+        let db = self.db.clone();
+        let del_vn = get_id.and_then(move |id| {
+            let del_vn = db::DelVnData(id, author);
+            db.send(del_vn).map_err(|error| {
                 error!("Error processing DelVnData: {}", error);
                 ResponseError::Internal(format!("{}", error))
             }).and_then(|result| match result {
                 Ok(num) => Ok(num),
-                Err(error) => Err(ResponseError::Internal(error))
+                Err(error) => Err(ResponseError::Db(error))
             })
         });
 
@@ -600,6 +880,319 @@ impl Handler<DelVn> for Executor {
     }
 }
 
+///Retrieves latest hook revisions of VN.
+///
+///VN is looked up only in DB, so history of removed VN is available only by its ID.
+pub struct GetHistory(pub String);
+impl Message for GetHistory {
+    type Result = Result<Vec<db::models::HookRevision>, ResponseError>;
+}
+type GetHistoryResponseFuture = Box<Future<Item=Vec<db::models::HookRevision>, Error=ResponseError>>;
+impl Handler<GetHistory> for Executor {
+    type Result = GetHistoryResponseFuture;
+
+    fn handle(&mut self, msg: GetHistory, _ctx: &mut Self::Context) -> Self::Result {
+        const LIMIT: i64 = 5;
+        let title = msg.0;
+
+        let get_id = match parse_vndb_ref(&title) {
+            Some((kind, id)) => {
+                if kind.short() != "v" {
+                    return Box::new(future::err(ResponseError::InvalidVnId(kind, id)));
+                }
+
+                future::Either::A(future::ok(id))
+            },
+            None => future::Either::B(find_vn_db(&self.db, title).and_then(|result| match result {
+                Some(vn) => Ok(vn.id as u64),
+                None => Err(ResponseError::UnknownVn)
+            }))
+        };
+
+        let db = self.db.clone();
+        let get_history = get_id.and_then(move |id| {
+            let get_revisions = db::GetRevisions { vn: Some(id), limit: LIMIT };
+            db.send(get_revisions).map_err(|error| {
+                error!("Error processing GetRevisions: {}", error);
+                ResponseError::Internal(format!("{}", error))
+            }).and_then(|result| result.map_err(ResponseError::Db))
+        });
+
+        Box::new(get_history)
+    }
+}
+
+///Retrieves aliases of VN.
+///
+///VN is looked up only in DB.
+pub struct GetAliases(pub String);
+impl Message for GetAliases {
+    type Result = Result<(db::models::Vn, Vec<db::models::Alias>), ResponseError>;
+}
+type GetAliasesResponseFuture = Box<Future<Item=(db::models::Vn, Vec<db::models::Alias>), Error=ResponseError>>;
+impl Handler<GetAliases> for Executor {
+    type Result = GetAliasesResponseFuture;
+
+    fn handle(&mut self, msg: GetAliases, _ctx: &mut Self::Context) -> Self::Result {
+        let db = self.db.clone();
+        let get_aliases = lookup_vn_db(&self.db, msg.0).and_then(move |vn| {
+            db.send(db::GetAliases(vn.clone())).map_err(|error| {
+                error!("Error processing GetAliases: {}", error);
+                ResponseError::Internal(format!("{}", error))
+            }).and_then(|result| result.map(|aliases| (vn, aliases)).map_err(ResponseError::Db))
+        });
+
+        Box::new(get_aliases)
+    }
+}
+
+///Adds alias to VN, storing VN if necessary.
+pub struct AddAlias {
+    title: String,
+    alias: String,
+    author: db::models::Author,
+}
+impl AddAlias {
+    pub fn new(title: String, alias: String, author: db::models::Author) -> Self {
+        Self {
+            title,
+            alias,
+            author
+        }
+    }
+}
+impl Message for AddAlias {
+    type Result = Result<db::models::Alias, ResponseError>;
+}
+type AddAliasResponseFuture = Box<Future<Item=db::models::Alias, Error=ResponseError>>;
+impl Handler<AddAlias> for Executor {
+    type Result = AddAliasResponseFuture;
+
+    fn handle(&mut self, msg: AddAlias, _ctx: &mut Self::Context) -> Self::Result {
+        let AddAlias{title, alias, author} = msg;
+
+        let db = self.db.clone();
+        let put_alias = self.lookup().resolve_vn(title).and_then(move |vn| {
+            let put_alias = db::PutAlias { vn, alias, author };
+            db.send(put_alias).map_err(|error| {
+                error!("Error processing PutAlias: {}", error);
+                ResponseError::Internal(format!("{}", error))
+            }).and_then(|result| result.map_err(ResponseError::Db))
+        });
+
+        Box::new(put_alias)
+    }
+}
+
+///Removes alias of VN.
+pub struct DelAlias {
+    title: String,
+    alias: String,
+    author: db::models::Author,
+}
+impl DelAlias {
+    pub fn new(title: String, alias: String, author: db::models::Author) -> Self {
+        Self {
+            title,
+            alias,
+            author
+        }
+    }
+}
+impl Message for DelAlias {
+    type Result = Result<usize, ResponseError>;
+}
+type DelAliasResponseFuture = Box<Future<Item=usize, Error=ResponseError>>;
+impl Handler<DelAlias> for Executor {
+    type Result = DelAliasResponseFuture;
+
+    fn handle(&mut self, msg: DelAlias, _ctx: &mut Self::Context) -> Self::Result {
+        let DelAlias{title, alias, author} = msg;
+
+        let db = self.db.clone();
+        let del_alias = lookup_vn_db(&self.db, title).and_then(move |vn| {
+            let del_alias = db::DelAlias { vn, alias, author };
+            db.send(del_alias).map_err(|error| {
+                error!("Error processing DelAlias: {}", error);
+                ResponseError::Internal(format!("{}", error))
+            }).and_then(|result| result.map_err(ResponseError::Db))
+        });
+
+        Box::new(del_alias)
+    }
+}
+
+///Validates name and optional hash of executable.
+fn check_executable(name: &str, hash: Option<&String>) -> Result<(), ResponseError> {
+    db::models::executable_name(name).map_err(ResponseError::InvalidExecutable)?;
+
+    match hash {
+        Some(hash) => db::models::file_hash(hash).map(|_| ()).map_err(ResponseError::InvalidExecutable),
+        None => Ok(())
+    }
+}
+
+///Registers executable of VN, storing VN if necessary.
+///
+///If title refers to release, executable is linked to it.
+pub struct AddExecutable {
+    title: String,
+    name: String,
+    hash: Option<String>,
+    author: db::models::Author,
+}
+impl AddExecutable {
+    pub fn new(title: String, name: String, hash: Option<String>, author: db::models::Author) -> Self {
+        Self {
+            title,
+            name,
+            hash,
+            author
+        }
+    }
+}
+impl Message for AddExecutable {
+    type Result = Result<db::models::Executable, ResponseError>;
+}
+type AddExecutableResponseFuture = Box<Future<Item=db::models::Executable, Error=ResponseError>>;
+impl Handler<AddExecutable> for Executor {
+    type Result = AddExecutableResponseFuture;
+
+    fn handle(&mut self, msg: AddExecutable, _ctx: &mut Self::Context) -> Self::Result {
+        let AddExecutable{title, name, hash, author} = msg;
+
+        if let Err(error) = check_executable(&name, hash.as_ref()) {
+            return Box::new(future::err(error));
+        }
+
+        let release_id = db::models::release_ref(&title);
+
+        let db = self.db.clone();
+        let put_executable = self.lookup().resolve_vn(title).and_then(move |vn| {
+            let put_executable = db::PutExecutable { vn, name, hash, release_id, author };
+            db.send(put_executable).map_err(|error| {
+                error!("Error processing PutExecutable: {}", error);
+                ResponseError::Internal(format!("{}", error))
+            }).and_then(|result| result.map_err(ResponseError::Db))
+        });
+
+        Box::new(put_executable)
+    }
+}
+
+///Removes executable of VN.
+pub struct DelExecutable {
+    title: String,
+    name: String,
+    hash: Option<String>,
+    author: db::models::Author,
+}
+impl DelExecutable {
+    pub fn new(title: String, name: String, hash: Option<String>, author: db::models::Author) -> Self {
+        Self {
+            title,
+            name,
+            hash,
+            author
+        }
+    }
+}
+impl Message for DelExecutable {
+    type Result = Result<usize, ResponseError>;
+}
+type DelExecutableResponseFuture = Box<Future<Item=usize, Error=ResponseError>>;
+impl Handler<DelExecutable> for Executor {
+    type Result = DelExecutableResponseFuture;
+
+    fn handle(&mut self, msg: DelExecutable, _ctx: &mut Self::Context) -> Self::Result {
+        let DelExecutable{title, name, hash, author} = msg;
+
+        if let Err(error) = check_executable(&name, hash.as_ref()) {
+            return Box::new(future::err(error));
+        }
+
+        let db = self.db.clone();
+        let del_executable = lookup_vn_db(&self.db, title).and_then(move |vn| {
+            let del_executable = db::DelExecutable { vn, name, hash, author };
+            db.send(del_executable).map_err(|error| {
+                error!("Error processing DelExecutable: {}", error);
+                ResponseError::Internal(format!("{}", error))
+            }).and_then(|result| result.map_err(ResponseError::Db))
+        });
+
+        Box::new(del_executable)
+    }
+}
+
+///Looks up VNs with their hooks by executable.
+pub struct FindExecutable {
+    name: String,
+    hash: Option<String>,
+}
+impl FindExecutable {
+    pub fn new(name: String, hash: Option<String>) -> Self {
+        Self {
+            name,
+            hash
+        }
+    }
+}
+impl Message for FindExecutable {
+    type Result = Result<Vec<db::ExecutableData>, ResponseError>;
+}
+type FindExecutableResponseFuture = Box<Future<Item=Vec<db::ExecutableData>, Error=ResponseError>>;
+impl Handler<FindExecutable> for Executor {
+    type Result = FindExecutableResponseFuture;
+
+    fn handle(&mut self, msg: FindExecutable, _ctx: &mut Self::Context) -> Self::Result {
+        let FindExecutable{name, hash} = msg;
+
+        if let Err(error) = check_executable(&name, hash.as_ref()) {
+            return Box::new(future::err(error));
+        }
+
+        let find = self.db.send(db::FindExecutable { name, hash }).map_err(|error| {
+            error!("Error processing FindExecutable: {}", error);
+            ResponseError::Internal(format!("{}", error))
+        }).and_then(|result| result.map_err(ResponseError::Db));
+
+        Box::new(find)
+    }
+}
+
+///Restores hook to the state before revision.
+pub struct RestoreRevision {
+    id: u64,
+    author: db::models::Author,
+}
+impl RestoreRevision {
+    pub fn new(id: u64, author: db::models::Author) -> Self {
+        Self {
+            id,
+            author
+        }
+    }
+}
+impl Message for RestoreRevision {
+    type Result = Result<db::Restore, ResponseError>;
+}
+type RestoreRevisionResponseFuture = Box<Future<Item=db::Restore, Error=ResponseError>>;
+impl Handler<RestoreRevision> for Executor {
+    type Result = RestoreRevisionResponseFuture;
+
+    fn handle(&mut self, msg: RestoreRevision, _ctx: &mut Self::Context) -> Self::Result {
+        let RestoreRevision{id, author} = msg;
+
+        let restore = db::RestoreRevision { id, author };
+        let restore = self.db.send(restore).map_err(|error| {
+            error!("Error processing RestoreRevision: {}", error);
+            ResponseError::Internal(format!("{}", error))
+        }).and_then(|result| result.map_err(ResponseError::Db));
+
+        Box::new(restore)
+    }
+}
+
 pub struct GetVndbObject {
     id: u64,
     kind: VndbRequestType
@@ -621,32 +1214,49 @@ impl Handler<GetVndbObject> for Executor {
 
     fn handle(&mut self, msg: GetVndbObject, _ctx: &mut Self::Context) -> Self::Result {
         let GetVndbObject {id, kind} = msg;
+        let key = (kind.short().to_owned(), id);
+
+        match self.cache.borrow_mut().objects.get(&key) {
+            Some(Cached::Found(result)) => return Box::new(future::ok(result)),
+            Some(Cached::NotFound) => return Box::new(future::err(ResponseError::UnknownObject(kind, id))),
+            None => (),
+        }
 
+        let cache = self.cache.clone();
         let get_ref = vndb::Get::get_by_id(kind.clone(), id);
         let get_ref = self.vndb.send(get_ref.into()).map_err(|error| {
             error!("Error processing GetVndbObject: {}", error);
             ResponseError::Internal(format!("{}", error))
-        }).and_then(|result| {
+        }).and_then(move |result| {
             let result = try_vndb_response!(Err result);
             let result = try_vndb_results!(Err result);
 
-            Ok(result)
+            match result.get("items").and_then(|items| items.get(0)).is_some() {
+                true => {
+                    cache.borrow_mut().objects.put(key, result.clone());
+                    Ok(result)
+                },
+                false => {
+                    cache.borrow_mut().objects.put_not_found(key);
+                    Err(ResponseError::UnknownObject(kind, id))
+                }
+            }
         });
 
         Box::new(get_ref)
     }
 }
 
-type OngoingVndbRequest = actix::dev::Request<vndb::Vndb, vndb::Request>;
+type OngoingVndbRequest = actix::dev::RecipientRequest<vndb::Request>;
 pub struct SearchVnFutureResult {
     page: u32,
     title: String,
     result: Option<Vec<vndb::response::results::Vn>>,
-    vndb: Addr<vndb::Vndb>,
+    vndb: backend::Client,
 }
 
 impl SearchVnFutureResult {
-    fn new(title: String, vndb: Addr<vndb::Vndb>) -> Self {
+    fn new(title: String, vndb: backend::Client) -> Self {
         Self {
             page: 1,
             title,
@@ -709,6 +1319,15 @@ impl Handler<SearchVn> for Executor {
 
     fn handle(&mut self, msg: SearchVn, _ctx: &mut Self::Context) -> Self::Result {
         let SearchVn {title} = msg;
+        let key = title.to_lowercase();
+
+        match self.cache.borrow_mut().searches.get(&key) {
+            Some(Cached::Found(vns)) => return Box::new(future::ok(vns)),
+            Some(Cached::NotFound) => return Box::new(future::ok(Vec::new())),
+            None => (),
+        }
+
+        let cache = self.cache.clone();
         let search_vn = SearchVnFutureResult::new(title, self.vndb.clone());
         let search_vn = future::loop_fn(search_vn, |mut search_vn| {
             search_vn.send_request().map_err(|error| {
@@ -719,12 +1338,64 @@ impl Handler<SearchVn> for Executor {
                 Ok(None) => Ok(future::Loop::Continue(search_vn)),
                 Err(error) => Err(error)
             })
+        }).map(move |vns| {
+            match vns.len() {
+                0 => cache.borrow_mut().searches.put_not_found(key),
+                _ => cache.borrow_mut().searches.put(key, vns.clone()),
+            }
+
+            vns
         });
 
         Box::new(search_vn)
     }
 }
 
+///Usage of VNDB response cache.
+pub struct CacheStats {
+    ///Lookups of VNs by ID.
+    pub vns: cache::Stats,
+    ///Lookups of VNs by title.
+    pub titles: cache::Stats,
+    ///Lookups of VNDB objects by reference.
+    pub objects: cache::Stats,
+    pub searches: cache::Stats,
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "VN: {} | Title: {} | Reference: {} | Search: {}", self.vns, self.titles, self.objects, self.searches)
+    }
+}
+
+///Retrieves usage of VNDB response cache.
+pub struct GetCacheStats;
+impl Message for GetCacheStats {
+    type Result = CacheStats;
+}
+impl Handler<GetCacheStats> for Executor {
+    type Result = MessageResult<GetCacheStats>;
+
+    fn handle(&mut self, _: GetCacheStats, _ctx: &mut Self::Context) -> Self::Result {
+        MessageResult(self.cache.borrow().stats())
+    }
+}
+
+///Stops executor.
+///
+///Requests that are already handled are still completed, as their futures don't borrow executor.
+pub struct Stop;
+impl Message for Stop {
+    type Result = ();
+}
+impl Handler<Stop> for Executor {
+    type Result = ();
+
+    fn handle(&mut self, _: Stop, ctx: &mut Self::Context) -> Self::Result {
+        ctx.stop();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
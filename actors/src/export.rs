@@ -0,0 +1,252 @@
+//!Export of hooks in formats that text hookers can load.
+//!
+//!Textractor and ITHVNR identify games by executable, which is taken from module of hook code
+//!or from executables registered for VN.
+//!Hooks without known executable are listed under VN's title instead.
+
+use ::db::{hook_code, models, VnData};
+
+use ::fmt::Write;
+use ::collections::BTreeMap;
+
+///Supported export formats.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Json,
+    Csv,
+    ///Textractor's `SavedHooks.txt`.
+    Textractor,
+    ///ITHVNR's profile.
+    Ithvnr,
+}
+
+pub const FORMATS: &'static [Format] = &[Format::Textractor, Format::Ithvnr, Format::Json, Format::Csv];
+
+impl Format {
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            &Format::Json => "hooks.json",
+            &Format::Csv => "hooks.csv",
+            &Format::Textractor => "SavedHooks.txt",
+            &Format::Ithvnr => "ITH_Profile.xml",
+        }
+    }
+
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        FORMATS.iter().find(|format| format.file_name() == name).cloned()
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            &Format::Json => "application/json",
+            &Format::Csv => "text/csv; charset=utf-8",
+            &Format::Textractor => "text/plain; charset=utf-8",
+            &Format::Ithvnr => "application/xml; charset=utf-8",
+        }
+    }
+
+    ///Returns path of export on web server.
+    ///
+    ///If `vn` is not specified, export of whole DB is assumed.
+    pub fn path(&self, vn: Option<i64>) -> String {
+        match vn {
+            Some(vn) => format!("/download/vn/{}/{}", vn, self.file_name()),
+            None => format!("/download/hooks/{}", self.file_name()),
+        }
+    }
+}
+
+///Determines executable that hook is meant for.
+///
+///Module of hook code is preferred, then executable registered for hook's release or for VN.
+fn executable(vn: &VnData, hook: &models::Hook) -> String {
+    let module = hook.code.split_whitespace()
+                          .filter_map(|code| hook_code::HookCode::parse(code).ok())
+                          .filter_map(|code| code.module)
+                          .find(|module| module.to_lowercase().ends_with(".exe"));
+
+    let registered = || vn.executables.iter().find(|executable| executable.release_id.is_some() && executable.release_id == hook.release_id)
+                                             .or_else(|| vn.executables.iter().find(|executable| executable.release_id.is_none()))
+                                             .map(|executable| executable.name.clone());
+
+    module.or_else(registered).unwrap_or_else(|| vn.data.title.clone())
+}
+
+///Groups hook codes by executable, preserving order of VNs.
+fn by_executable(data: &[VnData]) -> Vec<(String, Vec<(&models::Vn, &models::Hook)>)> {
+    let mut result: Vec<(String, Vec<(&models::Vn, &models::Hook)>)> = Vec::new();
+    let mut indexes: BTreeMap<String, usize> = BTreeMap::new();
+
+    for vn in data.iter() {
+        for hook in vn.hooks.iter() {
+            let executable = executable(vn, hook);
+            //Windows file names are case insensitive.
+            let key = executable.to_lowercase();
+
+            match indexes.get(&key).cloned() {
+                Some(idx) => result[idx].1.push((&vn.data, hook)),
+                None => {
+                    indexes.insert(key, result.len());
+                    result.push((executable, vec![(&vn.data, hook)]));
+                }
+            }
+        }
+    }
+
+    result
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        match ch {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            ch => result.push(ch),
+        }
+    }
+
+    result
+}
+
+fn csv_field(text: &str) -> String {
+    match text.contains(|ch| ch == ',' || ch == '"' || ch == '\n' || ch == '\r') {
+        true => format!("\"{}\"", text.replace("\"", "\"\"")),
+        false => text.to_owned(),
+    }
+}
+
+///Renders Textractor's saved hooks, one line per executable.
+pub fn textractor(data: &[VnData]) -> String {
+    let mut result = String::new();
+
+    for (executable, hooks) in by_executable(data) {
+        result.push_str(&executable);
+
+        for (_, hook) in hooks {
+            for code in hook.code.split_whitespace() {
+                let _ = write!(result, " , {}", code);
+            }
+        }
+
+        result.push_str("\r\n");
+    }
+
+    result
+}
+
+///Renders ITHVNR's profile, one game per executable.
+pub fn ithvnr(data: &[VnData]) -> String {
+    let mut result = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\r\n<ITH_Profile>\r\n\t<Games>\r\n");
+
+    for (executable, hooks) in by_executable(data) {
+        let title = hooks.first().map(|&(vn, _)| vn.title.as_str()).unwrap_or("");
+        let _ = write!(result, "\t\t<Game Path=\"{}\" Title=\"{}\">\r\n\t\t\t<Hooks>\r\n", xml_escape(&executable), xml_escape(title));
+
+        for (_, hook) in hooks {
+            for code in hook.code.split_whitespace() {
+                let _ = write!(result, "\t\t\t\t<Hook Code=\"{}\" Name=\"{}\"/>\r\n", xml_escape(code), xml_escape(&hook.version));
+            }
+        }
+
+        result.push_str("\t\t\t</Hooks>\r\n\t\t</Game>\r\n");
+    }
+
+    result.push_str("\t</Games>\r\n</ITH_Profile>\r\n");
+    result
+}
+
+///Renders CSV with one hook per row.
+pub fn csv(data: &[VnData]) -> String {
+    let mut result = String::from("vn_id,title,version,code,release_id\r\n");
+
+    for vn in data.iter() {
+        for hook in vn.hooks.iter() {
+            let release = hook.release_id.map(|id| format!("{}", id)).unwrap_or_default();
+            let _ = write!(result, "{},{},{},{},{}\r\n", vn.data.id, csv_field(&vn.data.title), csv_field(&hook.version), csv_field(&hook.code), release);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Format, FORMATS, textractor, ithvnr, csv};
+    use ::db::{models, VnData};
+
+    fn hook(vn_id: i64, version: &str, code: &str) -> models::Hook {
+        models::Hook {
+            id: 0,
+            vn_id,
+            version: version.to_owned(),
+            code: code.to_owned(),
+            author: None,
+            frontend: None,
+            created_at: None,
+            updated_at: None,
+            release_id: None,
+        }
+    }
+
+    fn data() -> Vec<VnData> {
+        vec![
+            VnData {
+                data: models::Vn { id: 1, title: "Ever17".to_owned() },
+                hooks: vec![hook(1, "1.0", "/HS-8@0:Ever17PC_us.exe"), hook(1, "2.0", "/HA4@1234 /HS-4@0:ever17pc_us.exe")],
+                releases: Vec::new(),
+                executables: Vec::new(),
+            },
+            VnData {
+                data: models::Vn { id: 2, title: "Fate \"stay\", night".to_owned() },
+                hooks: vec![hook(2, "1.0", "/HS-8@0")],
+                releases: Vec::new(),
+                executables: Vec::new(),
+            },
+        ]
+    }
+
+    #[test]
+    fn should_map_file_names() {
+        for format in FORMATS {
+            assert_eq!(Format::from_file_name(format.file_name()), Some(*format));
+        }
+
+        assert_eq!(Format::from_file_name("roseline.db"), None);
+        assert_eq!(Format::Csv.path(Some(1)), "/download/vn/1/hooks.csv");
+        assert_eq!(Format::Textractor.path(None), "/download/hooks/SavedHooks.txt");
+    }
+
+    #[test]
+    fn should_export_textractor() {
+        let expected = "Ever17PC_us.exe , /HS-8@0:Ever17PC_us.exe , /HA4@1234 , /HS-4@0:ever17pc_us.exe\r\n\
+                        Fate \"stay\", night , /HS-8@0\r\n";
+
+        assert_eq!(textractor(&data()), expected);
+    }
+
+    #[test]
+    fn should_export_ithvnr() {
+        let result = ithvnr(&data());
+
+        assert!(result.starts_with("<?xml"));
+        assert!(result.contains("<Game Path=\"Ever17PC_us.exe\" Title=\"Ever17\">"));
+        assert!(result.contains("<Hook Code=\"/HA4@1234\" Name=\"2.0\"/>"));
+        assert!(result.contains("<Game Path=\"Fate &quot;stay&quot;, night\" Title=\"Fate &quot;stay&quot;, night\">"));
+        assert!(result.ends_with("</ITH_Profile>\r\n"));
+    }
+
+    #[test]
+    fn should_export_csv() {
+        let expected = "vn_id,title,version,code,release_id\r\n\
+                        1,Ever17,1.0,/HS-8@0:Ever17PC_us.exe,\r\n\
+                        1,Ever17,2.0,/HA4@1234 /HS-4@0:ever17pc_us.exe,\r\n\
+                        2,\"Fate \"\"stay\"\", night\",1.0,/HS-8@0,\r\n";
+
+        assert_eq!(csv(&data()), expected);
+    }
+}
@@ -0,0 +1,404 @@
+//!Client of VNDB's JSON HTTP API, also known as Kana.
+//!
+//!Requests are translated from legacy TCP API into Kana's queries and results back into legacy format,
+//!so that they are handled the same way regardless of backend.
+
+extern crate actix;
+extern crate tokio;
+extern crate http;
+
+use self::actix::prelude::*;
+use self::http::{Request as HttpRequest, AutoClient, Future, ResponseError, header};
+use self::http::futures::future;
+use self::tokio::timer::{Delay, Timeout};
+
+use ::vndb::{Request, Response, Query, Filter, DEFAULT_REQUEST_TIMEOUT_SECS};
+use ::serde_json::{self, Value};
+use ::time;
+use ::io;
+
+///Official API endpoint.
+pub const DEFAULT_URL: &'static str = "https://api.vndb.org/kana";
+///Default number of results per page, the same as of TCP API.
+const DEFAULT_RESULTS: u32 = 10;
+///Maximum number of results per page allowed by Kana.
+const MAX_RESULTS: u32 = 100;
+///Seconds to wait when throttling response has no `Retry-After`.
+const DEFAULT_RETRY_AFTER_SECS: f64 = 1.0;
+
+type ResponseFuture = Box<Future<Item=Response, Error=io::Error>>;
+
+///Path of Kana's endpoint and fields to retrieve for type of objects.
+fn endpoint(kind: &str) -> Option<(&'static str, &'static str)> {
+    match kind {
+        "v" => Some(("/vn", "title, alttitle, aliases, released, languages, olang, platforms")),
+        "r" => Some(("/release", "title, alttitle, released, patch, freeware, languages.lang, platforms, vns.id, vns.rtype")),
+        "c" => Some(("/character", "name, original, sex, blood_type, birthday")),
+        "p" => Some(("/producer", "name, original, type, lang")),
+        _ => None,
+    }
+}
+
+fn filters(kind: &str, filter: &Filter) -> Value {
+    match filter {
+        &Filter::Id(id) => json!(["id", "=", format!("{}{}", kind, id)]),
+        &Filter::Ids(ref ids) => {
+            let mut filters = vec![json!("or")];
+            filters.extend(ids.iter().map(|id| json!(["id", "=", format!("{}{}", kind, id)])));
+            Value::Array(filters)
+        },
+        //Kana has no exact match, therefore search results are filtered afterwards.
+        &Filter::ExactTitle(ref title) => json!(["search", "=", title]),
+        &Filter::Title(ref title) => json!(["search", "=", title]),
+    }
+}
+
+///Creates body of Kana's query.
+fn query_body(query: &Query, fields: &str) -> Value {
+    let results = match query.filter {
+        Filter::ExactTitle(_) => MAX_RESULTS,
+        _ => query.results.unwrap_or(DEFAULT_RESULTS),
+    };
+
+    let mut body = json!({
+        "filters": filters(&query.kind, &query.filter),
+        "fields": fields,
+        "page": query.page.unwrap_or(1),
+        "results": results,
+    });
+
+    match query.filter {
+        Filter::ExactTitle(_) | Filter::Title(_) => body["sort"] = json!("searchrank"),
+        _ => (),
+    }
+
+    body
+}
+
+///Converts Kana's ID, e.g. `v17`, into number.
+fn legacy_id(id: &Value) -> Value {
+    id.as_str().and_then(|id| id.get(1..))
+               .and_then(|id| id.parse::<u64>().ok())
+               .map(Value::from)
+               .unwrap_or(Value::Null)
+}
+
+///Converts Kana's object into its representation in TCP API.
+fn legacy_item(kind: &str, item: &Value) -> Value {
+    let id = legacy_id(&item["id"]);
+
+    match kind {
+        "v" => {
+            let aliases = item["aliases"].as_array().map(|aliases| aliases.iter().filter_map(|alias| alias.as_str()).collect::<Vec<_>>().join("\n"));
+            let orig_lang = match item["olang"] {
+                Value::Null => json!([]),
+                ref lang => json!([lang]),
+            };
+
+            json!({
+                "id": id,
+                "title": item["title"],
+                "original": item["alttitle"],
+                "released": item["released"],
+                "languages": item["languages"],
+                "orig_lang": orig_lang,
+                "platforms": item["platforms"],
+                "aliases": aliases,
+            })
+        },
+        "r" => {
+            let vns = item["vns"].as_array().map(|vns| vns.as_slice()).unwrap_or(&[]);
+            let languages = item["languages"].as_array().map(|languages| languages.iter().map(|language| language["lang"].clone()).collect::<Vec<_>>())
+                                                        .unwrap_or_default();
+
+            json!({
+                "id": id,
+                "title": item["title"],
+                "original": item["alttitle"],
+                "released": item["released"],
+                //Kana specifies type for each VN of release.
+                "type": vns.first().map(|vn| vn["rtype"].clone()).unwrap_or(json!("complete")),
+                "patch": item["patch"],
+                "freeware": item["freeware"],
+                //Kana has no such field.
+                "doujin": false,
+                "languages": languages,
+                "platforms": item["platforms"],
+                "vn": vns.iter().map(|vn| json!({"id": legacy_id(&vn["id"])})).collect::<Vec<_>>(),
+            })
+        },
+        "c" => json!({
+            "id": id,
+            "name": item["name"],
+            "original": item["original"],
+            "gender": item["sex"][0],
+            "bloodt": item["blood_type"],
+            //Kana gives month first, while TCP API gives day first.
+            "birthday": [item["birthday"][1], item["birthday"][0]],
+        }),
+        "p" => json!({
+            "id": id,
+            "name": item["name"],
+            "original": item["original"],
+            "type": item["type"],
+            "language": item["lang"],
+        }),
+        _ => json!({
+            "id": id,
+            "username": item["username"],
+        }),
+    }
+}
+
+///Converts Kana's results into response of TCP API.
+fn legacy_results(query: &Query, body: &Value) -> io::Result<Response> {
+    let items = match query.kind.as_str() {
+        //User's endpoint returns object with requested IDs as keys.
+        "u" => body.as_object().map(|users| users.values().filter(|user| !user.is_null()).collect::<Vec<_>>()),
+        _ => body["results"].as_array().map(|results| results.iter().collect::<Vec<_>>()),
+    };
+    let items = items.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Kana response has no results"))?;
+
+    let (items, more) = match query.filter {
+        Filter::ExactTitle(ref title) => {
+            let title = Value::String(title.clone());
+            let items = items.into_iter().filter(|item| item["title"] == title || item["alttitle"] == title).collect::<Vec<_>>();
+            (items, false)
+        },
+        _ => (items, body["more"].as_bool().unwrap_or(false)),
+    };
+
+    let items = items.into_iter().map(|item| legacy_item(&query.kind, item)).collect::<Vec<_>>();
+    let results = json!({
+        "num": items.len(),
+        "more": more,
+        "items": items,
+    });
+
+    legacy_response("results", &results)
+}
+
+fn legacy_response(kind: &str, value: &Value) -> io::Result<Response> {
+    Response::from_str(&format!("{} {}", kind, value)).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, format!("{}", error)))
+}
+
+///Converts Kana's errors into error responses of TCP API.
+fn legacy_error(status: u16, retry_after: Option<f64>, msg: String) -> io::Result<Response> {
+    match status {
+        429 => {
+            let minwait = retry_after.unwrap_or(DEFAULT_RETRY_AFTER_SECS);
+            legacy_response("error", &json!({"id": "throttled", "msg": msg, "minwait": minwait, "fullwait": minwait}))
+        },
+        400 => legacy_response("error", &json!({"id": "parse", "msg": msg})),
+        _ => Err(io::Error::new(io::ErrorKind::Other, format!("Kana request failed. Status: {}", status))),
+    }
+}
+
+fn send(request: HttpRequest, query: Query) -> ResponseFuture {
+    let response = request.send().map_err(|error| match error {
+        ResponseError::Timeout(_) => io::Error::new(io::ErrorKind::TimedOut, "No response from Kana"),
+        ResponseError::Timer(_, _) => io::Error::new(io::ErrorKind::TimedOut, "No response from Kana"),
+        ResponseError::HyperError(error) => io::Error::new(io::ErrorKind::Other, format!("Kana request failed. Error: {}", error)),
+    }).and_then(move |rsp| {
+        let status = rsp.status().as_u16();
+        let is_success = rsp.is_success();
+        let retry_after = rsp.headers().get(header::RETRY_AFTER)
+                                       .and_then(|value| value.to_str().ok())
+                                       .and_then(|value| value.parse::<f64>().ok());
+
+        rsp.body().limit(u64::max_value()).map_err(|error| {
+            io::Error::new(io::ErrorKind::Other, format!("Unable to read Kana response. Error: {:?}", error))
+        }).and_then(move |body| match is_success {
+            true => serde_json::from_slice(&body).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid Kana response. Error: {}", error)))
+                                                 .and_then(|body: Value| legacy_results(&query, &body)),
+            false => legacy_error(status, retry_after, String::from_utf8_lossy(&body).into_owned()),
+        })
+    });
+
+    Box::new(response)
+}
+
+///Creates HTTP request for query.
+fn request(url: &str, query: Query) -> ResponseFuture {
+    let invalid = |error: String| -> ResponseFuture {
+        Box::new(future::err(io::Error::new(io::ErrorKind::InvalidInput, error)))
+    };
+
+    //Users cannot be queried, only looked up by ID.
+    if query.kind == "u" {
+        let id = match query.filter {
+            Filter::Id(id) => id,
+            ref filter => return invalid(format!("Users cannot be filtered by {:?}", filter)),
+        };
+
+        return match HttpRequest::get(format!("{}/user?q=u{}&fields=username", url, id).as_str()) {
+            Ok(request) => send(request.empty(), query),
+            Err(error) => invalid(format!("Invalid Kana URL '{}'. Error: {:?}", url, error)),
+        };
+    }
+
+    let (path, fields) = match endpoint(&query.kind) {
+        Some(endpoint) => endpoint,
+        None => return invalid(format!("Unsupported type '{}'", query.kind)),
+    };
+
+    let body = query_body(&query, fields);
+    let request = match HttpRequest::post(format!("{}{}", url, path).as_str()) {
+        Ok(request) => request,
+        Err(error) => return invalid(format!("Invalid Kana URL '{}'. Error: {:?}", url, error)),
+    };
+
+    match request.json(&body) {
+        Ok(request) => send(request, query),
+        Err(error) => invalid(format!("Unable to serialize Kana query. Error: {}", error)),
+    }
+}
+
+///VNDB client that uses Kana.
+///
+///HTTP client must be initialized by `http::init`.
+pub struct Kana {
+    url: String,
+    ///Time to wait for response.
+    request_timeout: time::Duration,
+}
+
+impl Kana {
+    #[inline]
+    pub fn new() -> Self {
+        Self::with_url(DEFAULT_URL)
+    }
+
+    pub fn with_url<T: Into<String>>(url: T) -> Self {
+        Self {
+            url: url.into().trim_right_matches('/').to_owned(),
+            request_timeout: time::Duration::from_secs(DEFAULT_REQUEST_TIMEOUT_SECS),
+        }
+    }
+
+    ///Sets time to wait for response.
+    pub fn request_timeout(mut self, timeout: time::Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+}
+
+impl Actor for Kana {
+    type Context = Context<Self>;
+}
+
+impl Supervised for Kana {}
+
+impl Handler<Request> for Kana {
+    type Result = ResponseActFuture<Self, Response, io::Error>;
+
+    fn handle(&mut self, msg: Request, _ctx: &mut Self::Context) -> Self::Result {
+        //Kana requires no login, so only queries are supported.
+        let query = match msg.query() {
+            Some(query) => query.clone(),
+            None => return Box::new(actix::fut::err(io::Error::new(io::ErrorKind::InvalidInput, "Kana supports only queries"))),
+        };
+
+        trace!("Kana: send {:?}", query);
+
+        let response = Timeout::new(request(&self.url, query), self.request_timeout).map_err(|error| match error.into_inner() {
+            Some(error) => error,
+            None => io::Error::new(io::ErrorKind::TimedOut, "No response from Kana"),
+        });
+
+        let response = response.into_actor(self).and_then(move |response, act, _ctx| -> Self::Result {
+            match msg.retry(&response) {
+                Some((retry, delay)) => {
+                    warn!("Kana: Throttled. Retry in {:?}", delay);
+
+                    let delay = Delay::new(time::Instant::now() + delay);
+                    let delay = delay.map_err(|error| io::Error::new(io::ErrorKind::Other, format!("{}", error)));
+                    Box::new(delay.into_actor(act).and_then(move |_, act, ctx| <Self as Handler<Request>>::handle(act, retry, ctx)))
+                },
+                None => Box::new(actix::fut::ok(response)),
+            }
+        });
+
+        Box::new(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::actix::prelude::*;
+    use super::{Kana, query_body, legacy_item, legacy_results, endpoint};
+    use ::serde_json::Value;
+    use ::vndb::{Query, Filter, Response, Request, protocol};
+    use ::io;
+
+    fn query(kind: &str, filter: Filter) -> Query {
+        Query {
+            kind: kind.to_owned(),
+            filter,
+            page: None,
+            results: None,
+        }
+    }
+
+    #[test]
+    fn should_create_query() {
+        let (_, fields) = endpoint("v").expect("To have VN endpoint");
+
+        let body = query_body(&query("v", Filter::Ids(vec![1, 2])), fields);
+        assert_eq!(body["filters"], json!(["or", ["id", "=", "v1"], ["id", "=", "v2"]]));
+        assert_eq!(body["page"], 1);
+        assert_eq!(body["results"], 10);
+        assert_eq!(body["sort"], Value::Null);
+
+        let body = query_body(&Query { page: Some(2), ..query("v", Filter::Title("Ever17".to_owned())) }, fields);
+        assert_eq!(body["filters"], json!(["search", "=", "Ever17"]));
+        assert_eq!(body["page"], 2);
+        assert_eq!(body["sort"], "searchrank");
+    }
+
+    #[test]
+    fn should_convert_items() {
+        let vn = legacy_item("v", &json!({"id": "v17", "title": "Ever17", "alttitle": null, "aliases": ["E17", "Ever 17"], "olang": "ja"}));
+        assert_eq!(vn["id"], 17);
+        assert_eq!(vn["aliases"], "E17\nEver 17");
+        assert_eq!(vn["orig_lang"], json!(["ja"]));
+
+        let release = legacy_item("r", &json!({"id": "r1", "title": "Ever17", "languages": [{"lang": "en"}], "vns": [{"id": "v17", "rtype": "partial"}]}));
+        assert_eq!(release["type"], "partial");
+        assert_eq!(release["languages"], json!(["en"]));
+        assert_eq!(release["vn"], json!([{"id": 17}]));
+
+        let character = legacy_item("c", &json!({"id": "c1", "name": "Tsugumi", "sex": ["f", "f"], "birthday": [12, 31]}));
+        assert_eq!(character["gender"], "f");
+        assert_eq!(character["birthday"], json!([31, 12]));
+    }
+
+    #[test]
+    fn should_filter_exact_title() {
+        let body = json!({
+            "results": [{"id": "v1", "title": "Ever17 -the out of infinity-", "alttitle": "Ever17"}, {"id": "v2", "title": "Ever17 Premium Edition"}],
+            "more": true
+        });
+
+        match legacy_results(&query("v", Filter::ExactTitle("Ever17".to_owned())), &body).expect("To convert results") {
+            Response::Results(results) => {
+                assert_eq!(results.get("num"), Some(&json!(1)));
+                assert_eq!(results.get("more"), Some(&json!(false)));
+            },
+            _ => panic!("Results are expected")
+        }
+    }
+
+    #[test]
+    fn should_reject_non_query_request() {
+        let mut system = System::new("test");
+        let kana = Kana::with_url("http://127.0.0.1:1").start();
+
+        let login: Request = protocol::message::request::Login::new(None, None).into();
+        let result = system.block_on(kana.send(login)).expect("To deliver request");
+        match result {
+            Err(error) => assert_eq!(error.kind(), io::ErrorKind::InvalidInput),
+            Ok(response) => panic!("Unexpected response: {:?}", response),
+        }
+    }
+}
@@ -1,14 +1,25 @@
 #[macro_use]
 extern crate log;
+#[macro_use]
+extern crate serde_json;
 
 use std::{
     time,
     io,
     collections,
     fmt,
-    mem
+    net,
+    str,
+    hash,
+    rc,
+    cell
 };
 
 pub mod db;
 pub mod vndb;
+pub mod kana;
+pub mod backend;
+pub mod cache;
 pub mod exec;
+pub mod resync;
+pub mod export;
@@ -0,0 +1,242 @@
+//!Periodic synchronization of stored VNs with VNDB.
+//!
+//!Stored VNs are walked in batches, least recently synchronized first.
+//!Titles and aliases are updated from VNDB, while VNs that VNDB no longer knows are flagged as missing.
+
+extern crate actix;
+extern crate futures;
+
+use self::actix::prelude::*;
+use self::futures::{future, Future};
+
+use ::vndb;
+use ::backend;
+use ::db;
+
+use ::time;
+use ::fmt;
+
+///Number of VNs requested from VNDB at once.
+pub const BATCH_SIZE: usize = vndb::MAX_RESULTS as usize;
+///Delay between batches.
+///
+///VNDB allows 200 commands per 10 minutes, which are shared with bot's requests.
+const BATCH_DELAY_MS: u64 = 10_000;
+///Delay before retrying after VNDB failure, e.g. due to throttling.
+const RETRY_DELAY_MS: u64 = 5 * 60_000;
+///Delay before checking for VNs to synchronize once all are up to date.
+const IDLE_DELAY_MS: u64 = 60 * 60_000;
+///Delay before first batch, so that VNDB client can connect.
+const START_DELAY_MS: u64 = 60_000;
+///Default minimal time between synchronizations of the same VN.
+const DEFAULT_PERIOD_SECS: i64 = 7 * 24 * 60 * 60;
+
+///Changes made by synchronization.
+#[derive(Default, Debug)]
+pub struct Report {
+    ///Number of checked VNs.
+    pub checked: usize,
+    ///Renamed VNs as `(id, old title, new title)`.
+    pub renamed: Vec<(i64, String, String)>,
+    ///VNs whose aliases are updated.
+    pub aliases: Vec<i64>,
+    ///VNs that are found missing on VNDB for the first time.
+    pub missing: Vec<i64>,
+}
+
+impl Report {
+    fn merge(&mut self, other: Report) {
+        self.checked += other.checked;
+        self.renamed.extend(other.renamed);
+        self.aliases.extend(other.aliases);
+        self.missing.extend(other.missing);
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "checked {} VNs", self.checked)?;
+
+        for &(id, ref old, ref new) in self.renamed.iter() {
+            write!(f, " | v{} renamed from '{}' to '{}'", id, old, new)?;
+        }
+
+        if self.aliases.len() > 0 {
+            let ids = self.aliases.iter().map(|id| format!("v{}", id)).collect::<Vec<_>>();
+            write!(f, " | updated aliases of {}", ids.join(", "))?;
+        }
+
+        if self.missing.len() > 0 {
+            let ids = self.missing.iter().map(|id| format!("v{}", id)).collect::<Vec<_>>();
+            write!(f, " | missing on VNDB: {}", ids.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+type SyncFuture = Box<Future<Item=Report, Error=String>>;
+
+///Applies VNDB's data to the stored VN.
+fn sync_vn(db: &Addr<db::Db>, vn: db::models::Vn, data: Option<vndb::response::results::Vn>) -> SyncFuture {
+    let id = vn.id;
+
+    match data {
+        Some(data) => {
+            let aliases = vndb::split_aliases(data.aliases.as_ref().map(|aliases| aliases.as_str()));
+            let title = data.title.unwrap_or_else(|| vn.title.clone());
+            let sync_vn = db::SyncVn { vn, title: title.clone(), original: data.original, aliases };
+
+            let sync_vn = db.send(sync_vn).map_err(|error| format!("{}", error)).and_then(move |result| result.map_err(|error| format!("{}", error)).map(|changes| {
+                let mut report = Report::default();
+                report.checked = 1;
+
+                if let Some(old) = changes.renamed {
+                    report.renamed.push((id, old, title));
+                }
+                if changes.aliases {
+                    report.aliases.push(id);
+                }
+
+                report
+            }));
+
+            Box::new(sync_vn)
+        },
+        None => {
+            let mark_missing = db.send(db::MarkVnMissing(vn)).map_err(|error| format!("{}", error)).and_then(move |result| result.map_err(|error| format!("{}", error)).map(|is_new| {
+                let mut report = Report::default();
+                report.checked = 1;
+
+                if is_new {
+                    report.missing.push(id);
+                }
+
+                report
+            }));
+
+            Box::new(mark_missing)
+        }
+    }
+}
+
+///Synchronizes single batch of VNs that haven't been synchronized for `period` seconds.
+fn sync_batch(vndb: backend::Client, db: Addr<db::Db>, period: i64) -> SyncFuture {
+    let get_vns = db::GetUnsyncedVns { age: period, limit: BATCH_SIZE as i64 };
+    let get_vns = db.send(get_vns).map_err(|error| format!("{}", error)).and_then(|result| result.map_err(|error| format!("{}", error)));
+
+    let sync = get_vns.and_then(move |vns| -> SyncFuture {
+        if vns.len() == 0 {
+            return Box::new(future::ok(Report::default()));
+        }
+
+        let ids = vns.iter().map(|vn| vn.id as u64).collect::<Vec<_>>();
+        let get_vns = vndb.send(vndb::Get::vn_by_ids(&ids).into()).map_err(|error| format!("{}", error)).and_then(|result| match result {
+            Ok(vndb::Response::Results(results)) => results.vn().map(|results| results.items).map_err(|error| format!("Unexpected VNDB response type: {:?}", error)),
+            Ok(other) => match vndb::VndbError::from_response(&other) {
+                Some(error) => Err(format!("VNDB error: {}", error)),
+                None => Err(format!("Unexpected VNDB response on get: {:?}", other)),
+            },
+            Err(error) => Err(format!("{}", error)),
+        });
+
+        let sync = get_vns.and_then(move |mut found| {
+            let sync = vns.into_iter().map(|vn| {
+                let data = found.iter().position(|data| data.id as i64 == vn.id).map(|idx| found.swap_remove(idx));
+                sync_vn(&db, vn, data)
+            }).collect::<Vec<_>>();
+
+            future::join_all(sync).map(|reports| reports.into_iter().fold(Report::default(), |mut result, report| {
+                result.merge(report);
+                result
+            }))
+        });
+
+        Box::new(sync)
+    });
+
+    Box::new(sync)
+}
+
+///Background job that keeps stored VNs in sync with VNDB.
+pub struct Resync {
+    vndb: backend::Client,
+    db: Addr<db::Db>,
+    ///Minimal time in seconds between synchronizations of the same VN.
+    period: i64,
+    ///Changes made since last report.
+    report: Report,
+}
+
+impl Resync {
+    pub fn new(vndb: backend::Client, db: Addr<db::Db>) -> Self {
+        Self {
+            vndb,
+            db,
+            period: DEFAULT_PERIOD_SECS,
+            report: Report::default(),
+        }
+    }
+
+    ///Sets minimal time in seconds between synchronizations of the same VN.
+    pub fn period(mut self, period: i64) -> Self {
+        self.period = period;
+        self
+    }
+
+    fn schedule(&mut self, delay: u64, ctx: &mut Context<Self>) {
+        ctx.run_later(time::Duration::from_millis(delay), |act, ctx| act.run(ctx));
+    }
+
+    fn run(&mut self, ctx: &mut Context<Self>) {
+        let sync = sync_batch(self.vndb.clone(), self.db.clone(), self.period).into_actor(self).then(|result, act, ctx| {
+            match result {
+                //Full batch means there are likely more VNs to synchronize.
+                Ok(report) => match report.checked == BATCH_SIZE {
+                    true => {
+                        act.report.merge(report);
+                        act.schedule(BATCH_DELAY_MS, ctx);
+                    },
+                    false => {
+                        act.report.merge(report);
+                        if act.report.checked > 0 {
+                            info!("Resync: {}", act.report);
+                            act.report = Report::default();
+                        }
+                        act.schedule(IDLE_DELAY_MS, ctx);
+                    }
+                },
+                Err(error) => {
+                    warn!("Resync: unable to synchronize VNs: {}", error);
+                    act.schedule(RETRY_DELAY_MS, ctx);
+                }
+            }
+
+            actix::fut::ok(())
+        });
+
+        ctx.spawn(sync);
+    }
+}
+
+impl Actor for Resync {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.schedule(START_DELAY_MS, ctx);
+    }
+}
+
+///Synchronizes single batch of VNs immediately.
+pub struct SyncBatch;
+impl Message for SyncBatch {
+    type Result = Result<Report, String>;
+}
+impl Handler<SyncBatch> for Resync {
+    type Result = SyncFuture;
+
+    fn handle(&mut self, _: SyncBatch, _ctx: &mut Self::Context) -> Self::Result {
+        sync_batch(self.vndb.clone(), self.db.clone(), self.period)
+    }
+}
+
@@ -1,34 +1,134 @@
 extern crate futures;
 extern crate actix;
 extern crate vndb;
+extern crate tokio;
+extern crate bytes;
 
-use self::futures::Future;
+use self::futures::{Future, Stream, Sink};
 use self::futures::unsync::oneshot;
+use self::futures::sync::mpsc;
 use self::actix::prelude::*;
+use self::tokio::net::TcpStream;
+use self::tokio::codec::{Decoder, Encoder};
+use self::tokio::timer::{Delay, Timeout};
+use self::bytes::BytesMut;
 pub use self::vndb::{protocol, client};
 
 use ::collections::VecDeque;
+use ::net::SocketAddr;
 use ::time;
 use ::io;
+use ::str;
+use ::fmt;
+
+///Default time to wait for response.
+pub const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+///Number of times throttled request is repeated.
+const MAX_RETRIES: u8 = 3;
+///Longest wait in seconds before repeating throttled request.
+///
+///If VNDB asks to wait longer, throttling error is returned instead.
+const MAX_RETRY_WAIT_SECS: f64 = 30.0;
+
+///VNDB API endpoint.
+#[derive(Clone, Debug)]
+pub enum Endpoint {
+    ///Official API over TLS.
+    Default,
+    ///Plain TCP connection.
+    ///
+    ///Intended for local servers, e.g. mock in tests.
+    Plain(SocketAddr),
+}
+
+impl Default for Endpoint {
+    fn default() -> Self {
+        Endpoint::Default
+    }
+}
+
+///Codec of VNDB messages, which are terminated by `0x04`.
+pub struct Codec;
+
+impl Decoder for Codec {
+    type Item = protocol::message::Response;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Self::Item>> {
+        let end = match src.iter().position(|byte| *byte == 0x04) {
+            Some(end) => end,
+            None => return Ok(None),
+        };
+
+        let msg = src.split_to(end + 1);
+        let msg = str::from_utf8(&msg[..end]).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+        protocol::message::Response::from_str(msg).map(Some)
+                                                  .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, format!("{}", error)))
+    }
+}
+
+impl Encoder for Codec {
+    type Item = protocol::message::Request;
+    type Error = io::Error;
+
+    fn encode(&mut self, msg: Self::Item, dst: &mut BytesMut) -> io::Result<()> {
+        let msg = format!("{}\x04", msg);
+        dst.extend_from_slice(msg.as_bytes());
+        Ok(())
+    }
+}
+
+enum Sender {
+    Tls(client::tokio::ClientSender),
+    Plain(mpsc::UnboundedSender<protocol::message::Request>),
+}
+
+impl Sender {
+    fn request(&mut self, msg: protocol::message::Request) -> Result<(), String> {
+        match self {
+            &mut Sender::Tls(ref mut sender) => sender.request(msg).map(|_| ()).map_err(|error| format!("{}", error)),
+            &mut Sender::Plain(ref mut sender) => sender.unbounded_send(msg).map_err(|error| format!("{}", error)),
+        }
+    }
+}
 
 pub struct Vndb {
-    sender: Option<client::tokio::ClientSender>,
+    endpoint: Endpoint,
+    sender: Option<Sender>,
     queue: VecDeque<oneshot::Sender<io::Result<protocol::message::Response>>>,
     //Controls restart delay
     //In case of constant failures it
     //increases with each restart.
-    timeout: u64
+    timeout: u64,
+    ///Time to wait for response.
+    request_timeout: time::Duration,
 }
 
 impl Vndb {
+    #[inline]
     pub fn new() -> Self {
+        Self::with_endpoint(Endpoint::default())
+    }
+
+    pub fn with_endpoint(endpoint: Endpoint) -> Self {
         Self {
+            endpoint,
             sender: None,
             queue: VecDeque::with_capacity(10),
-            timeout: 0
+            timeout: 0,
+            request_timeout: time::Duration::from_secs(DEFAULT_REQUEST_TIMEOUT_SECS),
         }
     }
 
+    ///Sets time to wait for response.
+    ///
+    ///Connection is restarted on timeout, as the rest of responses cannot be matched to requests anymore.
+    pub fn request_timeout(mut self, timeout: time::Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
     #[inline]
     fn reset_timeout(&mut self) {
         self.timeout = 0;
@@ -44,12 +144,8 @@ impl Vndb {
 
         ctx.run_later(time::Duration::new(self.timeout, 0), |_, ctx| ctx.stop());
     }
-}
-
-impl Actor for Vndb {
-    type Context = Context<Self>;
 
-    fn started(&mut self, ctx: &mut Context<Self>) {
+    fn connect_tls(&mut self, ctx: &mut Context<Self>) {
         let vndb = match client::tokio::Client::new() {
             Ok(vndb) => vndb,
             Err(error) => {
@@ -63,24 +159,69 @@ impl Actor for Vndb {
             error!("VNDB: Unable to connect. Error: {}", error);
             act.restart_later(ctx);
         }).map(|client, act, ctx| {
-            info!("VNDB: Connected.");
             let (sink, stream) = client.into_parts();
-
-            match sink.request(protocol::message::request::Login::new(None, None)) {
-                Ok(_) => info!("VNDB: Sent Login message"),
-                Err(error) => {
-                    warn!("VNDB: Unable to send login message. Error: {}", error);
-                    return act.restart_later(ctx);
-                }
-            }
             Self::add_stream(stream, ctx);
+            act.connected(Sender::Tls(sink), ctx);
+        });
+
+        ctx.spawn(vndb);
+    }
 
-            act.sender = Some(sink);
-            act.reset_timeout();
+    fn connect_plain(&mut self, addr: SocketAddr, ctx: &mut Context<Self>) {
+        let vndb = TcpStream::connect(&addr).into_actor(self).map_err(move |error, act, ctx| {
+            error!("VNDB: Unable to connect to {}. Error: {}", addr, error);
+            act.restart_later(ctx);
+        }).map(|stream, act, ctx| {
+            let (sink, stream) = Codec.framed(stream).split();
+            let (tx, rx) = mpsc::unbounded();
+
+            let requests = rx.map_err(|_| io::Error::new(io::ErrorKind::ConnectionAborted, "Sender is dropped"));
+            let requests = sink.send_all(requests).map(|_| ()).map_err(|error| {
+                warn!("VNDB: Unable to send request. Error: {}", error);
+            });
+            ctx.spawn(requests.into_actor(act));
+            Self::add_stream(stream, ctx);
+            act.connected(Sender::Plain(tx), ctx);
         });
 
         ctx.spawn(vndb);
     }
+
+    fn connected(&mut self, mut sender: Sender, ctx: &mut Context<Self>) {
+        info!("VNDB: Connected.");
+
+        match sender.request(protocol::message::request::Login::new(None, None).into()) {
+            Ok(_) => info!("VNDB: Sent Login message"),
+            Err(error) => {
+                warn!("VNDB: Unable to send login message. Error: {}", error);
+                return self.restart_later(ctx);
+            }
+        }
+
+        //Login's response must not be taken as response to the first request.
+        let (tx, rx) = oneshot::channel();
+        self.queue.push_back(tx);
+        let login = rx.into_actor(self).map(|result, _act, _ctx| match result {
+            Ok(protocol::message::Response::Ok) => info!("VNDB: Login complete"),
+            Ok(msg) => warn!("VNDB: Unexpected response on login. Message={:?}", msg),
+            Err(error) => warn!("VNDB: Login failed. Error: {}", error),
+        }).map_err(|_, _act, _ctx| ());
+        ctx.spawn(login);
+
+        self.sender = Some(sender);
+        self.reset_timeout();
+    }
+}
+
+impl Actor for Vndb {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        match self.endpoint.clone() {
+            Endpoint::Default => self.connect_tls(ctx),
+            Endpoint::Plain(addr) => self.connect_plain(addr, ctx),
+        }
+    }
 }
 
 impl Supervised for Vndb {
@@ -110,13 +251,7 @@ impl StreamHandler<protocol::message::Response, io::Error> for Vndb {
             Some(tx) => {
                 let _ = tx.send(Ok(msg));
             },
-            None => {
-                match msg {
-                    //As we only use Get methods, OK can be received on login only.
-                    protocol::message::Response::Ok => info!("VNDB: Login complete"),
-                    msg => warn!("Received message while there was no request. Message={:?}", msg)
-                }
-            }
+            None => warn!("Received message while there was no request. Message={:?}", msg)
         }
     }
 }
@@ -124,50 +259,204 @@ impl StreamHandler<protocol::message::Response, io::Error> for Vndb {
 pub use self::protocol::message::request::get::{Type, Flags, Filters, Options};
 pub use self::protocol::message::{Response, response};
 
+///Maximum number of results per page allowed by VNDB.
+pub const MAX_RESULTS: u32 = 25;
+
+///Error reported by VNDB.
+#[derive(Clone, PartialEq, Debug)]
+pub enum VndbError {
+    ///Too many requests. Wait times are in seconds.
+    Throttled {
+        ///Time until next request is allowed.
+        minwait: f64,
+        ///Time until throttling is lifted completely.
+        fullwait: f64,
+    },
+    ///Request cannot be parsed.
+    Parse(String),
+    ///Required argument is missing.
+    Missing(String),
+    ///Any other error, e.g. invalid filter or type.
+    Other {
+        id: String,
+        msg: String,
+    },
+}
+
+impl VndbError {
+    ///Extracts error from response, if it is one.
+    pub fn from_response(response: &Response) -> Option<Self> {
+        let error = match response {
+            &Response::Error(ref error) => error,
+            _ => return None,
+        };
+
+        let msg = error.get("msg").and_then(|msg| msg.as_str()).unwrap_or("").to_owned();
+        let wait = |field: &str| error.get(field).and_then(|wait| wait.as_f64()).unwrap_or(0.0);
+
+        Some(match error.get("id").and_then(|id| id.as_str()).unwrap_or("") {
+            "throttled" => VndbError::Throttled { minwait: wait("minwait"), fullwait: wait("fullwait") },
+            "parse" => VndbError::Parse(msg),
+            "missing" => VndbError::Missing(msg),
+            id => VndbError::Other { id: id.to_owned(), msg },
+        })
+    }
+}
+
+impl fmt::Display for VndbError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &VndbError::Throttled { minwait, fullwait } => write!(f, "Throttled for {:.1}s (fully {:.1}s)", minwait, fullwait),
+            &VndbError::Parse(ref msg) => write!(f, "Parse error: {}", msg),
+            &VndbError::Missing(ref msg) => write!(f, "Missing argument: {}", msg),
+            &VndbError::Other { ref id, ref msg } => write!(f, "{}: {}", id, msg),
+        }
+    }
+}
+
+///Splits VN's aliases, which VNDB separates by new line.
+pub fn split_aliases(aliases: Option<&str>) -> Vec<String> {
+    aliases.map(|aliases| aliases.lines().map(|alias| alias.trim())
+                                         .filter(|alias| alias.len() > 0)
+                                         .map(|alias| alias.to_owned())
+                                         .collect())
+           .unwrap_or_default()
+}
+
 //We cannot implement 3pp trait on 3pp struct... :(
 #[derive(Clone)]
-pub struct Request(protocol::message::Request);
+pub struct Request {
+    inner: protocol::message::Request,
+    ///Description of `get` request for backends other than TCP API.
+    query: Option<Query>,
+    ///Number of times request is repeated due to throttling.
+    retries: u8,
+}
+
+impl From<protocol::message::Request> for Request {
+    fn from(inner: protocol::message::Request) -> Self {
+        Self {
+            inner,
+            query: None,
+            retries: 0
+        }
+    }
+}
+
+impl Request {
+    ///Returns description of `get` request, if it is one.
+    pub fn query(&self) -> Option<&Query> {
+        self.query.as_ref()
+    }
+
+    ///Returns request to repeat and delay before it, if response is throttling error
+    ///and request can be repeated.
+    pub fn retry(&self, response: &Response) -> Option<(Request, time::Duration)> {
+        match VndbError::from_response(response) {
+            Some(VndbError::Throttled { minwait, .. }) if self.retries < MAX_RETRIES && minwait <= MAX_RETRY_WAIT_SECS => {
+                let retry = Request {
+                    inner: self.inner.clone(),
+                    query: self.query.clone(),
+                    retries: self.retries + 1
+                };
+
+                Some((retry, time::Duration::from_millis((minwait * 1000.0) as u64)))
+            },
+            _ => None
+        }
+    }
+}
+
+///Condition of `get` request.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Filter {
+    Id(u64),
+    Ids(Vec<u64>),
+    ///Title or original title is equal.
+    ExactTitle(String),
+    ///Title or original title contains text.
+    Title(String),
+}
+
+impl Filter {
+    fn filters(&self) -> Filters {
+        match self {
+            &Filter::Id(id) => Filters::new().filter(format_args!("id = {}", id)),
+            &Filter::Ids(ref ids) => {
+                let ids = ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(",");
+                Filters::new().filter(format_args!("id = [{}]", ids))
+            },
+            &Filter::ExactTitle(ref title) => Filters::new().filter(format_args!("title = \"{}\"", title))
+                                                            .or(format_args!("original = \"{}\"", title)),
+            &Filter::Title(ref title) => Filters::new().filter(format_args!("title ~ \"{}\"", title))
+                                                       .or(format_args!("original ~ \"{}\"", title)),
+        }
+    }
+}
+
+///Description of `get` request that can be translated for other APIs.
+#[derive(Clone, Debug)]
+pub struct Query {
+    ///Type of objects as prefix of their IDs, e.g. `v`.
+    pub kind: String,
+    pub filter: Filter,
+    pub page: Option<u32>,
+    pub results: Option<u32>,
+}
 
 #[derive(Clone)]
 pub struct Get {
-    inner: protocol::message::request::Get
+    inner: protocol::message::request::Get,
+    filter: Filter,
 }
 
 impl Get {
-    pub fn new(kind: Type, flags: Flags, filters: Filters, options: Option<Options>) -> Self {
+    pub fn new(kind: Type, flags: Flags, filter: Filter, options: Option<Options>) -> Self {
         Self {
             inner: protocol::message::request::Get {
                 kind,
                 flags,
-                filters,
+                filters: filter.filters(),
                 options
-            }
+            },
+            filter
         }
     }
 
     pub fn get_by_id(kind: Type, id: u64) -> Self {
-        let filters = Filters::new().filter(format_args!("id = {}", id));
-
-        Self::new(kind, Flags::new().basic(), filters, None)
+        Self::new(kind, Flags::new().basic(), Filter::Id(id), None)
     }
 
-    #[inline]
+    ///Retrieves VN with details, which include its aliases.
     pub fn vn_by_id(id: u64) -> Self {
-        Self::get_by_id(Type::vn(), id)
+        Self::new(Type::vn(), Flags::new().basic().details(), Filter::Id(id), None)
     }
 
-    pub fn vn_by_exact_title(title: &str) -> Self {
-        let filters = Filters::new().filter(format_args!("title = \"{}\"", title))
-                                    .or(format_args!("original = \"{}\"", title));
+    ///Retrieves several VNs with details at once.
+    ///
+    ///VNDB returns at most 25 VNs per request.
+    pub fn vn_by_ids(ids: &[u64]) -> Self {
+        let options = Options {
+            page: None,
+            results: Some(MAX_RESULTS),
+            sort: None,
+            reverse: None
+        };
 
-        Self::new(Type::vn(), Flags::new().basic(), filters, None)
+        Self::new(Type::vn(), Flags::new().basic().details(), Filter::Ids(ids.to_vec()), Some(options))
     }
 
-    pub fn vn_by_title(title: &str) -> Self {
-        let filters = Filters::new().filter(format_args!("title ~ \"{}\"", title))
-                                    .or(format_args!("original ~ \"{}\"", title));
+    ///Retrieves release with its platforms and VNs.
+    pub fn release_by_id(id: u64) -> Self {
+        Self::new(Type::release(), Flags::new().basic().details().vn(), Filter::Id(id), None)
+    }
+
+    pub fn vn_by_exact_title(title: &str) -> Self {
+        Self::new(Type::vn(), Flags::new().basic().details(), Filter::ExactTitle(title.to_owned()), None)
+    }
 
-        Self::new(Type::vn(), Flags::new().basic(), filters, None)
+    pub fn vn_by_title(title: &str) -> Self {
+        Self::new(Type::vn(), Flags::new().basic().details(), Filter::Title(title.to_owned()), None)
     }
 
     pub fn set_options(mut self, options: Option<Options>) -> Self {
@@ -178,13 +467,24 @@ impl Get {
 
 impl Into<Request> for Get {
     fn into(self) -> Request {
-        Request(self.inner.into())
+        let query = Query {
+            kind: self.inner.kind.short().to_owned(),
+            filter: self.filter,
+            page: self.inner.options.as_ref().and_then(|options| options.page),
+            results: self.inner.options.as_ref().and_then(|options| options.results),
+        };
+
+        Request {
+            inner: protocol::message::Request::from(self.inner),
+            query: Some(query),
+            retries: 0
+        }
     }
 }
 
 impl Into<Request> for protocol::message::request::Login {
     fn into(self) -> Request {
-        Request(self.into())
+        Request::from(protocol::message::Request::from(self))
     }
 }
 
@@ -193,13 +493,13 @@ impl Message for Request {
 }
 
 impl Handler<Request> for Vndb {
-    type Result = ResponseFuture<protocol::message::Response, io::Error>;
+    type Result = ResponseActFuture<Self, protocol::message::Response, io::Error>;
 
     fn handle(&mut self, msg: Request, ctx: &mut Self::Context) -> Self::Result {
-        trace!("VNDB: send {}", &msg.0);
+        trace!("VNDB: send {}", &msg.inner);
 
         let (tx, rx) = oneshot::channel();
-        let send = self.sender.as_mut().and_then(|sender| sender.request(msg.0).ok());
+        let send = self.sender.as_mut().and_then(|sender| sender.request(msg.inner.clone()).ok());
         match send {
             Some(_) => self.queue.push_back(tx),
             None => {
@@ -210,7 +510,58 @@ impl Handler<Request> for Vndb {
             }
         }
 
-        Box::new(rx.map_err(|_| io::Error::new(io::ErrorKind::ConnectionAborted, "Restart"))
-                   .and_then(|res| res))
+        let response = rx.map_err(|_| io::Error::new(io::ErrorKind::ConnectionAborted, "Restart"))
+                         .and_then(|res| res);
+        let response = Timeout::new(response, self.request_timeout).map_err(|error| match error.into_inner() {
+            Some(error) => error,
+            None => io::Error::new(io::ErrorKind::TimedOut, "No response from VNDB"),
+        });
+
+        let response = response.into_actor(self).then(move |result, act, ctx| -> Self::Result {
+            match result {
+                Ok(response) => match msg.retry(&response) {
+                    Some((retry, delay)) => {
+                        warn!("VNDB: Throttled. Retry in {:?}", delay);
+
+                        let delay = Delay::new(time::Instant::now() + delay);
+                        let delay = delay.map_err(|error| io::Error::new(io::ErrorKind::Other, format!("{}", error)));
+                        Box::new(delay.into_actor(act).and_then(move |_, act, ctx| <Self as Handler<Request>>::handle(act, retry, ctx)))
+                    },
+                    None => Box::new(actix::fut::ok(response)),
+                },
+                Err(error) => {
+                    if error.kind() == io::ErrorKind::TimedOut {
+                        //Responses are matched to requests by order, which is broken by missing response.
+                        warn!("VNDB: Request timed out. Reconnecting...");
+                        ctx.stop();
+                    }
+
+                    Box::new(actix::fut::err(error))
+                }
+            }
+        });
+
+        Box::new(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{VndbError, Response};
+
+    fn error(text: &str) -> Option<VndbError> {
+        let response = Response::from_str(text).expect("Valid response");
+        VndbError::from_response(&response)
+    }
+
+    #[test]
+    fn should_parse_vndb_errors() {
+        assert_eq!(error("error {\"id\":\"throttled\",\"msg\":\"Throttle limit reached.\",\"type\":\"cmd\",\"minwait\":1.5,\"fullwait\":60.0}"),
+                   Some(VndbError::Throttled { minwait: 1.5, fullwait: 60.0 }));
+        assert_eq!(error("error {\"id\":\"parse\",\"msg\":\"Invalid command\"}"), Some(VndbError::Parse("Invalid command".to_owned())));
+        assert_eq!(error("error {\"id\":\"missing\",\"msg\":\"No type given\",\"field\":\"type\"}"), Some(VndbError::Missing("No type given".to_owned())));
+        assert_eq!(error("error {\"id\":\"filter\",\"msg\":\"Unknown field\"}"),
+                   Some(VndbError::Other { id: "filter".to_owned(), msg: "Unknown field".to_owned() }));
+        assert_eq!(error("ok"), None);
     }
 }
@@ -0,0 +1,481 @@
+extern crate actix;
+extern crate tokio;
+
+extern crate actors;
+extern crate http;
+extern crate mock;
+
+use actix::prelude::*;
+use actix::SystemRunner;
+use actix::dev::ToEnvelope;
+use tokio::timer::Delay;
+
+use actors::exec::{self, Executor, ResponseError};
+use actors::backend::{self, Backend};
+use actors::vndb::{self, Endpoint};
+use actors::db::Db;
+use actors::db::models::{Author, Frontend};
+use mock::vndb::{Data, Server};
+
+use std::time::{Duration, Instant};
+use std::sync::{Once, ONCE_INIT};
+
+///VNDB API that scenario runs against.
+#[derive(Clone, Copy)]
+enum Api {
+    Tcp,
+    Kana,
+}
+
+static HTTP_INIT: Once = ONCE_INIT;
+
+struct Env {
+    system: SystemRunner,
+    server: Server,
+    vndb: backend::Client,
+    db: Addr<Db>,
+    executor: Addr<Executor>,
+}
+
+impl Env {
+    fn new(api: Api, data: Data) -> Self {
+        Self::with_timeout(api, data, Duration::from_secs(vndb::DEFAULT_REQUEST_TIMEOUT_SECS))
+    }
+
+    ///Creates environment with specified time to wait for VNDB response.
+    fn with_timeout(api: Api, data: Data, timeout: Duration) -> Self {
+        let mut system = System::new("test");
+        let (server, backend) = match api {
+            Api::Tcp => {
+                let server = Server::start(data).expect("To start mock VNDB");
+                let backend = Backend::Tcp(Endpoint::Plain(server.addr()));
+                (server, backend)
+            },
+            Api::Kana => {
+                HTTP_INIT.call_once(http::init);
+                let server = Server::start_kana(data).expect("To start mock Kana");
+                let backend = Backend::Kana(server.url());
+                (server, backend)
+            }
+        };
+
+        let vndb = backend.start_with_timeout(timeout);
+        let db = Db::start_threaded_at(1, ":memory:".to_owned());
+        let executor = Executor::new(vndb.clone(), db.clone()).start();
+
+        //Requests are rejected until VNDB client is connected.
+        if let Api::Tcp = api {
+            for _ in 0..200 {
+                if server.requests().iter().any(|request| request.starts_with("login")) {
+                    break;
+                }
+
+                let _ = system.block_on(Delay::new(Instant::now() + Duration::from_millis(10)));
+            }
+        }
+
+        Self {
+            system,
+            server,
+            vndb,
+            db,
+            executor
+        }
+    }
+
+    ///Stops executor and replaces it with new one.
+    fn restart_executor(&mut self) {
+        let stop = self.executor.send(exec::Stop);
+        self.system.block_on(stop).expect("To stop executor");
+
+        self.executor = Executor::new(self.vndb.clone(), self.db.clone()).start();
+    }
+
+    fn send<M, T>(&mut self, msg: M) -> Result<T, ResponseError>
+        where M: Message<Result=Result<T, ResponseError>> + Send + 'static,
+              T: Send + 'static,
+              Executor: Handler<M>,
+              <Executor as Actor>::Context: ToEnvelope<Executor, M>
+    {
+        let request = self.executor.send(msg);
+        self.system.block_on(request).expect("To deliver message")
+    }
+}
+
+fn unwrap<T>(result: Result<T, ResponseError>) -> T {
+    match result {
+        Ok(result) => result,
+        Err(error) => panic!("Unexpected error: {}", error)
+    }
+}
+
+fn author() -> Author {
+    Author::new("tester", Frontend::Console)
+}
+
+fn data() -> Data {
+    Data::new().vn(1, "Ever17 -the out of infinity-", Some("Ever17"))
+               .vn(2, "Ever17 -the out of infinity- Premium Edition", None)
+               .vn(3, "Never7 -the end of infinity-", None)
+}
+
+fn should_find_vn_by_exact_title(api: Api) {
+    let mut env = Env::new(api, data());
+
+    let vn = unwrap(env.send(exec::FindVn::new("Ever17".to_owned())));
+    assert_eq!(vn.id, 1);
+    assert_eq!(env.server.gets(), 1);
+}
+
+fn should_find_vn_by_partial_title(api: Api) {
+    let mut env = Env::new(api, data());
+
+    let vn = unwrap(env.send(exec::FindVn::new("Never7".to_owned())));
+    assert_eq!(vn.id, 3);
+    assert_eq!(env.server.gets(), 2);
+}
+
+fn should_not_find_ambiguous_vn(api: Api) {
+    let mut env = Env::new(api, data());
+
+    match env.send(exec::FindVn::new("infinity".to_owned())) {
+        Err(ResponseError::TooMany(num, _)) => assert_eq!(num, 3),
+        Err(error) => panic!("Unexpected error: {}", error),
+        Ok(_) => panic!("Ambiguous VN is found")
+    }
+
+    match env.send(exec::FindVn::new("Remember11".to_owned())) {
+        Err(ResponseError::UnknownVn) => (),
+        Err(error) => panic!("Unexpected error: {}", error),
+        Ok(_) => panic!("Unknown VN is found")
+    }
+}
+
+fn should_cache_vndb_responses(api: Api) {
+    let mut env = Env::new(api, data());
+
+    let vn = unwrap(env.send(exec::FindVn::new("Ever17".to_owned())));
+    assert_eq!(vn.id, 1);
+    let gets = env.server.gets();
+
+    //VN found by title is cached by ID too.
+    let vn = unwrap(env.send(exec::FindVn::new("Ever17".to_owned())));
+    assert_eq!(vn.id, 1);
+    let vn = unwrap(env.send(exec::GetVn::new(1)));
+    assert_eq!(vn.id, 1);
+    assert_eq!(env.server.gets(), gets);
+
+    for _ in 0..2 {
+        match env.send(exec::GetVn::new(4)) {
+            Err(ResponseError::UnknownVn) => (),
+            Err(error) => panic!("Unexpected error: {}", error),
+            Ok(_) => panic!("Unknown VN is found")
+        }
+    }
+    assert_eq!(env.server.gets(), gets + 1);
+
+    let stats = env.system.block_on(env.executor.send(exec::GetCacheStats)).expect("To deliver message");
+    assert_eq!(stats.titles.hits, 1);
+    assert_eq!(stats.titles.misses, 1);
+    assert_eq!(stats.vns.hits, 2);
+    assert_eq!(stats.vns.misses, 1);
+}
+
+fn should_retry_throttled_request(api: Api) {
+    let mut env = Env::new(api, data().throttle(1, 0.1));
+
+    let vn = unwrap(env.send(exec::GetVn::new(1)));
+    assert_eq!(vn.id, 1);
+    assert_eq!(env.server.gets(), 2);
+}
+
+fn should_report_throttling(api: Api) {
+    let mut env = Env::new(api, data().throttle(10, 0.1));
+
+    match env.send(exec::GetVn::new(1)) {
+        Err(ResponseError::Throttled(wait)) => assert_eq!(wait, 1),
+        Err(error) => panic!("Unexpected error: {}", error),
+        Ok(_) => panic!("Throttled request succeeded")
+    }
+    //Initial request and 3 retries.
+    assert_eq!(env.server.gets(), 4);
+}
+
+fn should_time_out_request(api: Api) {
+    let mut env = Env::with_timeout(api, data().unresponsive(), Duration::from_millis(200));
+
+    match env.send(exec::GetVn::new(1)) {
+        Err(ResponseError::VndbTimeout) => (),
+        Err(error) => panic!("Unexpected error: {}", error),
+        Ok(_) => panic!("Unresponsive VNDB returned VN")
+    }
+}
+
+fn should_search_vn_on_all_pages(api: Api) {
+    let data = (1..26).fold(Data::new(), |data, id| data.vn(id, &format!("Title {}", id), None));
+    let mut env = Env::new(api, data);
+
+    let vns = unwrap(env.send(exec::SearchVn::new("Title".to_owned())));
+    assert_eq!(vns.len(), 25);
+    assert_eq!(env.server.gets(), 3);
+}
+
+fn should_not_get_hook_of_unknown_vn(api: Api) {
+    let mut env = Env::new(api, data());
+
+    match env.send(exec::GetHook("v1".to_owned())) {
+        Err(ResponseError::UnknownVn) => (),
+        Err(error) => panic!("Unexpected error: {}", error),
+        Ok(_) => panic!("VN is not supposed to be in DB")
+    }
+}
+
+fn should_set_and_get_hook_by_id(api: Api) {
+    let mut env = Env::new(api, data());
+
+    let hook = unwrap(env.send(exec::SetHook::new("v1".to_owned(), "1.0".to_owned(), "/HS-8@0".to_owned(), author())));
+    assert_eq!(hook.code, "/HS-8@0");
+    assert_eq!(hook.author.as_ref().map(|author| author.as_str()), Some("tester"));
+
+    let data = unwrap(env.send(exec::GetHook("v1".to_owned())));
+    assert_eq!(data.data.title, "Ever17 -the out of infinity-");
+    assert_eq!(data.hooks.len(), 1);
+    assert_eq!(data.hooks[0].version, "1.0");
+    assert_eq!(data.hooks[0].code, "/HS-8@0");
+
+    //VN is stored in DB so no further VNDB requests are needed.
+    let gets = env.server.gets();
+    let data = unwrap(env.send(exec::GetHook("Ever17".to_owned())));
+    assert_eq!(data.hooks.len(), 1);
+    assert_eq!(env.server.gets(), gets);
+}
+
+fn should_set_hook_by_title(api: Api) {
+    let mut env = Env::new(api, data());
+
+    unwrap(env.send(exec::SetHook::new("Never7".to_owned(), "2.0".to_owned(), "/HSN4@0".to_owned(), author())));
+    let hook = unwrap(env.send(exec::SetHook::new("v3".to_owned(), "2.0".to_owned(), "/HSN8@0".to_owned(), author())));
+    assert_eq!(hook.code, "/HSN8@0");
+
+    let data = unwrap(env.send(exec::GetHook("Never7".to_owned())));
+    assert_eq!(data.data.id, 3);
+    assert_eq!(data.hooks.len(), 1);
+    assert_eq!(data.hooks[0].code, "/HSN8@0");
+}
+
+fn should_queue_submitted_hook(api: Api) {
+    let mut env = Env::new(api, data());
+
+    let pending = unwrap(env.send(exec::SubmitHook::new("v1".to_owned(), "1.0".to_owned(), "/HS-8@0".to_owned(), author(), Some("tester".to_owned()))));
+    assert_eq!(pending.vn_id, 1);
+    assert_eq!(pending.vn_title, "Ever17 -the out of infinity-");
+    assert_eq!(pending.code, "/HS-8@0");
+    assert_eq!(pending.contact.as_ref().map(|contact| contact.as_str()), Some("tester"));
+
+    //Hook becomes visible only after approval.
+    let data = unwrap(env.send(exec::GetHook("v1".to_owned())));
+    assert_eq!(data.hooks.len(), 0);
+}
+
+fn should_normalize_and_validate_hook_code(api: Api) {
+    let mut env = Env::new(api, data());
+
+    let hook = unwrap(env.send(exec::SetHook::new("v1".to_owned(), "1.0".to_owned(), " /hs-8@0a ".to_owned(), author())));
+    assert_eq!(hook.code, "/HS-8@A");
+
+    //Invalid code is rejected before VN is looked up.
+    let gets = env.server.gets();
+    match env.send(exec::SetHook::new("v2".to_owned(), "1.0".to_owned(), "HS-8@0".to_owned(), author())) {
+        Err(ResponseError::InvalidCode(_)) => (),
+        Err(error) => panic!("Unexpected error: {}", error),
+        Ok(_) => panic!("Invalid code is not supposed to be stored"),
+    }
+    match env.send(exec::SubmitHook::new("v1".to_owned(), "1.0".to_owned(), "/HS-8G@0".to_owned(), author(), None)) {
+        Err(ResponseError::InvalidCode(_)) => (),
+        Err(error) => panic!("Unexpected error: {}", error),
+        Ok(_) => panic!("Invalid code is not supposed to be queued"),
+    }
+    assert_eq!(env.server.gets(), gets);
+
+    let data = unwrap(env.send(exec::GetHook("v1".to_owned())));
+    assert_eq!(data.hooks.len(), 1);
+    assert_eq!(data.hooks[0].code, "/HS-8@A");
+}
+
+fn should_get_hook_by_normalized_title(api: Api) {
+    let data = Data::new().vn(1, "Fate/stay night", None)
+                          .vn(2, "Fate/hollow ataraxia", None);
+    let mut env = Env::new(api, data);
+
+    unwrap(env.send(exec::SetHook::new("v1".to_owned(), "1.0".to_owned(), "/HS-8@0".to_owned(), author())));
+    unwrap(env.send(exec::SetHook::new("v2".to_owned(), "1.0".to_owned(), "/HS-4@0".to_owned(), author())));
+
+    //Both VNs are in DB now, so no further VNDB requests are needed.
+    let gets = env.server.gets();
+    let data = unwrap(env.send(exec::GetHook("Fate Stay Night".to_owned())));
+    assert_eq!(data.data.id, 1);
+
+    let data = unwrap(env.send(exec::GetHook("ＦＡＴＥ／ｈｏｌｌｏｗ".to_owned())));
+    assert_eq!(data.data.id, 2);
+    assert_eq!(env.server.gets(), gets);
+}
+
+fn should_get_hook_by_alias(api: Api) {
+    let data = Data::new().vn_aliased(1, "Higurashi no Naku Koro ni", Some("ひぐらしのなく頃に"), Some("Higurashi\nWhen They Cry"));
+    let mut env = Env::new(api, data);
+
+    unwrap(env.send(exec::SetHook::new("v1".to_owned(), "1.0".to_owned(), "/HS-8@0".to_owned(), author())));
+    let (vn, aliases) = unwrap(env.send(exec::GetAliases("v1".to_owned())));
+    assert_eq!(vn.id, 1);
+    assert_eq!(aliases.iter().map(|alias| alias.alias.as_str()).collect::<Vec<_>>(), vec!["ひぐらしのなく頃に", "Higurashi", "When They Cry"]);
+
+    //Aliases are stored in DB, so no further VNDB requests are needed.
+    let gets = env.server.gets();
+    let data = unwrap(env.send(exec::GetHook("When They Cry".to_owned())));
+    assert_eq!(data.data.id, 1);
+    let data = unwrap(env.send(exec::GetHook("ヒグラシノナク頃ニ".to_owned())));
+    assert_eq!(data.data.id, 1);
+
+    unwrap(env.send(exec::AddAlias::new("v1".to_owned(), "Higu".to_owned(), author())));
+    let data = unwrap(env.send(exec::GetHook("higu".to_owned())));
+    assert_eq!(data.data.id, 1);
+    assert_eq!(env.server.gets(), gets);
+
+    assert_eq!(unwrap(env.send(exec::DelAlias::new("v1".to_owned(), "Higu".to_owned(), author()))), 1);
+    assert_eq!(unwrap(env.send(exec::DelAlias::new("v1".to_owned(), "Higu".to_owned(), author()))), 0);
+}
+
+fn should_set_hook_for_release(api: Api) {
+    let data = data().release_on(10, "Ever17 -the out of infinity- Premium Edition", 1, &["win"], &["ja", "en"]);
+    let mut env = Env::new(api, data);
+
+    let hook = unwrap(env.send(exec::SetHook::new("r10".to_owned(), "r10".to_owned(), "/HS-8@0".to_owned(), author())));
+    assert_eq!(hook.vn_id, 1);
+    assert_eq!(hook.release_id, Some(10));
+
+    //Release is stored, so it is not requested again.
+    let gets = env.server.gets();
+    let hook = unwrap(env.send(exec::SetHook::new("r10".to_owned(), "r10".to_owned(), "/HS-4@0".to_owned(), author())));
+    assert_eq!(hook.release_id, Some(10));
+    assert_eq!(env.server.gets(), gets);
+
+    let data = unwrap(env.send(exec::GetHook("v1".to_owned())));
+    assert_eq!(data.hooks.len(), 1);
+    let release = data.release(&data.hooks[0]).expect("Hook to have release");
+    assert_eq!(release.title, "Ever17 -the out of infinity- Premium Edition");
+    assert_eq!(release.platforms, "win");
+    assert_eq!(release.languages, "ja,en");
+
+    match env.send(exec::SetHook::new("r11".to_owned(), "r11".to_owned(), "/HS-8@0".to_owned(), author())) {
+        Err(ResponseError::UnknownRelease) => (),
+        Err(error) => panic!("Unexpected error: {}", error),
+        Ok(_) => panic!("Unexpected success"),
+    }
+}
+
+fn should_find_vn_by_executable(api: Api) {
+    let data = data().release_on(10, "Ever17 -the out of infinity- Premium Edition", 1, &["win"], &["ja", "en"]);
+    let mut env = Env::new(api, data);
+    const HASH: &'static str = "D41D8CD98F00B204E9800998ECF8427E";
+
+    unwrap(env.send(exec::SetHook::new("v1".to_owned(), "1.0".to_owned(), "/HS-8@0".to_owned(), author())));
+    let executable = unwrap(env.send(exec::AddExecutable::new("r10".to_owned(), "C:\\Games\\Ever17PC_us.exe".to_owned(), Some(HASH.to_owned()), author())));
+    assert_eq!(executable.name, "ever17pc_us.exe");
+    assert_eq!(executable.hash.as_ref().map(|hash| hash.as_str()), Some("d41d8cd98f00b204e9800998ecf8427e"));
+    assert_eq!(executable.vn_id, 1);
+    assert_eq!(executable.release_id, Some(10));
+
+    let found = unwrap(env.send(exec::FindExecutable::new("EVER17PC_US.EXE".to_owned(), None)));
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].data.data.id, 1);
+    assert_eq!(found[0].data.hooks.len(), 1);
+    assert_eq!(found[0].data.executables.len(), 1);
+
+    let found = unwrap(env.send(exec::FindExecutable::new("ever17pc_us.exe".to_owned(), Some(HASH.to_owned()))));
+    assert_eq!(found.len(), 1);
+    let found = unwrap(env.send(exec::FindExecutable::new("game.exe".to_owned(), None)));
+    assert_eq!(found.len(), 0);
+
+    //Invalid executable is rejected before VN is looked up.
+    let gets = env.server.gets();
+    match env.send(exec::AddExecutable::new("v2".to_owned(), "game.exe".to_owned(), Some("abc".to_owned()), author())) {
+        Err(ResponseError::InvalidExecutable(_)) => (),
+        Err(error) => panic!("Unexpected error: {}", error),
+        Ok(_) => panic!("Invalid executable is not supposed to be stored"),
+    }
+    assert_eq!(env.server.gets(), gets);
+
+    assert_eq!(unwrap(env.send(exec::DelExecutable::new("v1".to_owned(), "ever17pc_us.exe".to_owned(), Some(HASH.to_owned()), author()))), 1);
+    assert_eq!(unwrap(env.send(exec::DelExecutable::new("v1".to_owned(), "ever17pc_us.exe".to_owned(), Some(HASH.to_owned()), author()))), 0);
+}
+
+fn should_complete_requests_after_executor_restart(api: Api) {
+    let mut env = Env::new(api, data().throttle(2, 0.2));
+
+    //Both requests are delayed by throttling, while executor that handled them is stopped.
+    let set_hook = env.executor.send(exec::SetHook::new("Ever17".to_owned(), "1.0".to_owned(), "/HS-8@0".to_owned(), author()));
+    let find_vn = env.executor.send(exec::FindVn::new("Never7".to_owned()));
+    env.restart_executor();
+
+    //Requests are either completed or cleanly cancelled, but must not touch stopped executor.
+    match env.system.block_on(set_hook) {
+        Ok(hook) => {
+            assert_eq!(unwrap(hook).code, "/HS-8@0");
+
+            let data = unwrap(env.send(exec::GetHook("v1".to_owned())));
+            assert_eq!(data.hooks.len(), 1);
+            assert_eq!(data.hooks[0].code, "/HS-8@0");
+        },
+        Err(MailboxError::Closed) => (),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+    match env.system.block_on(find_vn) {
+        Ok(vn) => assert_eq!(unwrap(vn).id, 3),
+        Err(MailboxError::Closed) => (),
+        Err(error) => panic!("Unexpected error: {}", error),
+    }
+}
+
+///Runs scenarios against each VNDB API.
+macro_rules! scenarios {
+    ($($name:ident),*) => {
+        mod tcp {
+            $(
+            #[test]
+            fn $name() {
+                super::$name(super::Api::Tcp)
+            }
+            )*
+        }
+
+        mod kana {
+            $(
+            #[test]
+            fn $name() {
+                super::$name(super::Api::Kana)
+            }
+            )*
+        }
+    }
+}
+
+scenarios!(
+    should_find_vn_by_exact_title,
+    should_find_vn_by_partial_title,
+    should_not_find_ambiguous_vn,
+    should_cache_vndb_responses,
+    should_retry_throttled_request,
+    should_report_throttling,
+    should_time_out_request,
+    should_search_vn_on_all_pages,
+    should_not_get_hook_of_unknown_vn,
+    should_set_and_get_hook_by_id,
+    should_set_hook_by_title,
+    should_queue_submitted_hook,
+    should_normalize_and_validate_hook_code,
+    should_get_hook_by_normalized_title,
+    should_get_hook_by_alias,
+    should_set_hook_for_release,
+    should_find_vn_by_executable,
+    should_complete_requests_after_executor_restart
+);
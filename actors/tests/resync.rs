@@ -0,0 +1,67 @@
+extern crate actix;
+extern crate tokio;
+
+extern crate actors;
+extern crate mock;
+
+use actix::prelude::*;
+use tokio::timer::Delay;
+
+use actors::resync::{Resync, SyncBatch};
+use actors::vndb::{Vndb, Endpoint};
+use actors::db::{self, Db};
+use mock::vndb::{Data, Server};
+
+use std::time::{Duration, Instant};
+
+fn put_vn(id: u64, title: &str) -> db::PutVn {
+    db::PutVn {
+        id,
+        title: title.to_owned(),
+        original: None,
+        aliases: Vec::new()
+    }
+}
+
+#[test]
+fn should_sync_stored_vns() {
+    let data = Data::new().vn_aliased(1, "Ever17 -the out of infinity-", Some("Ever17"), Some("E17"))
+                          .vn(2, "Never7 -the end of infinity-", None);
+
+    let mut system = System::new("test");
+    let server = Server::start(data).expect("To start mock VNDB");
+    let addr = server.addr();
+    let vndb: Addr<_> = Supervisor::start(move |_| Vndb::with_endpoint(Endpoint::Plain(addr)));
+    let db = Db::start_threaded_at(1, ":memory:".to_owned());
+    let resync = Resync::new(vndb.recipient(), db.clone()).start();
+
+    //Requests are rejected until VNDB client is connected.
+    for _ in 0..200 {
+        if server.requests().iter().any(|request| request.starts_with("login")) {
+            break;
+        }
+
+        let _ = system.block_on(Delay::new(Instant::now() + Duration::from_millis(10)));
+    }
+
+    for &(id, title) in [(1, "Ever 17"), (2, "Never7 -the end of infinity-"), (3, "Removed VN")].iter() {
+        system.block_on(db.send(put_vn(id, title))).expect("To deliver message").expect("To put VN");
+    }
+
+    let report = system.block_on(resync.send(SyncBatch)).expect("To deliver message").expect("To sync");
+    assert_eq!(report.checked, 3);
+    assert_eq!(report.renamed, vec![(1, "Ever 17".to_owned(), "Ever17 -the out of infinity-".to_owned())]);
+    assert_eq!(report.aliases, vec![1]);
+    assert_eq!(report.missing, vec![3]);
+    //All VNs are requested at once.
+    assert_eq!(server.gets(), 1);
+
+    let vns = system.block_on(db.send(db::FindVn("E17".to_owned()))).expect("To deliver message").expect("To find VN");
+    assert_eq!(vns.len(), 1);
+    assert_eq!(vns[0].title, "Ever17 -the out of infinity-");
+
+    //Everything is up to date now.
+    let report = system.block_on(resync.send(SyncBatch)).expect("To deliver message").expect("To sync");
+    assert_eq!(report.checked, 0);
+    assert_eq!(server.gets(), 1);
+}
@@ -0,0 +1,557 @@
+//!Frontend-neutral execution of commands.
+//!
+//!Frontends parse command, determine caller's role
+//!and send it to `Dispatcher`, which replies with `Reply`.
+
+extern crate actix;
+extern crate futures;
+extern crate actors;
+extern crate utils;
+
+use self::actix::prelude::*;
+use self::futures::{future, Future};
+use self::actors::db::models::{Author, Frontend, PendingHook, Review};
+use self::utils::duration;
+
+use ::fmt::Display;
+use ::collections::HashMap;
+
+use ::http::kouryaku::{Kouryaku, Find as FindKouryaku};
+use ::permission::Role;
+use ::ignore::{Ignores, normalize_mask};
+
+use super::{Command, Ref, Text, SetHook, VndbRequestType, DEFAULT_WEB_URL};
+
+macro_rules! try_option {
+    ($result:expr, $warn:expr) => { match $result {
+        Some(result) => result,
+        None => {
+            warn!($warn);
+            return None;
+        }
+    }}
+}
+
+///Issuer of command.
+#[derive(Clone, Debug)]
+pub struct Caller {
+    pub name: String,
+    pub frontend: Frontend,
+    pub role: Role,
+    ///Frontend specific address to send notifications to.
+    pub contact: Option<String>,
+}
+
+impl Caller {
+    pub fn new<T: Into<String>>(name: T, frontend: Frontend, role: Role) -> Self {
+        Self {
+            name: name.into(),
+            frontend,
+            role,
+            contact: None
+        }
+    }
+
+    #[inline]
+    pub fn with_contact<T: Into<String>>(mut self, contact: T) -> Self {
+        self.contact = Some(contact.into());
+        self
+    }
+
+    #[inline]
+    pub fn author(&self) -> Author {
+        Author::new(self.name.clone(), self.frontend)
+    }
+}
+
+///Result of command execution.
+#[derive(Debug)]
+pub enum Reply {
+    ///Nothing to send back.
+    None,
+    ///Single message.
+    Text(String),
+    ///Several independent messages.
+    Lines(Vec<String>),
+    ///Bot should be shut down.
+    Shutdown,
+}
+
+impl Reply {
+    #[inline]
+    fn lines(lines: Vec<String>) -> Self {
+        match lines.len() {
+            0 => Reply::None,
+            _ => Reply::Lines(lines)
+        }
+    }
+}
+
+impl From<String> for Reply {
+    fn from(text: String) -> Self {
+        Reply::Text(text)
+    }
+}
+
+impl From<Text> for Reply {
+    fn from(text: Text) -> Self {
+        Reply::Text(text.0)
+    }
+}
+
+type DispatchFuture = Box<Future<Item=Reply, Error=()>>;
+
+///Turns outcome of request into reply.
+///
+///Errors are reported to the caller as they are.
+fn reply<F, T, E, R>(name: &'static str, request: F, on_success: R) -> DispatchFuture
+    where F: Future<Item=Result<T, E>, Error=MailboxError> + 'static,
+          E: Display,
+          R: FnOnce(T) -> Reply + 'static
+{
+    Box::new(request.then(move |result| Ok(match result {
+        Ok(Ok(result)) => on_success(result),
+        Ok(Err(error)) => Reply::Text(format!("{}", error)),
+        Err(error) => {
+            error!("Dispatcher: error processing {}: {}", name, error);
+            Text::error(error).into()
+        }
+    })))
+}
+
+///Describes VNDB object found by reference.
+fn ref_text(result: &actors::vndb::response::Results, kind: &VndbRequestType, url: bool) -> Option<String> {
+    let items = try_option!(result.get("items"), "VNDB results is missing items field!");
+    let item = try_option!(items.get(0), "VNDB results's items is empty field!");
+    let name = try_option!(item.get("title").or(item.get("name")).or(item.get("username")),
+                           "VNDB results's item is missing title/name field!");
+    let kind = kind.short();
+    let id = item.get("id").unwrap();
+
+    Some(match url {
+        true => format!("{0}{1}: {2} - https://vndb.org/{0}{1}", kind, id, name),
+        false => format!("{0}{1}: {2}", kind, id, name),
+    })
+}
+
+///Describes outcome of review to submitter.
+fn review_text(pending: &PendingHook) -> String {
+    let reviewer = pending.reviewer.as_ref().map(|reviewer| reviewer.as_str()).unwrap_or("moderator");
+
+    match (pending.review(), pending.reason.as_ref()) {
+        (Review::Rejected, Some(reason)) => format!("Your hook '{}' for {} ({}) is rejected by {}: {}", pending.code, pending.vn_title, pending.version, reviewer, reason),
+        (review, _) => format!("Your hook '{}' for {} ({}) is {} by {}", pending.code, pending.vn_title, pending.version, review, reviewer),
+    }
+}
+
+///Lists links to exports of VN's hooks or whole DB.
+fn export_links(web_url: &str, vn: Option<i64>) -> String {
+    actors::export::FORMATS.iter().map(|format| format!("{}{}", web_url, format.path(vn))).collect::<Vec<_>>().join(" | ")
+}
+
+///Executes commands on behalf of all frontends.
+pub struct Dispatcher {
+    executor: Addr<actors::exec::Executor>,
+    db: Addr<actors::db::Db>,
+    ignores: Ignores,
+    notifiers: HashMap<Frontend, Recipient<Notify>>,
+    web_url: String,
+}
+
+impl Dispatcher {
+    pub fn new(executor: Addr<actors::exec::Executor>, db: Addr<actors::db::Db>, ignores: Ignores) -> Self {
+        Self {
+            executor,
+            db,
+            ignores,
+            notifiers: HashMap::new(),
+            web_url: DEFAULT_WEB_URL.to_owned(),
+        }
+    }
+
+    ///Sets address of web interface used in links to exports.
+    pub fn with_web_url<T: Into<String>>(mut self, web_url: T) -> Self {
+        self.web_url = web_url.into();
+        self
+    }
+
+    ///Queues hook of untrusted caller for review.
+    fn submit_hook(&mut self, set_hook: SetHook, caller: Caller) -> DispatchFuture {
+        let SetHook {title, version, code} = set_hook;
+
+        let submit = actors::exec::SubmitHook::new(title.clone(), version, code, caller.author(), caller.contact.clone());
+        let submit = self.executor.send(submit);
+        reply("SubmitHook", submit, move |pending| format!("Hook '{}' for VN {} is submitted for review as #{}", pending.code, title, pending.id).into())
+    }
+
+    ///Sends outcome of reviewed submissions to their authors.
+    ///
+    ///Reviews are picked from DB as they can be made via web too.
+    fn notify_reviews(&mut self, ctx: &mut Context<Self>) {
+        if self.notifiers.is_empty() {
+            return;
+        }
+
+        let frontends = self.notifiers.keys().cloned().collect();
+        let take_reviewed = self.db.send(actors::db::TakeReviewedSubmissions(frontends)).into_actor(self);
+        let take_reviewed = take_reviewed.map(|result, act, _ctx| match result {
+            Ok(reviewed) => for pending in reviewed {
+                let contact = pending.contact.clone().unwrap_or_default();
+                let notifier = match Frontend::from_str(&pending.frontend) {
+                    Some(frontend) => act.notifiers.get(&frontend),
+                    None => None
+                };
+
+                match notifier {
+                    Some(notifier) => if let Err(error) = notifier.do_send(Notify::new(contact, review_text(&pending))) {
+                        warn!("Dispatcher: unable to notify about #{}: {}", pending.id, error);
+                    },
+                    None => warn!("Dispatcher: no notifier for #{}", pending.id),
+                }
+            },
+            Err(error) => error!("Dispatcher: Unable to load reviewed submissions: {}", error)
+        }).map_err(|error, _act, _ctx| {
+            error!("Dispatcher: error processing TakeReviewedSubmissions: {}", error)
+        });
+
+        ctx.spawn(take_reviewed);
+    }
+
+    fn get_ref(&self, reference: Ref) -> Box<Future<Item=Option<String>, Error=()>> {
+        let Ref {kind, id, url} = reference;
+
+        let get_ref = actors::exec::GetVndbObject::new(id, kind.clone());
+        let get_ref = self.executor.send(get_ref).then(move |result| Ok(match result {
+            Ok(Ok(result)) => ref_text(&result, &kind, url),
+            //References to missing objects are common in chat, so they're ignored silently.
+            Ok(Err(actors::exec::ResponseError::UnknownObject(_, _))) => None,
+            Ok(Err(error)) => {
+                warn!("GetRef failed: {}", error);
+                None
+            },
+            Err(error) => {
+                error!("Dispatcher: error processing GetRef: {}", error);
+                None
+            }
+        }));
+
+        Box::new(get_ref)
+    }
+
+    fn execute(&mut self, cmd: Command, caller: Caller) -> DispatchFuture {
+        match cmd {
+            Command::Text(text) => Box::new(future::ok(Reply::from(text))),
+            Command::GetVn(get_vn) => {
+                let get_vn = self.executor.send(actors::exec::FindVn::new(get_vn.title));
+                reply("GetVn", get_vn, |vn| format!("{} - https://vndb.org/v{}", vn.title.as_ref().unwrap(), vn.id).into())
+            },
+            Command::GetHook(get_hook) => {
+                let get_hook = self.executor.send(actors::exec::GetHook(get_hook.title));
+                reply("GetHook", get_hook, |data| format!("{}", data).into())
+            },
+            Command::SetHook(set_hook) => {
+                let super::SetHook {title, version, code} = set_hook;
+
+                let set_hook = self.executor.send(actors::exec::SetHook::new(title.clone(), version, code, caller.author()));
+                reply("SetHook", set_hook, move |hook| format!("Added hook '{}' for VN: {}", hook.code, title).into())
+            },
+            Command::DelHook(del_hook) => {
+                let super::DelHook {title, version} = del_hook;
+
+                let del_hook = self.executor.send(actors::exec::DelHook::new(title.clone(), version, caller.author()));
+                reply("DelHook", del_hook, move |result| match result {
+                    0 => format!("{}: No hook to remove.", title).into(),
+                    _ => format!("{}: Removed hook.", title).into(),
+                })
+            },
+            Command::DelVn(del_vn) => {
+                let title = del_vn.title;
+
+                let del_vn = self.executor.send(actors::exec::DelVn::new(title.clone(), caller.author()));
+                reply("DelVn", del_vn, move |result| match result {
+                    0 => format!("{}: No such VN exists in DB.", title).into(),
+                    _ => format!("{}: removed from DB.", title).into(),
+                })
+            },
+            Command::History(history) => {
+                let title = history.title;
+
+                let history = self.executor.send(actors::exec::GetHistory(title.clone()));
+                reply("History", history, move |revisions| match revisions.len() {
+                    0 => format!("{}: No changes recorded.", title).into(),
+                    _ => Reply::lines(revisions.iter().map(|revision| format!("{}", revision)).collect()),
+                })
+            },
+            Command::Restore(restore) => {
+                let id = restore.id;
+
+                let restore = self.executor.send(actors::exec::RestoreRevision::new(id, caller.author()));
+                reply("Restore", restore, move |result| match result {
+                    actors::db::Restore::NotFound => format!("#{}: No such revision.", id).into(),
+                    actors::db::Restore::Put(hook) => format!("#{}: Restored hook '{}' for version '{}'", id, hook.code, hook.version).into(),
+                    actors::db::Restore::Removed(_) => format!("#{}: Removed added hook.", id).into(),
+                })
+            },
+            Command::Aliases(aliases) => {
+                let get_aliases = self.executor.send(actors::exec::GetAliases(aliases.title));
+                reply("Aliases", get_aliases, |(vn, aliases)| match aliases.len() {
+                    0 => format!("{}: No aliases.", vn.title).into(),
+                    _ => format!("{}: {}", vn.title, aliases.iter().map(|alias| alias.alias.as_str()).collect::<Vec<_>>().join(" | ")).into(),
+                })
+            },
+            Command::AddAlias(alias) => {
+                let super::Alias {title, alias} = alias;
+
+                let add_alias = self.executor.send(actors::exec::AddAlias::new(title, alias, caller.author()));
+                reply("AddAlias", add_alias, |alias| format!("Added alias '{}' for v{}", alias.alias, alias.vn_id).into())
+            },
+            Command::DelAlias(alias) => {
+                let super::Alias {title, alias} = alias;
+
+                let del_alias = self.executor.send(actors::exec::DelAlias::new(title.clone(), alias.clone(), caller.author()));
+                reply("DelAlias", del_alias, move |result| match result {
+                    0 => format!("{}: No alias '{}' to remove.", title, alias).into(),
+                    _ => format!("{}: Removed alias '{}'.", title, alias).into(),
+                })
+            },
+            Command::GetExe(exe) => {
+                let super::Exe {name, hash} = exe;
+
+                let find = self.executor.send(actors::exec::FindExecutable::new(name.clone(), hash));
+                reply("GetExe", find, move |found| match found.len() {
+                    0 => format!("No VN is known for executable '{}'", name).into(),
+                    _ => Reply::lines(found.iter().map(|found| format!("{}", found)).collect()),
+                })
+            },
+            Command::AddExe(exe) => {
+                let super::ExeLink {title, name, hash} = exe;
+
+                let add_exe = self.executor.send(actors::exec::AddExecutable::new(title, name, hash, caller.author()));
+                reply("AddExe", add_exe, |executable| format!("Added executable {}", executable).into())
+            },
+            Command::DelExe(exe) => {
+                let super::ExeLink {title, name, hash} = exe;
+
+                let del_exe = self.executor.send(actors::exec::DelExecutable::new(title.clone(), name.clone(), hash, caller.author()));
+                reply("DelExe", del_exe, move |result| match result {
+                    0 => format!("{}: No executable '{}' to remove.", title, name).into(),
+                    _ => format!("{}: Removed executable '{}'.", title, name).into(),
+                })
+            },
+            Command::Export(export) => match export.title {
+                Some(title) => {
+                    let web_url = self.web_url.clone();
+                    let get_hook = self.executor.send(actors::exec::GetHook(title));
+                    reply("Export", get_hook, move |data| match data.hooks.len() {
+                        0 => format!("No hook exists for VN '{}'", data.data.title).into(),
+                        _ => format!("{}: {}", data.data.title, export_links(&web_url, Some(data.data.id))).into(),
+                    })
+                },
+                None => Box::new(future::ok(Reply::Text(format!("All hooks: {}", export_links(&self.web_url, None))))),
+            },
+            Command::Kouryaku(kouryaku) => {
+                let find = System::current().registry().get::<Kouryaku>().send(FindKouryaku(kouryaku.title));
+                reply("Kouryaku", find, |result| match result {
+                    Some((title, url)) => format!("{} - {}", title, url).into(),
+                    None => Reply::Text("Unable to find kouryaku".to_string()),
+                })
+            },
+            Command::Refs(refs) => {
+                let refs = refs.refs.iter().filter_map(|reference| reference.clone())
+                                           .map(|reference| self.get_ref(reference))
+                                           .collect::<Vec<_>>();
+
+                Box::new(future::join_all(refs).map(|lines| Reply::lines(lines.into_iter().filter_map(|line| line).collect())))
+            },
+            Command::Ignore(ignore) => {
+                let ignores = self.ignores.clone();
+                let put_ignore = actors::db::PutIgnore {
+                    pattern: normalize_mask(&ignore.mask),
+                    reason: ignore.reason,
+                    author: caller.author()
+                };
+
+                let put_ignore = self.db.send(put_ignore).map(|result| result.map_err(|error| Text::error(error).0));
+                reply("Ignore", put_ignore, move |ignore| {
+                    let text = format!("Added '{}' to ignore list", ignore.pattern);
+                    ignores.add(ignore);
+                    text.into()
+                })
+            },
+            Command::Unignore(mask) => {
+                let ignores = self.ignores.clone();
+                let pattern = normalize_mask(&mask);
+                let del_ignore = actors::db::DelIgnore {
+                    pattern: pattern.clone(),
+                    author: caller.author()
+                };
+
+                let del_ignore = self.db.send(del_ignore).map(|result| result.map_err(|error| Text::error(error).0));
+                reply("Unignore", del_ignore, move |result| match result {
+                    0 => format!("'{}' is not ignored", pattern).into(),
+                    _ => {
+                        ignores.remove(&pattern);
+                        format!("Removed '{}' from ignore list", pattern).into()
+                    }
+                })
+            },
+            Command::CacheStats => {
+                let get_stats = self.executor.send(actors::exec::GetCacheStats).map(|stats| Ok::<_, String>(stats));
+                reply("CacheStats", get_stats, |stats| format!("VNDB cache: {}", stats).into())
+            },
+            Command::Pending => {
+                let get_pending = self.db.send(actors::db::GetPendingHooks).map(|result| result.map_err(|error| Text::error(error).0));
+                reply("Pending", get_pending, |pending| match pending.len() {
+                    0 => "No submissions to review.".to_owned().into(),
+                    _ => Reply::lines(pending.iter().map(|pending| format!("{}", pending)).collect()),
+                })
+            },
+            Command::Approve(approve) => {
+                let super::Approve {id, code} = approve;
+                let approve = actors::db::ApprovePendingHook {
+                    id,
+                    code,
+                    reviewer: caller.author()
+                };
+
+                let approve = self.db.send(approve).map(|result| result.map_err(|error| Text::error(error).0));
+                reply("Approve", approve, move |result| match result {
+                    Some((pending, hook)) => format!("#{}: Added hook '{}' for VN: {}", id, hook.code, pending.vn_title).into(),
+                    None => format!("#{}: No such submission to review.", id).into(),
+                })
+            },
+            Command::Reject(reject) => {
+                let super::Reject {id, reason} = reject;
+                let reject = actors::db::RejectPendingHook {
+                    id,
+                    reason,
+                    reviewer: caller.author()
+                };
+
+                let reject = self.db.send(reject).map(|result| result.map_err(|error| Text::error(error).0));
+                reply("Reject", reject, move |result| match result {
+                    Some(pending) => format!("#{}: Rejected hook '{}' for VN: {}", id, pending.code, pending.vn_title).into(),
+                    None => format!("#{}: No such submission to review.", id).into(),
+                })
+            },
+            Command::IgnoreList => Box::new(future::ok(Reply::Text(format!("Ignore list: {}", self.ignores.patterns().join(", "))))),
+            Command::Shutdown => {
+                warn!("Shutdown command is issued by {}", caller.author());
+                Box::new(future::ok(Reply::Shutdown))
+            }
+        }
+    }
+}
+
+impl Actor for Dispatcher {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        let ignores = self.ignores.clone();
+        let get_ignores = self.db.send(actors::db::GetIgnores).map(move |result| match result {
+            Ok(list) => {
+                info!("Dispatcher: loaded {} ignores", list.len());
+                ignores.set(list);
+            },
+            Err(error) => error!("Dispatcher: Unable to load ignore list: {}", error)
+        }).map_err(|error| {
+            error!("Dispatcher: error processing GetIgnores: {}", error)
+        });
+
+        ctx.spawn(get_ignores.into_actor(self));
+
+        ctx.run_interval(duration::ms(NOTIFY_INTERVAL_MS), |act, ctx| act.notify_reviews(ctx));
+    }
+}
+
+///Interval of checking for reviewed submissions.
+const NOTIFY_INTERVAL_MS: u64 = 30_000;
+
+///Text to deliver to user outside of command's reply.
+pub struct Notify {
+    ///Frontend specific address of user.
+    pub contact: String,
+    pub text: String,
+}
+
+impl Notify {
+    pub fn new(contact: String, text: String) -> Self {
+        Self {
+            contact,
+            text
+        }
+    }
+}
+
+impl Message for Notify {
+    type Result = ();
+}
+
+///Registers frontend's recipient of notifications.
+pub struct Subscribe {
+    pub frontend: Frontend,
+    pub recipient: Recipient<Notify>,
+}
+
+impl Subscribe {
+    pub fn new(frontend: Frontend, recipient: Recipient<Notify>) -> Self {
+        Self {
+            frontend,
+            recipient
+        }
+    }
+}
+
+impl Message for Subscribe {
+    type Result = ();
+}
+
+impl Handler<Subscribe> for Dispatcher {
+    type Result = ();
+
+    fn handle(&mut self, msg: Subscribe, _ctx: &mut Self::Context) -> Self::Result {
+        let Subscribe {frontend, recipient} = msg;
+
+        info!("Dispatcher: {} subscribes to notifications", frontend);
+        self.notifiers.insert(frontend, recipient);
+    }
+}
+
+///Executes command on behalf of caller.
+pub struct Dispatch {
+    pub cmd: Command,
+    pub caller: Caller,
+}
+
+impl Dispatch {
+    pub fn new(cmd: Command, caller: Caller) -> Self {
+        Self {
+            cmd,
+            caller
+        }
+    }
+}
+
+impl Message for Dispatch {
+    type Result = Result<Reply, ()>;
+}
+
+impl Handler<Dispatch> for Dispatcher {
+    type Result = DispatchFuture;
+
+    fn handle(&mut self, msg: Dispatch, _ctx: &mut Self::Context) -> Self::Result {
+        let Dispatch {cmd, caller} = msg;
+
+        let required = cmd.required_role();
+        if caller.role < required {
+            if let Command::SetHook(set_hook) = cmd {
+                return self.submit_hook(set_hook, caller);
+            }
+
+            info!("Dispatcher: {} is not allowed to use command requiring {} role", caller.author(), required);
+            return Box::new(future::ok(Reply::Text(format!("You're not allowed to do this. Required role: {}", required))));
+        }
+
+        self.execute(cmd, caller)
+    }
+}
@@ -3,15 +3,24 @@ extern crate vndb;
 extern crate actors;
 
 mod args;
+mod dispatcher;
 
 use self::args::shell_split;
+pub use self::dispatcher::{Dispatcher, Dispatch, Caller, Reply, Notify, Subscribe};
 
 use self::vndb::protocol::message::request::get::Type as VndbRequestType;
 use ::fmt::Display;
+use ::permission::Role;
 
-pub const HELP: &'static str = "Available commands: .ping, .vn, .hook, .set_hook, .del_hook, .del_vn, .kouryaku";
-pub const SET_HOOK_USAGE: &'static str = "Usage: <title> <version> <code>";
+pub const HELP: &'static str = "Available commands: .ping, .vn, .hook, .set_hook, .del_hook, .del_vn, .history, .restore, .kouryaku, .alias, .exe, .export, .pending, .approve, .reject, .cache";
+pub const ALIAS_USAGE: &'static str = "Usage: <title> | add <title> <alias> | del <title> <alias>";
+pub const EXE_USAGE: &'static str = "Usage: <name> [hash] | add <title> <name> [hash] | del <title> <name> [hash]";
+pub const SET_HOOK_USAGE: &'static str = "Usage: <title> <version> <code> | <release> <code>";
 pub const DEL_HOOK_USAGE: &'static str = "Usage: <title> <version>";
+pub const APPROVE_USAGE: &'static str = "Usage: <id> [code]";
+pub const REJECT_USAGE: &'static str = "Usage: <id> [reason]";
+///Default address of web interface, which serves exports.
+pub const DEFAULT_WEB_URL: &'static str = "http://roseline.duckdns.org";
 
 ///Gets VN info
 pub struct GetVn {
@@ -81,6 +90,66 @@ pub struct Kouryaku {
     pub title: String
 }
 
+//.ignore
+pub struct Ignore {
+    pub mask: String,
+    pub reason: Option<String>
+}
+
+//.history
+pub struct History {
+    pub title: String
+}
+
+//.restore
+pub struct Restore {
+    pub id: u64
+}
+
+//.alias
+pub struct Aliases {
+    pub title: String
+}
+
+//.alias add and .alias del
+pub struct Alias {
+    pub title: String,
+    pub alias: String,
+}
+
+//.exe
+pub struct Exe {
+    pub name: String,
+    pub hash: Option<String>,
+}
+
+//.exe add and .exe del
+pub struct ExeLink {
+    ///VN or release.
+    pub title: String,
+    pub name: String,
+    pub hash: Option<String>,
+}
+
+//.export
+pub struct Export {
+    ///VN to export, whole DB otherwise.
+    pub title: Option<String>
+}
+
+//.approve
+pub struct Approve {
+    pub id: u64,
+    ///Replaces submitted code.
+    pub code: Option<String>
+}
+
+//.reject
+pub struct Reject {
+    pub id: u64,
+    pub reason: Option<String>
+}
+
 pub enum Command {
     Text(Text),
     GetVn(GetVn),
@@ -89,9 +158,23 @@ pub enum Command {
     DelHook(DelHook),
     DelVn(DelVn),
     Kouryaku(Kouryaku),
+    History(History),
+    Restore(Restore),
+    Aliases(Aliases),
+    AddAlias(Alias),
+    DelAlias(Alias),
+    GetExe(Exe),
+    AddExe(ExeLink),
+    DelExe(ExeLink),
+    Export(Export),
     Refs(Refs),
-    Ignore(String),
+    Ignore(Ignore),
+    Unignore(String),
     IgnoreList,
+    CacheStats,
+    Pending,
+    Approve(Approve),
+    Reject(Reject),
     Shutdown
 }
 
@@ -142,7 +225,31 @@ pub fn extract_vndb_references(text: &str) -> Option<Refs> {
     Some(result)
 }
 
+///Splits arguments of review commands into submission's id and optional remainder.
+fn parse_review_args(arg: Option<&str>) -> Option<Result<(u64, Option<String>), ::std::num::ParseIntError>> {
+    let mut arg = arg?.trim().splitn(2, char::is_whitespace);
+    let id = arg.next().unwrap_or("").trim_left_matches('#');
+    let rest = arg.next().map(|rest| rest.trim().to_owned()).filter(|rest| rest.len() > 0);
+
+    Some(id.parse::<u64>().map(|id| (id, rest)))
+}
+
 impl Command {
+    ///Returns minimal role required to execute command.
+    ///
+    ///Hooks set by callers without required role are queued for review instead.
+    pub fn required_role(&self) -> Role {
+        match self {
+            &Command::SetHook(_) | &Command::AddAlias(_) | &Command::AddExe(_) => Role::Trusted,
+            &Command::DelHook(_) | &Command::DelVn(_) | &Command::Restore(_) | &Command::Ignore(_) | &Command::Unignore(_) => Role::Moderator,
+            &Command::DelAlias(_) | &Command::DelExe(_) => Role::Moderator,
+            &Command::Pending | &Command::Approve(_) | &Command::Reject(_) => Role::Moderator,
+            &Command::CacheStats => Role::Moderator,
+            &Command::Shutdown => Role::Owner,
+            _ => Role::Everyone,
+        }
+    }
+
     pub fn from_str(text: &str) -> Option<Command> {
         lazy_static! {
             static ref EXTRACT_CMD: regex::Regex = regex::Regex::new("^\\s*\\.([^\\s]*)(\\s+(.+))*").unwrap();
@@ -159,10 +266,20 @@ impl Command {
                 Some("ping") => Some(Command::Text("pong".into())),
                 Some("help") => Some(Command::Text(HELP.into())),
                 Some("ignore_list") => Some(Command::IgnoreList),
+                Some("cache") => Some(Command::CacheStats),
                 Some("ignore") => match captures.get(ARG_IDX) {
-                    Some(name) => Some(Command::Ignore(name.as_str().to_owned())),
+                    Some(arg) => {
+                        let mut arg = arg.as_str().trim().splitn(2, char::is_whitespace);
+                        let mask = arg.next().unwrap_or("").to_owned();
+                        let reason = arg.next().map(|reason| reason.trim().to_owned());
+                        Some(Command::Ignore(Ignore { mask, reason }))
+                    },
                     None => Some(Command::Text("Who to ignore?".into()))
                 },
+                Some("unignore") => match captures.get(ARG_IDX) {
+                    Some(mask) => Some(Command::Unignore(mask.as_str().trim().to_owned())),
+                    None => Some(Command::Text("Who to unignore?".into()))
+                },
                 Some("vn") => match captures.get(ARG_IDX) {
                     Some(title) => Some(Command::GetVn(GetVn { title: title.as_str().to_owned() })),
                     None => Some(Command::Text("Which VN...?".into()))
@@ -179,6 +296,90 @@ impl Command {
                     Some(arg) => Some(Command::Kouryaku(Kouryaku{ title: arg.as_str().trim().to_string()})),
                     None => Some(Command::Text("For which VN...?".into()))
                 },
+                Some("export") => Some(Command::Export(Export { title: captures.get(ARG_IDX).map(|arg| arg.as_str().trim().to_string()) })),
+                Some("history") => match captures.get(ARG_IDX) {
+                    Some(arg) => Some(Command::History(History{ title: arg.as_str().trim().to_string()})),
+                    None => Some(Command::Text("For which VN...?".into()))
+                },
+                Some("restore") => match captures.get(ARG_IDX).map(|arg| arg.as_str().trim().trim_left_matches('#').parse::<u64>()) {
+                    Some(Ok(id)) => Some(Command::Restore(Restore{ id })),
+                    Some(Err(_)) => Some(Command::Text("Revision should be a number".into())),
+                    None => Some(Command::Text("Which revision...?".into()))
+                },
+                Some("alias") => {
+                    let arg = match captures.get(ARG_IDX) {
+                        Some(arg) => arg.as_str().trim(),
+                        None => return Some(Command::Text(ALIAS_USAGE.into())),
+                    };
+
+                    let action = arg.split_whitespace().next().unwrap_or("");
+                    if action != "add" && action != "del" {
+                        return Some(Command::Aliases(Aliases { title: arg.to_string() }));
+                    }
+
+                    let args = match shell_split(arg) {
+                        Ok(args) => args,
+                        Err(error) => return Some(Command::Text(Text::error(error))),
+                    };
+
+                    if args.len() != 3 {
+                        return Some(Command::Text(format!("Invalid number of arguments {}. Expected 3", args.len()).into()))
+                    }
+
+                    let title = unsafe { args.get_unchecked(1).to_string() };
+                    let alias = unsafe { args.get_unchecked(2).to_string() };
+
+                    match action {
+                        "add" => Some(Command::AddAlias(Alias { title, alias })),
+                        _ => Some(Command::DelAlias(Alias { title, alias })),
+                    }
+                },
+                Some("exe") => {
+                    let arg = match captures.get(ARG_IDX) {
+                        Some(arg) => arg.as_str().trim(),
+                        None => return Some(Command::Text(EXE_USAGE.into())),
+                    };
+
+                    let args = match shell_split(arg) {
+                        Ok(args) => args,
+                        Err(error) => return Some(Command::Text(Text::error(error))),
+                    };
+
+                    let action = args.get(0).cloned().unwrap_or("");
+                    if action != "add" && action != "del" {
+                        if args.len() > 2 {
+                            return Some(Command::Text(format!("Invalid number of arguments {}. Expected 1 or 2", args.len()).into()))
+                        }
+
+                        let name = args.get(0).map(|name| name.to_string()).unwrap_or_default();
+                        let hash = args.get(1).map(|hash| hash.to_string());
+                        return Some(Command::GetExe(Exe { name, hash }));
+                    }
+
+                    if args.len() != 3 && args.len() != 4 {
+                        return Some(Command::Text(format!("Invalid number of arguments {}. Expected 3 or 4", args.len()).into()))
+                    }
+
+                    let title = unsafe { args.get_unchecked(1).to_string() };
+                    let name = unsafe { args.get_unchecked(2).to_string() };
+                    let hash = args.get(3).map(|hash| hash.to_string());
+
+                    match action {
+                        "add" => Some(Command::AddExe(ExeLink { title, name, hash })),
+                        _ => Some(Command::DelExe(ExeLink { title, name, hash })),
+                    }
+                },
+                Some("pending") => Some(Command::Pending),
+                Some("approve") => match parse_review_args(captures.get(ARG_IDX).map(|arg| arg.as_str())) {
+                    Some(Ok((id, code))) => Some(Command::Approve(Approve { id, code })),
+                    Some(Err(_)) => Some(Command::Text("Submission should be a number".into())),
+                    None => Some(Command::Text(APPROVE_USAGE.into()))
+                },
+                Some("reject") => match parse_review_args(captures.get(ARG_IDX).map(|arg| arg.as_str())) {
+                    Some(Ok((id, reason))) => Some(Command::Reject(Reject { id, reason })),
+                    Some(Err(_)) => Some(Command::Text("Submission should be a number".into())),
+                    None => Some(Command::Text(REJECT_USAGE.into()))
+                },
                 Some("set_hook") => {
                     let arg = match captures.get(ARG_IDX) {
                         Some(arg) => arg,
@@ -190,6 +391,18 @@ impl Command {
                         Err(error) => return Some(Command::Text(Text::error(error)))
                     };
 
+                    //Release identifies both VN and version.
+                    if args.len() == 2 && actors::db::models::release_ref(args[0]).is_some() {
+                        let release = unsafe { args.get_unchecked(0).to_string() };
+                        let code = unsafe { args.get_unchecked(1).to_string() };
+
+                        return Some(Command::SetHook(SetHook {
+                            title: release.clone(),
+                            version: release,
+                            code
+                        }));
+                    }
+
                     if args.len() != 3 {
                         return Some(Command::Text(format!("Invalid number of arguments {}. Expected 3", args.len()).into()))
                     }
@@ -244,6 +457,7 @@ impl Command {
 mod tests {
     use super::{
         Command,
+        Role,
         Text,
         Refs,
         VndbRequestType,
@@ -251,9 +465,23 @@ mod tests {
         SetHook,
         DelHook,
         DelVn,
+        History,
+        Export,
+        Restore,
+        Ignore,
+        Aliases,
+        Alias,
+        Exe,
+        ExeLink,
+        Approve,
+        Reject,
         HELP,
+        ALIAS_USAGE,
+        EXE_USAGE,
         SET_HOOK_USAGE,
-        DEL_HOOK_USAGE
+        DEL_HOOK_USAGE,
+        APPROVE_USAGE,
+        REJECT_USAGE
     };
 
     #[test]
@@ -394,6 +622,20 @@ mod tests {
             _ => panic!("Unexpected result for .set_hook")
         }
 
+        match Command::from_str(".set_hook r12345 code") {
+            Some(Command::SetHook(SetHook{title, version, code})) => {
+                assert_eq!(title, "r12345");
+                assert_eq!(version, "r12345");
+                assert_eq!(code, "code");
+            },
+            _ => panic!("Unexpected result for .set_hook")
+        }
+
+        match Command::from_str(".set_hook title code") {
+            Some(Command::Text(Text(text))) => assert!(text.len() > 0),
+            _ => panic!("Unexpected result for .set_hook")
+        }
+
         match Command::from_str(".set_hook 'title multi' version code") {
             Some(Command::SetHook(SetHook{title, version, code})) => {
                 assert_eq!(title, "title multi");
@@ -428,4 +670,235 @@ mod tests {
             _ => panic!("Unexpected result for .del_hook")
         }
     }
+
+    #[test]
+    fn should_cmd_history() {
+        match Command::from_str(".history") {
+            Some(Command::Text(Text(text))) => assert_eq!(text, "For which VN...?"),
+            _ => panic!("Unexpected result for .history")
+        }
+
+        match Command::from_str(".history v5") {
+            Some(Command::History(History{title})) => assert_eq!(title, "v5"),
+            _ => panic!("Unexpected result for .history")
+        }
+    }
+
+    #[test]
+    fn should_cmd_export() {
+        match Command::from_str(".export") {
+            Some(Command::Export(Export{title})) => assert!(title.is_none()),
+            _ => panic!("Unexpected result for .export")
+        }
+
+        match Command::from_str(".export  Ever17 ") {
+            Some(Command::Export(Export{title})) => assert_eq!(title.as_ref().map(|title| title.as_str()), Some("Ever17")),
+            _ => panic!("Unexpected result for .export")
+        }
+    }
+
+    #[test]
+    fn should_cmd_restore() {
+        match Command::from_str(".restore") {
+            Some(Command::Text(Text(text))) => assert_eq!(text, "Which revision...?"),
+            _ => panic!("Unexpected result for .restore")
+        }
+
+        match Command::from_str(".restore abc") {
+            Some(Command::Text(Text(text))) => assert_eq!(text, "Revision should be a number"),
+            _ => panic!("Unexpected result for .restore")
+        }
+
+        match Command::from_str(".restore #12") {
+            Some(Command::Restore(Restore{id})) => assert_eq!(id, 12),
+            _ => panic!("Unexpected result for .restore")
+        }
+    }
+
+    #[test]
+    fn should_require_role() {
+        let cases = [
+            (".ping", Role::Everyone),
+            (".hook title", Role::Everyone),
+            (".set_hook title version code", Role::Trusted),
+            (".del_hook title version", Role::Moderator),
+            (".del_vn title", Role::Moderator),
+            (".restore 1", Role::Moderator),
+            (".ignore nick", Role::Moderator),
+            (".unignore nick", Role::Moderator),
+            (".alias title", Role::Everyone),
+            (".alias add title nick", Role::Trusted),
+            (".alias del title nick", Role::Moderator),
+            (".exe game.exe", Role::Everyone),
+            (".exe add title game.exe", Role::Trusted),
+            (".exe del title game.exe", Role::Moderator),
+            (".pending", Role::Moderator),
+            (".cache", Role::Moderator),
+            (".approve 1", Role::Moderator),
+            (".reject 1", Role::Moderator),
+            ("._shutdown", Role::Owner),
+        ];
+
+        for &(text, role) in cases.iter() {
+            let cmd = Command::from_str(text).expect("To parse command");
+            assert_eq!(cmd.required_role(), role);
+        }
+    }
+
+    #[test]
+    fn should_cmd_ignore() {
+        match Command::from_str(".ignore") {
+            Some(Command::Text(Text(text))) => assert_eq!(text, "Who to ignore?"),
+            _ => panic!("Unexpected result for .ignore")
+        }
+
+        match Command::from_str(".ignore *!*@spam.host") {
+            Some(Command::Ignore(Ignore{mask, reason})) => {
+                assert_eq!(mask, "*!*@spam.host");
+                assert!(reason.is_none());
+            },
+            _ => panic!("Unexpected result for .ignore")
+        }
+
+        match Command::from_str(".ignore nick  spams hooks") {
+            Some(Command::Ignore(Ignore{mask, reason})) => {
+                assert_eq!(mask, "nick");
+                assert_eq!(reason.as_ref().map(|reason| reason.as_str()), Some("spams hooks"));
+            },
+            _ => panic!("Unexpected result for .ignore")
+        }
+
+        match Command::from_str(".unignore nick") {
+            Some(Command::Unignore(mask)) => assert_eq!(mask, "nick"),
+            _ => panic!("Unexpected result for .unignore")
+        }
+    }
+
+    #[test]
+    fn should_cmd_review() {
+        match Command::from_str(".pending") {
+            Some(Command::Pending) => (),
+            _ => panic!("Unexpected result for .pending")
+        }
+
+        match Command::from_str(".approve") {
+            Some(Command::Text(Text(text))) => assert_eq!(text, APPROVE_USAGE),
+            _ => panic!("Unexpected result for .approve")
+        }
+
+        match Command::from_str(".approve abc") {
+            Some(Command::Text(Text(text))) => assert_eq!(text, "Submission should be a number"),
+            _ => panic!("Unexpected result for .approve")
+        }
+
+        match Command::from_str(".approve #3") {
+            Some(Command::Approve(Approve{id, code})) => {
+                assert_eq!(id, 3);
+                assert!(code.is_none());
+            },
+            _ => panic!("Unexpected result for .approve")
+        }
+
+        match Command::from_str(".approve 3 /HS-8@0 ") {
+            Some(Command::Approve(Approve{id, code})) => {
+                assert_eq!(id, 3);
+                assert_eq!(code.as_ref().map(|code| code.as_str()), Some("/HS-8@0"));
+            },
+            _ => panic!("Unexpected result for .approve")
+        }
+
+        match Command::from_str(".reject") {
+            Some(Command::Text(Text(text))) => assert_eq!(text, REJECT_USAGE),
+            _ => panic!("Unexpected result for .reject")
+        }
+
+        match Command::from_str(".reject 4 wrong  version") {
+            Some(Command::Reject(Reject{id, reason})) => {
+                assert_eq!(id, 4);
+                assert_eq!(reason.as_ref().map(|reason| reason.as_str()), Some("wrong  version"));
+            },
+            _ => panic!("Unexpected result for .reject")
+        }
+    }
+
+    #[test]
+    fn should_cmd_alias() {
+        match Command::from_str(".alias") {
+            Some(Command::Text(Text(text))) => assert_eq!(text, ALIAS_USAGE),
+            _ => panic!("Unexpected result for .alias")
+        }
+
+        match Command::from_str(".alias Some Title") {
+            Some(Command::Aliases(Aliases{title})) => assert_eq!(title, "Some Title"),
+            _ => panic!("Unexpected result for .alias")
+        }
+
+        match Command::from_str(".alias add title") {
+            Some(Command::Text(Text(text))) => assert!(text.len() > 0),
+            _ => panic!("Unexpected result for .alias add")
+        }
+
+        match Command::from_str(".alias add v1 'Ever 17'") {
+            Some(Command::AddAlias(Alias{title, alias})) => {
+                assert_eq!(title, "v1");
+                assert_eq!(alias, "Ever 17");
+            },
+            _ => panic!("Unexpected result for .alias add")
+        }
+
+        match Command::from_str(".alias del 'Ever17 -the out of infinity-' e17") {
+            Some(Command::DelAlias(Alias{title, alias})) => {
+                assert_eq!(title, "Ever17 -the out of infinity-");
+                assert_eq!(alias, "e17");
+            },
+            _ => panic!("Unexpected result for .alias del")
+        }
+    }
+
+    #[test]
+    fn should_cmd_exe() {
+        match Command::from_str(".exe") {
+            Some(Command::Text(Text(text))) => assert_eq!(text, EXE_USAGE),
+            _ => panic!("Unexpected result for .exe")
+        }
+
+        match Command::from_str(".exe 'Ever17 PC.exe'") {
+            Some(Command::GetExe(Exe{name, hash})) => {
+                assert_eq!(name, "Ever17 PC.exe");
+                assert!(hash.is_none());
+            },
+            _ => panic!("Unexpected result for .exe")
+        }
+
+        match Command::from_str(".exe game.exe d41d8cd98f00b204e9800998ecf8427e") {
+            Some(Command::GetExe(Exe{name, hash})) => {
+                assert_eq!(name, "game.exe");
+                assert_eq!(hash.as_ref().map(|hash| hash.as_str()), Some("d41d8cd98f00b204e9800998ecf8427e"));
+            },
+            _ => panic!("Unexpected result for .exe")
+        }
+
+        match Command::from_str(".exe add v1") {
+            Some(Command::Text(Text(text))) => assert!(text.len() > 0),
+            _ => panic!("Unexpected result for .exe add")
+        }
+
+        match Command::from_str(".exe add r10 Ever17PC_us.exe") {
+            Some(Command::AddExe(ExeLink{title, name, hash})) => {
+                assert_eq!(title, "r10");
+                assert_eq!(name, "Ever17PC_us.exe");
+                assert!(hash.is_none());
+            },
+            _ => panic!("Unexpected result for .exe add")
+        }
+
+        match Command::from_str(".exe del 'Ever17 -the out of infinity-' game.exe d41d8cd98f00b204e9800998ecf8427e") {
+            Some(Command::DelExe(ExeLink{title, name, hash})) => {
+                assert_eq!(title, "Ever17 -the out of infinity-");
+                assert_eq!(name, "game.exe");
+                assert_eq!(hash.as_ref().map(|hash| hash.as_str()), Some("d41d8cd98f00b204e9800998ecf8427e"));
+            },
+            _ => panic!("Unexpected result for .exe del")
+        }
+    }
 }
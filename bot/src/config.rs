@@ -1,12 +1,18 @@
 extern crate irc;
+extern crate toml;
+extern crate actors;
 
 use self::irc::client::data::config::Config as IrcConfig;
 
 use ::std::env;
+use ::std::fs;
 use ::std::path::PathBuf;
 use ::std::ops::Deref;
+use ::std::time::Duration;
 
 use ::utils::ResultExt;
+use ::permission::Permissions;
+use ::command;
 
 const NAME: &'static str = "roseline.toml";
 
@@ -19,8 +25,89 @@ fn get_config() -> PathBuf {
     result
 }
 
+///Settings of VNDB response cache.
+///
+///Omitted values are taken from default configuration.
+#[derive(Deserialize, Default)]
+struct Cache {
+    ///Maximum number of entries per kind of lookup.
+    capacity: Option<usize>,
+    ///Time to live of found objects in seconds.
+    ttl: Option<u64>,
+    ///Time to live of not found objects in seconds.
+    not_found_ttl: Option<u64>,
+}
+
+impl Into<actors::cache::Config> for Cache {
+    fn into(self) -> actors::cache::Config {
+        let default = actors::cache::Config::default();
+
+        actors::cache::Config {
+            capacity: self.capacity.unwrap_or(default.capacity),
+            ttl: self.ttl.map(Duration::from_secs).unwrap_or(default.ttl),
+            not_found_ttl: self.not_found_ttl.map(Duration::from_secs).unwrap_or(default.not_found_ttl),
+        }
+    }
+}
+
+///VNDB API to use.
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Api {
+    Tcp,
+    Kana,
+}
+
+impl Default for Api {
+    fn default() -> Self {
+        Api::Tcp
+    }
+}
+
+///Settings of VNDB client.
+#[derive(Deserialize, Default)]
+struct Vndb {
+    #[serde(default)]
+    api: Api,
+    ///URL of Kana. Official one is used if omitted.
+    url: Option<String>,
+}
+
+impl Into<actors::backend::Backend> for Vndb {
+    fn into(self) -> actors::backend::Backend {
+        match self.api {
+            Api::Tcp => actors::backend::Backend::default(),
+            Api::Kana => actors::backend::Backend::Kana(self.url.unwrap_or_else(|| actors::kana::DEFAULT_URL.to_owned())),
+        }
+    }
+}
+
+///Settings of web interface.
+#[derive(Deserialize, Default)]
+struct Web {
+    ///Address of web interface, used in links to exports.
+    url: Option<String>,
+}
+
+///Roseline's own settings that are stored alongside IRC's.
+#[derive(Deserialize, Default)]
+struct Extra {
+    #[serde(default)]
+    permissions: Permissions,
+    #[serde(default)]
+    cache: Cache,
+    #[serde(default)]
+    vndb: Vndb,
+    #[serde(default)]
+    web: Web,
+}
+
 pub struct Config {
     inner: IrcConfig,
+    pub permissions: Permissions,
+    pub cache: actors::cache::Config,
+    pub backend: actors::backend::Backend,
+    pub web_url: String,
     path: PathBuf
 }
 
@@ -37,8 +124,16 @@ impl Config {
     pub fn new() -> Result<Config, String> {
         let path = get_config();
 
+        let inner = IrcConfig::load(&path).format_err("Failed to load bot's config")?;
+        let extra = fs::read_to_string(&path).format_err("Failed to read bot's config")?;
+        let extra: Extra = toml::from_str(&extra).format_err("Invalid bot's config")?;
+
         Ok(Config {
-            inner: IrcConfig::load(&path).format_err("Failed to load bot's config")?,
+            inner,
+            permissions: extra.permissions,
+            cache: extra.cache.into(),
+            backend: extra.vndb.into(),
+            web_url: extra.web.url.unwrap_or_else(|| command::DEFAULT_WEB_URL.to_owned()),
             path
         })
     }
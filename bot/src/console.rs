@@ -0,0 +1,76 @@
+extern crate actix;
+extern crate futures;
+extern crate actors;
+
+use self::futures::Future;
+use self::actors::db::models::Frontend;
+
+use ::std::io::{self, BufRead, Write};
+use ::std::thread;
+use ::std::env;
+
+use ::command;
+use ::permission::Role;
+
+#[inline]
+fn prompt() {
+    print!("> ");
+    let _ = io::stdout().flush();
+}
+
+///Returns name under which changes are recorded.
+fn user_name() -> String {
+    env::var("USER").or_else(|_| env::var("USERNAME"))
+                    .unwrap_or_else(|_| "console".to_owned())
+}
+
+///Starts reading commands from stdin.
+///
+///Whoever has access to console is considered as owner.
+///System is stopped once stdin is closed or on shutdown command.
+pub fn start(dispatcher: actix::Addr<command::Dispatcher>, system: actix::System) {
+    thread::spawn(move || {
+        let name = user_name();
+        let stdin = io::stdin();
+
+        prompt();
+        for line in stdin.lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(error) => {
+                    error!("Console: unable to read stdin: {}", error);
+                    break;
+                }
+            };
+
+            let cmd = match command::Command::from_str(&line) {
+                Some(cmd) => cmd,
+                None => {
+                    if !line.trim().is_empty() {
+                        println!("Unknown command. See .help");
+                    }
+                    prompt();
+                    continue;
+                }
+            };
+
+            let caller = command::Caller::new(name.as_str(), Frontend::Console, Role::Owner);
+            match dispatcher.send(command::Dispatch::new(cmd, caller)).wait() {
+                Ok(Ok(command::Reply::None)) | Ok(Err(())) => (),
+                Ok(Ok(command::Reply::Text(text))) => println!("{}", text),
+                Ok(Ok(command::Reply::Lines(lines))) => for line in lines {
+                    println!("{}", line);
+                },
+                Ok(Ok(command::Reply::Shutdown)) => break,
+                Err(error) => {
+                    error!("Console: error dispatching command: {}", error);
+                    break;
+                }
+            }
+
+            prompt();
+        }
+
+        system.stop();
+    });
+}
@@ -2,34 +2,45 @@ extern crate futures;
 extern crate actix;
 extern crate actors;
 
-extern crate typemap;
 extern crate serenity;
 
 use self::serenity::client::Client;
 use self::serenity::prelude::{EventHandler, Context};
-use self::serenity::framework::standard::{
-    StandardFramework,
-    CommandError,
-    Args,
-    help_commands,
-    HelpBehaviour
-};
 use self::serenity::model::channel::Message;
+use self::serenity::model::id::UserId;
 use self::futures::Future;
+use self::actix::{Actor, SyncContext, Handler as ActorHandler};
 
 use ::command;
-use ::http::kouryaku::Kouryaku;
+use ::permission::DiscordPermissions;
+use ::ignore::{Ignores, discord_mask};
 
-struct CommandHandler;
-impl typemap::Key for CommandHandler {
-    type Value = actix::Addr<actors::exec::Executor>;
-}
-struct KouryakuHandler;
-impl typemap::Key for KouryakuHandler {
-    type Value = actix::Addr<Kouryaku>;
+use self::actors::db::models::Frontend;
+
+struct Handler {
+    dispatcher: actix::Addr<command::Dispatcher>,
+    permissions: DiscordPermissions,
+    ignores: Ignores,
+    system: actix::System,
 }
 
-struct Handler;
+impl Handler {
+    fn dispatch(&self, message: &Message, cmd: command::Command) -> command::Reply {
+        let roles = message.member().map(|member| member.roles.iter().map(|role| role.0).collect::<Vec<_>>())
+                                    .unwrap_or_default();
+        let role = self.permissions.role(message.author.id.0, &roles);
+        let caller = command::Caller::new(message.author.tag(), Frontend::Discord, role).with_contact(format!("{}", message.author.id.0));
+
+        match self.dispatcher.send(command::Dispatch::new(cmd, caller)).wait() {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(())) => command::Reply::None,
+            Err(error) => {
+                error!("Discord: error dispatching command: {}", error);
+                command::Reply::None
+            }
+        }
+    }
+}
 
 impl EventHandler for Handler {
     fn message(&self, _: Context, msg: Message) {
@@ -41,198 +52,69 @@ impl EventHandler for Handler {
         let chan = msg.channel_id.name().unwrap_or("{}".to_string());
 
         debug!("{}|{}: {}", chan, msg.author.name, msg.content);
-    }
-}
-
-pub fn client(executor: actix::Addr<actors::exec::Executor>, kouryaku_addr: actix::Addr<Kouryaku>) -> Client {
-    let token = include_str!("../discord.token");
-    // Login with a bot token from the environment
-    let mut client = Client::new(token, Handler).expect("Error creating client");
-    {
-        let mut data = client.data.lock();
-        data.insert::<CommandHandler>(executor);
-        data.insert::<KouryakuHandler>(kouryaku_addr);
-    }
-
-    let framework = StandardFramework::new().configure(|c| c.prefix(".").ignore_bots(true).case_insensitivity(true).allow_dm(true))
-                                            .customised_help(help_commands::plain, |c| {
-                                                c.lacking_permissions(HelpBehaviour::Hide)
-                                            })
-                                            .command("ping", |config| config.desc("Ping").exec(ping))
-                                            .command("vn", |config| config.desc("Search VN").exec(vn))
-                                            .command("hook", |config| config.desc("Get Hook for VN").exec(hook))
-                                            .command("del_hook", |config| {
-                                                config.desc("Remove Hook for VN")
-                                                      .exec(del_hook)
-                                                      .usage(command::DEL_HOOK_USAGE)
-                                            })
-                                            .command("set_hook", |config| {
-                                                config.desc("Set Hook for VN")
-                                                      .usage(command::SET_HOOK_USAGE)
-                                                      .exec(set_hook)
-                                            })
-                                            .command("del_vn", |config| config.desc("Remove VN").exec(del_vn))
-                                            .command("kouryaku", |config| config.desc("Find walkthrough for VN").exec(kouryaku).known_as("walk").known_as("k"));
-
-    client.with_framework(framework);
-
-    client
-}
-
-fn ping(_context: &mut Context, message: &Message, _args: Args) -> Result<(), CommandError> {
-    let _ = message.reply("pong")?;
-
-    Ok(())
-}
 
-fn vn(context: &mut Context, message: &Message, args: Args) -> Result<(), CommandError> {
-    let executor = {
-        let data = context.data.lock();
-        data.get::<CommandHandler>().unwrap().clone()
-    };
-
-    let get_vn = actors::exec::FindVn::new(args.full().to_string());
-    let result = executor.send(get_vn).wait().map_err(|error| CommandError(format!("{}", error)))?;
-
-    match result {
-        Ok(vn) => {
-            let text = format!("{} - https://vndb.org/v{}", vn.title.as_ref().unwrap(), vn.id);
-            message.reply(&text)?;
-        },
-        Err(error) => {
-            message.reply(&format!("{}", error))?;
+        if self.ignores.is_ignored(&discord_mask(&msg.author.name, msg.author.id.0)) {
+            return;
         }
-    }
-
-    Ok(())
-}
 
-fn hook(context: &mut Context, message: &Message, args: Args) -> Result<(), CommandError> {
-    if args.is_empty() {
-        message.reply("For which VN?")?;
-    }
-    else {
-        let executor = {
-            let data = context.data.lock();
-            data.get::<CommandHandler>().unwrap().clone()
-        };
-
-        let get_hook = actors::exec::GetHook(args.full().to_string());
-        let result = executor.send(get_hook).wait().map_err(|error| CommandError(format!("{}", error)))?;
-
-        match result {
-            Ok(data) => message.reply(&format!("{}", data))?,
-            Err(error) => message.reply(&format!("{}", error))?
+        let cmd = match command::Command::from_str(&msg.content) {
+            Some(cmd) => cmd,
+            None => return
         };
-    }
-
-    Ok(())
-}
 
-fn set_hook(context: &mut Context, message: &Message, args: Args) -> Result<(), CommandError> {
-    let mut args = args.multiple_quoted()?;
-
-    if args.len() == 0 {
-        message.reply(command::SET_HOOK_USAGE)?;
-    }
-    else if args.len() != 3 {
-        message.reply("Wrong number of arguments. Expected 3. See usage.")?;
-    }
-    else {
-        let executor = {
-            let data = context.data.lock();
-            data.get::<CommandHandler>().unwrap().clone()
+        let result = match self.dispatch(&msg, cmd) {
+            command::Reply::None => return,
+            command::Reply::Text(text) => msg.reply(&text),
+            command::Reply::Lines(lines) => msg.reply(&lines.join("\n")),
+            command::Reply::Shutdown => {
+                warn!("Shutdown command is issued!");
+                let result = msg.reply("さようなら");
+                self.system.stop();
+                result
+            }
         };
 
-        let mut args = args.drain(..);
-        let title: String = args.next().unwrap();
-        let version = args.next().unwrap();
-        let code = args.next().unwrap();
-
-        let set_hook = actors::exec::SetHook::new(title.clone(), version, code);
-        let result = executor.send(set_hook).wait().map_err(|error| CommandError(format!("{}", error)))?;
-
-        match result {
-            Ok(hook) => message.reply(&format!("Added hook '{}' for VN: {}", hook.code, title))?,
-            Err(error) => message.reply(&format!("{}", error))?
-        };
+        if let Err(error) = result {
+            warn!("Discord: unable to reply: {}", error);
+        }
     }
-
-    Ok(())
 }
 
-fn del_hook(context: &mut Context, message: &Message, args: Args) -> Result<(), CommandError> {
-    let mut args = args.multiple_quoted()?;
+///Delivers notifications via direct messages.
+///
+///Contact is expected to be user's id.
+pub struct Notifier;
 
-    if args.len() == 0 {
-        message.reply(command::DEL_HOOK_USAGE)?;
-    }
-    else if args.len() != 2 {
-        message.reply("Wrong number of arguments. Expected 2. See usage.")?;
-    }
-    else {
-        let executor = {
-            let data = context.data.lock();
-            data.get::<CommandHandler>().unwrap().clone()
-        };
-
-        let mut args = args.drain(..);
-        let title: String = args.next().unwrap();
-        let version = args.next().unwrap();
-
-        let del_hook = actors::exec::DelHook::new(title.clone(), version);
-        let result = executor.send(del_hook).wait().map_err(|error| CommandError(format!("{}", error)))?;
-
-        match result {
-            Ok(0) => message.reply(&format!("{}: No hook to remove.", title))?,
-            Ok(_) => message.reply(&format!("{}: Removed hook.", title))?,
-            Err(error) => message.reply(&format!("{}", error))?
-        };
-    }
-
-    Ok(())
+impl Actor for Notifier {
+    type Context = SyncContext<Self>;
 }
 
-fn del_vn(context: &mut Context, message: &Message, args: Args) -> Result<(), CommandError> {
-    if args.is_empty() {
-        message.reply("For which VN?")?;
-    } else {
-        let executor = {
-            let data = context.data.lock();
-            data.get::<CommandHandler>().unwrap().clone()
-        };
-
-        let del_vn = actors::exec::DelVn(args.full().to_string());
-        let result = executor.send(del_vn).wait().map_err(|error| CommandError(format!("{}", error)))?;
+impl ActorHandler<command::Notify> for Notifier {
+    type Result = ();
 
-        match result {
-            Ok(0) => message.reply(&format!("{}: No such VN exists in DB.", args.full()))?,
-            Ok(_) => message.reply(&format!("{}: removed from DB.", args.full()))?,
-            Err(error) => message.reply(&format!("{}", error))?
+    fn handle(&mut self, msg: command::Notify, _: &mut Self::Context) -> Self::Result {
+        let id = match msg.contact.parse::<u64>() {
+            Ok(id) => UserId(id),
+            Err(_) => {
+                warn!("Discord: invalid contact '{}'", msg.contact);
+                return;
+            }
         };
-    }
 
-    Ok(())
+        if let Err(error) = id.create_dm_channel().and_then(|channel| channel.say(&msg.text)) {
+            warn!("Discord: unable to notify {}: {}", msg.contact, error);
+        }
+    }
 }
 
-fn kouryaku(context: &mut Context, message: &Message, args: Args) -> Result<(), CommandError> {
-    if args.is_empty() {
-        message.reply("For which VN?")?;
-    } else {
-        let kouryaku = {
-            let data = context.data.lock();
-            data.get::<KouryakuHandler>().unwrap().clone()
-        };
-
-        let find = ::http::kouryaku::Find(args.full().to_string());
-        let result = kouryaku.send(find).wait().map_err(|error| CommandError(format!("{}", error)))?;
-
-        match result {
-            Ok(Some((title, url))) => message.reply(&format!("{} - {}", title, url))?,
-            Ok(None) => message.reply("Unable to find kouryaku")?,
-            Err(error) => message.reply(&format!("{}", error))?,
-        };
-    }
+pub fn client(dispatcher: actix::Addr<command::Dispatcher>, permissions: DiscordPermissions, ignores: Ignores, system: actix::System) -> Client {
+    let token = include_str!("../discord.token");
+    let handler = Handler {
+        dispatcher,
+        permissions,
+        ignores,
+        system
+    };
 
-    Ok(())
+    Client::new(token, handler).expect("Error creating client")
 }
@@ -0,0 +1,81 @@
+//!Ignore list shared by all frontends.
+
+use ::std::sync::{Arc, RwLock};
+
+use ::actors::db::models;
+use ::permission::mask_match;
+
+///Converts user supplied mask into full `nick!user@host` form.
+pub fn normalize_mask(mask: &str) -> String {
+    match (mask.find('!'), mask.find('@')) {
+        (Some(_), Some(_)) => mask.to_owned(),
+        (Some(_), None) => format!("{}@*", mask),
+        (None, Some(_)) => format!("*!{}", mask),
+        (None, None) => format!("{}!*@*", mask),
+    }
+}
+
+#[inline]
+///Returns mask that identifies Discord user.
+pub fn discord_mask(name: &str, id: u64) -> String {
+    format!("{}!{}@discord", name, id)
+}
+
+#[derive(Clone, Default)]
+pub struct Ignores {
+    inner: Arc<RwLock<Vec<models::Ignore>>>
+}
+
+impl Ignores {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, list: Vec<models::Ignore>) {
+        *self.inner.write().unwrap() = list;
+    }
+
+    pub fn add(&self, ignore: models::Ignore) {
+        let mut list = self.inner.write().unwrap();
+
+        if !list.iter().any(|entry| entry.pattern == ignore.pattern) {
+            list.push(ignore);
+        }
+    }
+
+    pub fn remove(&self, pattern: &str) {
+        self.inner.write().unwrap().retain(|entry| entry.pattern != pattern);
+    }
+
+    ///Checks whether user with given `nick!user@host` mask is ignored.
+    pub fn is_ignored(&self, mask: &str) -> bool {
+        self.inner.read().unwrap().iter().any(|entry| mask_match(&entry.pattern, mask))
+    }
+
+    pub fn patterns(&self) -> Vec<String> {
+        self.inner.read().unwrap().iter().map(|entry| entry.pattern.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_mask, discord_mask};
+    use ::permission::mask_match;
+
+    #[test]
+    fn should_normalize_mask() {
+        assert_eq!(normalize_mask("nick"), "nick!*@*");
+        assert_eq!(normalize_mask("nick!user"), "nick!user@*");
+        assert_eq!(normalize_mask("*@host"), "*!*@host");
+        assert_eq!(normalize_mask("nick!user@host"), "nick!user@host");
+    }
+
+    #[test]
+    fn should_match_discord_user() {
+        let mask = discord_mask("Name", 1234);
+
+        assert!(mask_match("*!1234@discord", &mask));
+        assert!(mask_match(&normalize_mask("name"), &mask));
+        assert!(!mask_match("*!*@*.rizon.net", &mask));
+    }
+}
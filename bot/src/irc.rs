@@ -18,42 +18,143 @@ use self::irc::proto::message::Message as InnerIrcMessage;
 use self::irc::error::IrcError;
 use self::irc::client::ext::ClientExt;
 use self::utils::duration;
+use self::actors::db::models::Frontend;
 
-use ::collections::HashSet;
+use ::collections::HashMap;
 
-use ::http;
 use ::config::Config;
 use ::command;
+use ::permission::Role;
+use ::ignore::Ignores;
 
-macro_rules! try_option {
-    ($result:expr, $warn:expr) => { match $result {
-        Some(result) => result,
-        None => {
-            warn!($warn);
-            return;
-        }
-    }}
+///Command that awaits NickServ verification of its sender.
+struct PendingCommand {
+    target: String,
+    from: String,
+    is_pm: bool,
+    cmd: command::Command,
+    //Role granted by host mask
+    role: Role,
 }
 
 pub struct Irc {
     config: Config,
-    handler: Addr<actors::exec::Executor>,
+    dispatcher: Addr<command::Dispatcher>,
     client: Option<IrcClient>,
-    ignores: HashSet<String>
+    ignores: Ignores,
+    //Pending commands by lowercase nick
+    pending: HashMap<String, Vec<PendingCommand>>,
 }
 
 impl Irc {
-    pub fn new(config: Config, handler: Addr<actors::exec::Executor>) -> Self {
-        let mut ignores = HashSet::new();
-        ignores.insert("Fltrsh".to_string());
-
+    pub fn new(config: Config, dispatcher: Addr<command::Dispatcher>, ignores: Ignores) -> Self {
         Self {
             config,
-            handler,
+            dispatcher,
             client: None,
-            ignores
+            ignores,
+            pending: HashMap::new(),
+        }
+    }
+
+    ///Determines sender's role and dispatches command.
+    ///
+    ///If host mask is not enough, NickServ is asked to verify sender's account.
+    fn authorize(&mut self, prefix: &str, target: String, from: String, is_pm: bool, cmd: command::Command, ctx: &mut Context<Self>) {
+        let required = cmd.required_role();
+        let permissions = &self.config.permissions.irc;
+        let role = permissions.role_by_mask(prefix);
+
+        match role < required && permissions.has_accounts_for(required) {
+            true => self.verify_account(PendingCommand { target, from, is_pm, cmd, role }, ctx),
+            false => self.dispatch(target, from, is_pm, role, cmd, ctx),
+        }
+    }
+
+    fn verify_account(&mut self, pending: PendingCommand, ctx: &mut Context<Self>) {
+        const TIMEOUT_MS: u64 = 10_000;
+
+        let nick = pending.from.to_lowercase();
+        {
+            let queue = self.pending.entry(nick.clone()).or_insert_with(Vec::new);
+            queue.push(pending);
+
+            //Lookup is already in progress
+            if queue.len() > 1 {
+                return;
+            }
+        }
+
+        let client = self.client.as_ref().unwrap();
+        if let Err(error) = client.send_privmsg("NickServ", &format!("STATUS {}", nick)) {
+            error!("IRC: Unable to query NickServ: {}", error);
+        }
+
+        ctx.run_later(duration::ms(TIMEOUT_MS), move |act, ctx| {
+            if let Some(pending) = act.pending.remove(&nick) {
+                warn!("IRC: NickServ hasn't verified '{}' in time", nick);
+                for cmd in pending {
+                    ctx.notify(TextResponse::new(cmd.target, cmd.from, cmd.is_pm, "Unable to verify your identity with NickServ".into()));
+                }
+            }
+        });
+    }
+
+    fn on_nickserv_notice(&mut self, text: &str, ctx: &mut Context<Self>) {
+        let (nick, level) = match parse_nickserv_status(text) {
+            Some(status) => status,
+            None => return
+        };
+
+        let pending = match self.pending.remove(&nick.to_lowercase()) {
+            Some(pending) => pending,
+            None => return
+        };
+
+        //Level 3 means user is identified to the account of the nick.
+        let account_role = match level {
+            3 => self.config.permissions.irc.role_by_account(nick),
+            _ => Role::Everyone
+        };
+
+        for PendingCommand { target, from, is_pm, cmd, role } in pending {
+            self.dispatch(target, from, is_pm, ::std::cmp::max(role, account_role), cmd, ctx);
         }
     }
+
+    fn dispatch(&mut self, target: String, from: String, is_pm: bool, role: Role, cmd: command::Command, ctx: &mut Context<Self>) {
+        let caller = command::Caller::new(from.clone(), Frontend::Irc, role).with_contact(from.clone());
+
+        let dispatch = self.dispatcher.send(command::Dispatch::new(cmd, caller)).into_actor(self);
+        let dispatch = dispatch.map(move |result, _act, ctx| match result {
+            Ok(command::Reply::None) | Err(()) => (),
+            Ok(command::Reply::Text(text)) => ctx.notify(TextResponse::new(target, from, is_pm, text.into())),
+            Ok(command::Reply::Lines(lines)) => for line in lines {
+                ctx.notify(TextResponse::new(target.clone(), from.clone(), is_pm, line.into()));
+            },
+            Ok(command::Reply::Shutdown) => ctx.notify(StopSystem),
+        }).map_err(|error, _act, _ctx| {
+            error!("IRC: error dispatching command: {}", error)
+        });
+        ctx.spawn(dispatch);
+    }
+}
+
+///Parses NickServ's reply to `STATUS` or `ACC` query.
+///
+///Returns nick and its status level.
+fn parse_nickserv_status(text: &str) -> Option<(&str, u8)> {
+    let mut parts = text.split_whitespace();
+
+    let (nick, level) = match (parts.next(), parts.next(), parts.next()) {
+        //Anope: STATUS <nick> <level>
+        (Some("STATUS"), Some(nick), Some(level)) => (nick, level),
+        //Atheme: <nick> ACC <level>
+        (Some(nick), Some("ACC"), Some(level)) => (nick, level),
+        _ => return None
+    };
+
+    level.parse::<u8>().ok().map(|level| (nick, level))
 }
 
 #[derive(Debug)]
@@ -78,6 +179,7 @@ impl StreamHandler<IrcMessage, IrcError> for Irc {
         debug!("IRC: message={:?}", msg);
 
         let msg = msg.0;
+        let prefix = msg.prefix.as_ref().map(|prefix| prefix.as_str()).unwrap_or("");
         let from = msg.prefix.as_ref().map(|prefix| &prefix[..prefix.find('!').unwrap_or(0)]);
         let client = self.client.as_ref().unwrap();
 
@@ -87,7 +189,7 @@ impl StreamHandler<IrcMessage, IrcError> for Irc {
 
                 let from = from.unwrap().to_string();
 
-                if self.ignores.contains(&from) {
+                if self.ignores.is_ignored(prefix) {
                     return;
                 }
 
@@ -98,47 +200,10 @@ impl StreamHandler<IrcMessage, IrcError> for Irc {
 
                 let is_pm = *client.current_nickname() == target;
 
-                match cmd {
-                    command::Command::Text(text) => ctx.notify(TextResponse::new(target, from, is_pm, text)),
-                    command::Command::GetVn(get_vn) => ctx.notify(GetVnResponse::new(target, from, is_pm, get_vn)),
-                    command::Command::GetHook(get_hook) => ctx.notify(GetHookResponse::new(target, from, is_pm, get_hook)),
-                    command::Command::SetHook(set_hook) => ctx.notify(SetHookResponse::new(target, from, is_pm, set_hook)),
-                    command::Command::DelHook(del_hook) => ctx.notify(DelHookResponse::new(target, from, is_pm, del_hook)),
-                    command::Command::DelVn(del_vn) => ctx.notify(DelVnResponse::new(target, from, is_pm, del_vn)),
-                    command::Command::Kouryaku(kouryaku) => ctx.notify(KouryakuVnResponse::new(target, from, is_pm, kouryaku)),
-                    command::Command::Ignore(name) => match self.ignores.contains(&name) {
-                        true => {
-                            let text = format!("Removed '{}' from ignore list", name);
-                            self.ignores.remove(&name);
-                            info!("{}", &text);
-                            ctx.notify(TextResponse::new(target, from, is_pm, text.into()))
-                        },
-                        false => {
-                            let text = format!("Added '{}' to ignore list", name);
-                            self.ignores.insert(name);
-                            info!("{}", &text);
-                            ctx.notify(TextResponse::new(target, from, is_pm, text.into()))
-                        }
-                    },
-                    command::Command::IgnoreList => {
-                        let list = self.ignores.iter().map(String::as_str).collect::<Vec<_>>();
-                        let list = &list[..];
-                        let text = format!("Ignore list: {}", list.join(", "));
-                        ctx.notify(TextResponse::new(target, from, is_pm, text.into()))
-                    },
-                    command::Command::Refs(mut refs) => for reference in &mut refs.refs {
-                        let reference = reference.take();
-                        if let Some(reference) = reference {
-                            ctx.notify(GetRefResponse::new(target.clone(), from.clone(), is_pm, reference));
-                        }
-                        else {
-                            break;
-                        }
-                    },
-                    command::Command::Shutdown => {
-                        ctx.notify(StopSystem);
-                    },
-                }
+                self.authorize(prefix, target, from, is_pm, cmd, ctx);
+            },
+            Command::NOTICE(_, text) => if from.map(|from| from.eq_ignore_ascii_case("NickServ")).unwrap_or(false) {
+                self.on_nickserv_notice(&text, ctx);
             },
             Command::JOIN(chanlist, _, _) => debug!("{:?} joined {}", from, chanlist),
             Command::PART(chanlist, _) => debug!("{:?} left {}", from, chanlist),
@@ -201,188 +266,21 @@ impl Handler<TextResponse> for Irc {
     }
 }
 
-//.vn
-type GetVnResponse = GetIrcResponse<command::GetVn>;
-impl Handler<GetVnResponse> for Irc {
-    type Result = <GetVnResponse as Message>::Result;
-
-    fn handle(&mut self, msg: GetVnResponse, ctx: &mut Self::Context) -> Self::Result {
-        let GetVnResponse {target, from, is_pm, cmd} = msg;
-        let title = cmd.title;
-
-        let get_vn = actors::exec::FindVn::new(title);
-        let get_vn = self.handler.send(get_vn).into_actor(self);
-        let get_vn = get_vn.map(move |result, _act, ctx| match result {
-            Ok(vn) => {
-                let text = format!("{} - https://vndb.org/v{}", vn.title.as_ref().unwrap(), vn.id);
-                ctx.notify(TextResponse::new(target, from, is_pm, text.into()))
-            },
-            Err(error) => ctx.notify(TextResponse::new(target, from, is_pm, format!("{}", error).into()))
-        }).map_err(|error, _act, _ctx| {
-            error!("IRC: error processing GetVn: {}", error);
-        });
-        ctx.spawn(get_vn);
-
-        Ok(())
-    }
-}
-
-//.set_hook
-type SetHookResponse = GetIrcResponse<command::SetHook>;
-impl Handler<SetHookResponse> for Irc {
-    type Result = <SetHookResponse as Message>::Result;
-
-    fn handle(&mut self, msg: SetHookResponse, ctx: &mut Self::Context) -> Self::Result {
-        let SetHookResponse {target, from, is_pm, cmd} = msg;
-        let command::SetHook {title, version, code} = cmd;
-
-        let set_hook = actors::exec::SetHook::new(title.clone(), version, code);
-        let set_hook = self.handler.send(set_hook).into_actor(self);
-        let set_hook = set_hook.map(move |result, _act, ctx| match result {
-            Ok(hook) => ctx.notify(TextResponse::new(target, from, is_pm, format!("Added hook '{}' for VN: {}", hook.code, title).into())),
-            Err(error) => ctx.notify(TextResponse::new(target, from, is_pm, format!("{}", error).into()))
-        }).map_err(|error, _act, _ctx| {
-            error!("IRC: error processing SetHook: {}", error)
-        });
-        ctx.spawn(set_hook);
-
-        Ok(())
-    }
-}
-
-//.hook
-type GetHookResponse = GetIrcResponse<command::GetHook>;
-impl Handler<GetHookResponse> for Irc {
-    type Result = <GetHookResponse as Message>::Result;
-
-    fn handle(&mut self, msg: GetHookResponse, ctx: &mut Self::Context) -> Self::Result {
-        let GetHookResponse {target, from, is_pm, cmd} = msg;
-        let title = cmd.title;
-
-        let get_hook = actors::exec::GetHook(title);
-        let get_hook = self.handler.send(get_hook).into_actor(self);
-        let get_hook = get_hook.map(move |result, _act, ctx| match result {
-            Ok(data) => ctx.notify(TextResponse::new(target, from, is_pm, format!("{}", data).into())),
-            Err(error) => ctx.notify(TextResponse::new(target, from, is_pm, format!("{}", error).into()))
-        }).map_err(|error, _act, _ctx| {
-            error!("IRC: error processing GetHook: {}", error)
-        });
-        ctx.spawn(get_hook);
-
-        Ok(())
-    }
-}
-
-//.del_hook
-type DelHookResponse = GetIrcResponse<command::DelHook>;
-impl Handler<DelHookResponse> for Irc {
-    type Result = <DelHookResponse as Message>::Result;
-
-    fn handle(&mut self, msg: DelHookResponse, ctx: &mut Self::Context) -> Self::Result {
-        let DelHookResponse {target, from, is_pm, cmd} = msg;
-        let command::DelHook {title, version} = cmd;
-
-        let del_hook = actors::exec::DelHook::new(title.clone(), version);
-        let del_hook = self.handler.send(del_hook).into_actor(self);
-        let del_hook = del_hook.map(move |result, _act, ctx| match result {
-            Ok(0) => ctx.notify(TextResponse::new(target, from, is_pm, format!("{}: No hook to remove.", title).into())),
-            Ok(_) => ctx.notify(TextResponse::new(target, from, is_pm, format!("{}: Removed hook.", title).into())),
-            Err(error) => ctx.notify(TextResponse::new(target, from, is_pm, format!("{}", error).into()))
-        }).map_err(|error, _act, _ctx| {
-            error!("IRC: error processing DelHook: {}", error)
-        });
-        ctx.spawn(del_hook);
-
-        Ok(())
-    }
-}
-
-//.del_vn
-type DelVnResponse = GetIrcResponse<command::DelVn>;
-impl Handler<DelVnResponse> for Irc {
-    type Result = <DelVnResponse as Message>::Result;
-
-    fn handle(&mut self, msg: DelVnResponse, ctx: &mut Self::Context) -> Self::Result {
-        let DelVnResponse {target, from, is_pm, cmd} = msg;
-        let title = cmd.title;
-
-        let del_vn = actors::exec::DelVn(title.clone());
-        let del_vn = self.handler.send(del_vn).into_actor(self);
-        let del_vn = del_vn.map(move |result, _act, ctx| match result {
-            Ok(0) => ctx.notify(TextResponse::new(target, from, is_pm, format!("{}: No such VN exists in DB.", title).into())),
-            Ok(_) => ctx.notify(TextResponse::new(target, from, is_pm, format!("{}: removed from DB.", title).into())),
-            Err(error) => ctx.notify(TextResponse::new(target, from, is_pm, format!("{}", error).into()))
-        }).map_err(|error, _act, _ctx| {
-            error!("IRC: error processing DelVn: {}", error)
-        });
-        ctx.spawn(del_vn);
-
-        Ok(())
-    }
-}
-
-//.Kouryaku
-type KouryakuVnResponse = GetIrcResponse<command::Kouryaku>;
-impl Handler<KouryakuVnResponse> for Irc {
-    type Result = <KouryakuVnResponse as Message>::Result;
-
-    fn handle(&mut self, msg: KouryakuVnResponse, ctx: &mut Self::Context) -> Self::Result {
-        let KouryakuVnResponse {target, from, is_pm, cmd} = msg;
-        let title = cmd.title;
-
-        let find = http::kouryaku::Find(title);
-        let find = System::current().registry()
-                                    .get::<http::kouryaku::Kouryaku>()
-                                    .send(find)
-                                    .into_actor(self)
-                                    .map(move |result, _act, ctx| match result {
-                                        Ok(Some((title, url))) => ctx.notify(TextResponse::new(target, from, is_pm, format!("{} - {}", title, url).into())),
-                                        Ok(None) => ctx.notify(TextResponse::new(target, from, is_pm, "Unable to find kouryaku".into())),
-                                        Err(error) => ctx.notify(TextResponse::new(target, from, is_pm, format!("{}", error).into())),
-                                    }).map_err(|error, _act, _ctx| {
-                                        error!("IRC: error processing Kouryaku: {}", error);
-                                    });
-
-        ctx.spawn(find);
-
-        Ok(())
-    }
-}
-
-
-//References
-type GetRefResponse = GetIrcResponse<command::Ref>;
-impl Handler<GetRefResponse> for Irc {
-    type Result = <GetRefResponse as Message>::Result;
-
-    fn handle(&mut self, msg: GetRefResponse, ctx: &mut Self::Context) -> Self::Result {
-        let GetRefResponse {target, from, is_pm, cmd} = msg;
-        let command::Ref {kind, id, url} = cmd;
-
-        let get_ref = actors::exec::GetVndbObject::new(id, kind.clone());
-        let get_ref = self.handler.send(get_ref).into_actor(self);
-        let get_ref = get_ref.map(move |result, _act, ctx| match result {
-            Ok(result) => {
-                let items = try_option!(result.get("items"), "VNDB results is missing items field!");
-                let item = try_option!(items.get(0), "VNDB results's items is empty field!");
-                let name = try_option!(item.get("title").or(item.get("name")).or(item.get("username")),
-                                       "VNDB results's item is missing title/name field!");
-                let kind = kind.short();
-                let id = item.get("id").unwrap();
-                let text = match url {
-                    true => format!("{0}{1}: {2} - https://vndb.org/{0}{1}", kind, id, name),
-                    false => format!("{0}{1}: {2}", kind, id, name),
-                };
-                ctx.notify(TextResponse::new(target, from, is_pm, text.into()))
-            },
-            Err(error) => warn!("GetRef failed: {}", error)
-        }).map_err(|error, _act, _ctx| {
-            error!("IRC: error processing GetRef: {}", error)
-        });
-
-        ctx.spawn(get_ref);
+impl Handler<command::Notify> for Irc {
+    type Result = ();
 
-        Ok(())
+    fn handle(&mut self, msg: command::Notify, _: &mut Self::Context) -> Self::Result {
+        let client = match self.client.as_ref() {
+            Some(client) => client,
+            None => {
+                warn!("IRC: not connected, unable to notify {}", msg.contact);
+                return;
+            }
+        };
+
+        if let Err(error) = client.send_privmsg(&msg.contact, &msg.text) {
+            error!("IRC: Unable to notify {}: {}", msg.contact, error);
+        }
     }
 }
 
@@ -409,6 +307,7 @@ impl Supervised for Irc {
     fn restarting(&mut self, _: &mut Context<Self>) {
         info!("IRC: Restarting...");
         self.client.take();
+        self.pending.clear();
     }
 }
 
@@ -419,6 +318,8 @@ impl Actor for Irc {
         const TIMEOUT_MS: u64 = 1500;
         info!("IRC: starting");
 
+        self.dispatcher.do_send(command::Subscribe::new(Frontend::Irc, ctx.address().recipient()));
+
         let irc = IrcClient::new_future(self.config.clone()).into_actor(self).map_err(|error, _act, ctx| {
             error!("IRC: Unable to connect to server. Error: {}", error);
             ctx.run_later(duration::ms(TIMEOUT_MS), |_, ctx| ctx.stop());
@@ -443,3 +344,17 @@ impl Actor for Irc {
         ctx.spawn(irc);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::parse_nickserv_status;
+
+    #[test]
+    fn should_parse_nickserv_status() {
+        assert_eq!(parse_nickserv_status("STATUS Douman 3"), Some(("Douman", 3)));
+        assert_eq!(parse_nickserv_status("Douman ACC 1"), Some(("Douman", 1)));
+        assert_eq!(parse_nickserv_status("Douman ACC 3 (Douman)"), Some(("Douman", 3)));
+        assert_eq!(parse_nickserv_status("This nickname is registered"), None);
+        assert_eq!(parse_nickserv_status("STATUS Douman"), None);
+    }
+}
@@ -2,6 +2,8 @@
 extern crate log;
 #[macro_use]
 extern crate lazy_static;
+#[macro_use]
+extern crate serde_derive;
 
 extern crate actix;
 
@@ -9,16 +11,20 @@ extern crate utils;
 extern crate actors;
 extern crate http;
 
-use actix::{Supervisor, Actor};
+use actix::{Supervisor, SyncArbiter, Actor};
 
 use std::collections;
 use std::thread;
 use std::fmt;
+use std::env;
 
 mod config;
+mod permission;
+mod ignore;
 mod command;
 mod irc;
 mod discord;
+mod console;
 
 fn run() -> Result<i32, String> {
     utils::ssl::init();
@@ -26,18 +32,44 @@ fn run() -> Result<i32, String> {
 
     let _log_guard = utils::log::init();
 
-    let config = config::load()?;
+    let is_console = env::args().skip(1).any(|arg| arg == "--console");
     let system = actix::System::new("roseline");
 
-    let executor: actix::Addr<_> = actors::exec::Executor::default_threads(2).start();
-    let executor2 = executor.clone();
-    let _irc: actix::Addr<_> = Supervisor::start(move |_| irc::Irc::new(config, executor2));
+    //Console doesn't require configuration.
+    let config = match is_console {
+        true => None,
+        false => Some(config::load()?),
+    };
+    let cache = config.as_ref().map(|config| config.cache).unwrap_or_default();
+    let backend = config.as_ref().map(|config| config.backend.clone()).unwrap_or_default();
+    let web_url = config.as_ref().map(|config| config.web_url.clone()).unwrap_or_else(|| command::DEFAULT_WEB_URL.to_owned());
+
+    let executor = actors::exec::Executor::with_backend(2, &backend).cache(cache);
+    let db = executor.db.clone();
+    let _resync: actix::Addr<_> = actors::resync::Resync::new(executor.vndb.clone(), db.clone()).start();
+    let executor: actix::Addr<_> = executor.start();
+    let ignores = ignore::Ignores::new();
+    let dispatcher: actix::Addr<_> = command::Dispatcher::new(executor, db, ignores.clone()).with_web_url(web_url).start();
+
+    if is_console {
+        console::start(dispatcher, actix::System::current());
+        return Ok(system.run());
+    }
+
+    let config = config.unwrap();
+    let discord_permissions = config.permissions.discord.clone();
+    let dispatcher2 = dispatcher.clone();
+    let ignores2 = ignores.clone();
+    let _irc: actix::Addr<_> = Supervisor::start(move |_| irc::Irc::new(config, dispatcher2, ignores2));
+
+    let notifier = SyncArbiter::start(1, || discord::Notifier);
+    dispatcher.do_send(command::Subscribe::new(actors::db::models::Frontend::Discord, notifier.recipient()));
 
-    let kouryaku = actix::System::current().registry().get::<http::kouryaku::Kouryaku>();
+    let current_system = actix::System::current();
 
     thread::spawn(move || {
         loop {
-            let mut client = discord::client(executor.clone(), kouryaku.clone());
+            let mut client = discord::client(dispatcher.clone(), discord_permissions.clone(), ignores.clone(), current_system.clone());
             if let Err(why) = client.start() {
                 println!("An error occurred while running the client: {:?}", why);
             }
@@ -0,0 +1,209 @@
+//!Roles of users and their configuration.
+
+extern crate actors;
+
+pub use self::actors::db::models::Role;
+
+///Matches text against glob-like pattern, ignoring case.
+///
+///`*` matches any sequence of characters and `?` any single character.
+pub fn mask_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.to_lowercase().chars().collect::<Vec<_>>();
+    let text = text.to_lowercase().chars().collect::<Vec<_>>();
+
+    let (mut p_idx, mut t_idx) = (0, 0);
+    //Position of last `*` in pattern and text position it has been tried from.
+    let mut backtrack = None;
+
+    while t_idx < text.len() {
+        match pattern.get(p_idx) {
+            Some(&'*') => {
+                backtrack = Some((p_idx, t_idx));
+                p_idx += 1;
+                continue;
+            },
+            Some(&'?') => {
+                p_idx += 1;
+                t_idx += 1;
+                continue;
+            },
+            Some(ch) if *ch == text[t_idx] => {
+                p_idx += 1;
+                t_idx += 1;
+                continue;
+            },
+            _ => ()
+        }
+
+        match backtrack {
+            Some((star_idx, star_t_idx)) => {
+                p_idx = star_idx + 1;
+                t_idx = star_t_idx + 1;
+                backtrack = Some((star_idx, t_idx));
+            },
+            None => return false,
+        }
+    }
+
+    pattern[p_idx..].iter().all(|ch| *ch == '*')
+}
+
+#[derive(Deserialize, Clone, Default)]
+///IRC users granted a role.
+pub struct IrcRule {
+    ///NickServ accounts.
+    #[serde(default)]
+    pub accounts: Vec<String>,
+    ///Host masks in form `nick!user@host`.
+    #[serde(default)]
+    pub masks: Vec<String>,
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct IrcPermissions {
+    #[serde(default)]
+    pub owner: IrcRule,
+    #[serde(default)]
+    pub moderator: IrcRule,
+    #[serde(default)]
+    pub trusted: IrcRule,
+}
+
+impl IrcPermissions {
+    #[inline]
+    fn rule(&self, role: Role) -> Option<&IrcRule> {
+        match role {
+            Role::Owner => Some(&self.owner),
+            Role::Moderator => Some(&self.moderator),
+            Role::Trusted => Some(&self.trusted),
+            Role::Everyone => None,
+        }
+    }
+
+    ///Determines role by host mask alone.
+    pub fn role_by_mask(&self, prefix: &str) -> Role {
+        for role in Role::GRANTED.iter() {
+            let rule = self.rule(*role).unwrap();
+            if rule.masks.iter().any(|mask| mask_match(mask, prefix)) {
+                return *role;
+            }
+        }
+
+        Role::Everyone
+    }
+
+    ///Determines role by NickServ account, which must be verified beforehand.
+    pub fn role_by_account(&self, account: &str) -> Role {
+        for role in Role::GRANTED.iter() {
+            let rule = self.rule(*role).unwrap();
+            if rule.accounts.iter().any(|name| name.eq_ignore_ascii_case(account)) {
+                return *role;
+            }
+        }
+
+        Role::Everyone
+    }
+
+    ///Returns whether some account may have at least `required` role.
+    ///
+    ///Used to avoid needless NickServ lookups.
+    pub fn has_accounts_for(&self, required: Role) -> bool {
+        Role::GRANTED.iter().filter(|role| **role >= required)
+                            .any(|role| !self.rule(*role).unwrap().accounts.is_empty())
+    }
+}
+
+#[derive(Deserialize, Clone, Default)]
+///Discord users granted a role.
+pub struct DiscordRule {
+    ///User IDs.
+    #[serde(default)]
+    pub users: Vec<u64>,
+    ///Guild role IDs.
+    #[serde(default)]
+    pub roles: Vec<u64>,
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct DiscordPermissions {
+    #[serde(default)]
+    pub owner: DiscordRule,
+    #[serde(default)]
+    pub moderator: DiscordRule,
+    #[serde(default)]
+    pub trusted: DiscordRule,
+}
+
+impl DiscordPermissions {
+    ///Determines role by user ID and IDs of its guild roles.
+    pub fn role(&self, user: u64, roles: &[u64]) -> Role {
+        for role in Role::GRANTED.iter() {
+            let rule = match role {
+                &Role::Owner => &self.owner,
+                &Role::Moderator => &self.moderator,
+                _ => &self.trusted,
+            };
+
+            if rule.users.contains(&user) || rule.roles.iter().any(|id| roles.contains(id)) {
+                return *role;
+            }
+        }
+
+        Role::Everyone
+    }
+}
+
+#[derive(Deserialize, Clone, Default)]
+///Permissions section of configuration.
+pub struct Permissions {
+    #[serde(default)]
+    pub irc: IrcPermissions,
+    #[serde(default)]
+    pub discord: DiscordPermissions,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mask_match, Role, IrcPermissions, IrcRule, DiscordPermissions, DiscordRule};
+
+    #[test]
+    fn should_match_mask() {
+        assert!(mask_match("*!*@*", "nick!user@host"));
+        assert!(mask_match("nick!*@*", "Nick!user@host"));
+        assert!(mask_match("*!*@*.users.rizon.net", "nick!user@Douman.users.rizon.net"));
+        assert!(mask_match("n?ck!*@host", "nock!user@host"));
+        assert!(mask_match("*a*b*", "xxaxxbxx"));
+        assert!(!mask_match("nick!*@*", "nickname!user@host"));
+        assert!(!mask_match("*!*@host", "nick!user@host2"));
+        assert!(!mask_match("n?ck!*@*", "nck!user@host"));
+    }
+
+    #[test]
+    fn should_determine_irc_role() {
+        let perms = IrcPermissions {
+            owner: IrcRule { accounts: vec!["Douman".to_owned()], masks: vec![] },
+            moderator: IrcRule { accounts: vec![], masks: vec!["*!*@mod.host".to_owned()] },
+            trusted: IrcRule::default(),
+        };
+
+        assert_eq!(perms.role_by_mask("nick!user@mod.host"), Role::Moderator);
+        assert_eq!(perms.role_by_mask("nick!user@other.host"), Role::Everyone);
+        assert_eq!(perms.role_by_account("douman"), Role::Owner);
+        assert_eq!(perms.role_by_account("nick"), Role::Everyone);
+        assert!(perms.has_accounts_for(Role::Owner));
+        assert!(perms.has_accounts_for(Role::Trusted));
+    }
+
+    #[test]
+    fn should_determine_discord_role() {
+        let perms = DiscordPermissions {
+            owner: DiscordRule { users: vec![1], roles: vec![] },
+            moderator: DiscordRule { users: vec![], roles: vec![10] },
+            trusted: DiscordRule::default(),
+        };
+
+        assert_eq!(perms.role(1, &[]), Role::Owner);
+        assert_eq!(perms.role(2, &[5, 10]), Role::Moderator);
+        assert_eq!(perms.role(2, &[5]), Role::Everyone);
+    }
+}
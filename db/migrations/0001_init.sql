@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS vns (
+    id INTEGER,
+    title TEXT NOT NULL,
+    PRIMARY KEY (id)
+);
+
+CREATE TABLE IF NOT EXISTS hooks (
+    id INTEGER,
+    vn_id INTEGER NOT NULL,
+    version TEXT NOT NULL,
+    code TEXT NOT NULL,
+    PRIMARY KEY (id),
+    FOREIGN KEY (vn_id) REFERENCES vns (id) ON DELETE CASCADE ON UPDATE NO ACTION
+);
@@ -0,0 +1,14 @@
+CREATE TABLE hook_revisions (
+    id INTEGER,
+    vn_id INTEGER NOT NULL,
+    vn_title TEXT NOT NULL,
+    version TEXT NOT NULL,
+    old_code TEXT,
+    new_code TEXT,
+    author TEXT NOT NULL,
+    frontend TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (id)
+);
+
+CREATE INDEX hook_revisions_vn_id ON hook_revisions (vn_id);
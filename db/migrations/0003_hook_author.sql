@@ -0,0 +1,13 @@
+ALTER TABLE hooks ADD COLUMN author TEXT;
+ALTER TABLE hooks ADD COLUMN frontend TEXT;
+ALTER TABLE hooks ADD COLUMN created_at TIMESTAMP;
+ALTER TABLE hooks ADD COLUMN updated_at TIMESTAMP;
+
+-- Recover what we can from history for already existing hooks
+UPDATE hooks SET created_at = (
+    SELECT MIN(hook_revisions.created_at) FROM hook_revisions
+    WHERE hook_revisions.vn_id = hooks.vn_id AND hook_revisions.version = hooks.version
+), updated_at = (
+    SELECT MAX(hook_revisions.created_at) FROM hook_revisions
+    WHERE hook_revisions.vn_id = hooks.vn_id AND hook_revisions.version = hooks.version
+);
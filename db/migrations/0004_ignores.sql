@@ -0,0 +1,13 @@
+CREATE TABLE ignores (
+    id INTEGER,
+    pattern TEXT NOT NULL UNIQUE,
+    reason TEXT,
+    author TEXT NOT NULL,
+    frontend TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (id)
+);
+
+-- Previously hardcoded in bot
+INSERT INTO ignores (pattern, reason, author, frontend, created_at)
+VALUES ('Fltrsh!*@*', 'Hardcoded before ignore list became persistent', 'Roseline', 'IRC', CURRENT_TIMESTAMP);
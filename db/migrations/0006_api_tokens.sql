@@ -0,0 +1,10 @@
+CREATE TABLE api_tokens (
+    id INTEGER,
+    user_id INTEGER NOT NULL REFERENCES users(id),
+    name TEXT NOT NULL,
+    token TEXT NOT NULL UNIQUE,
+    scope TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (id)
+);
+CREATE INDEX api_tokens_user_id ON api_tokens (user_id);
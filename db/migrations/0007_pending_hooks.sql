@@ -0,0 +1,18 @@
+CREATE TABLE pending_hooks (
+    id INTEGER,
+    vn_id INTEGER NOT NULL,
+    vn_title TEXT NOT NULL,
+    version TEXT NOT NULL,
+    code TEXT NOT NULL,
+    author TEXT NOT NULL,
+    frontend TEXT NOT NULL,
+    contact TEXT,
+    status TEXT NOT NULL,
+    reviewer TEXT,
+    reason TEXT,
+    created_at TIMESTAMP NOT NULL,
+    reviewed_at TIMESTAMP,
+    notified BOOLEAN NOT NULL DEFAULT 0,
+    PRIMARY KEY (id)
+);
+CREATE INDEX pending_hooks_status ON pending_hooks (status);
@@ -0,0 +1,7 @@
+-- Titles are stored normalized, see `search::normalize`.
+-- Index is populated by application on start.
+CREATE VIRTUAL TABLE vn_search USING fts5(
+    vn_id UNINDEXED,
+    title,
+    tokenize = 'unicode61 remove_diacritics 1'
+);
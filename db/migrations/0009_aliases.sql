@@ -0,0 +1,25 @@
+CREATE TABLE aliases (
+    id INTEGER,
+    vn_id INTEGER NOT NULL REFERENCES vns(id),
+    alias TEXT NOT NULL,
+    -- Normalized alias, see `search::normalize`.
+    normalized TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    author TEXT,
+    frontend TEXT,
+    created_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (id),
+    UNIQUE (vn_id, alias)
+);
+CREATE INDEX aliases_normalized ON aliases (normalized);
+
+-- Search index is extended with original titles and aliases.
+-- It is repopulated by application on start.
+DROP TABLE vn_search;
+CREATE VIRTUAL TABLE vn_search USING fts5(
+    vn_id UNINDEXED,
+    title,
+    original,
+    aliases,
+    tokenize = 'unicode61 remove_diacritics 1'
+);
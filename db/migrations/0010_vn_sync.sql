@@ -0,0 +1,7 @@
+CREATE TABLE vn_sync (
+    vn_id INTEGER NOT NULL REFERENCES vns(id),
+    synced_at TIMESTAMP NOT NULL,
+    -- VNDB no longer knows this VN, e.g. it is deleted or merged.
+    missing BOOLEAN NOT NULL DEFAULT 0,
+    PRIMARY KEY (vn_id)
+);
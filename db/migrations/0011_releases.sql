@@ -0,0 +1,12 @@
+CREATE TABLE releases (
+    id INTEGER,
+    vn_id INTEGER NOT NULL REFERENCES vns(id),
+    title TEXT NOT NULL,
+    -- Comma separated platforms and languages, as in VNDB.
+    platforms TEXT NOT NULL,
+    languages TEXT NOT NULL,
+    PRIMARY KEY (id)
+);
+CREATE INDEX releases_vn_id ON releases (vn_id);
+
+ALTER TABLE hooks ADD COLUMN release_id INTEGER REFERENCES releases(id);
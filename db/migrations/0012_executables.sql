@@ -0,0 +1,15 @@
+CREATE TABLE executables (
+    id INTEGER,
+    -- Lowercase file name, e.g. `game.exe`.
+    name TEXT NOT NULL,
+    -- Optional lowercase hex digest of file, to tell apart executables with the same name.
+    hash TEXT,
+    vn_id INTEGER NOT NULL REFERENCES vns(id),
+    release_id INTEGER REFERENCES releases(id),
+    author TEXT,
+    frontend TEXT,
+    created_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (id)
+);
+CREATE INDEX executables_name ON executables (name);
+CREATE INDEX executables_vn_id ON executables (vn_id);
@@ -0,0 +1,305 @@
+//!Parsing and validation of AGTH/ITH hook codes.
+//!
+//!Supported codes:
+//!
+//!- `/H<type>[N][codepage#]<data>[*<deref>][:<split>[*<deref>]]@<address>[:<module>[:<function>]]`
+//!- `/R<type>[N][codepage#][<data>[*<deref>]]@<address>[:<module>[:<function>]]`
+//!
+//!Offsets and address are hexadecimal, offsets may be negative.
+
+use std::fmt;
+
+///Types of `/H` codes.
+const HOOK_TYPES: &'static str = "ABWHSQV";
+///Types of `/R` codes, which read only strings.
+const READ_TYPES: &'static str = "SQV";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    ///`/H` code, which hooks function.
+    Hook,
+    ///`/R` code, which reads text at address.
+    Read,
+}
+
+impl Kind {
+    #[inline]
+    fn prefix(&self) -> &'static str {
+        match self {
+            &Kind::Hook => "/H",
+            &Kind::Read => "/R",
+        }
+    }
+
+    #[inline]
+    fn types(&self) -> &'static str {
+        match self {
+            &Kind::Hook => HOOK_TYPES,
+            &Kind::Read => READ_TYPES,
+        }
+    }
+}
+
+///Offset with optional dereference.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Offset {
+    pub offset: i64,
+    ///Offset applied after dereferencing.
+    pub deref: Option<i64>,
+}
+
+///Parsed hook code.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HookCode {
+    pub kind: Kind,
+    ///Type letter, which describes how text is stored.
+    pub typ: char,
+    ///Hook doesn't use context to separate threads.
+    pub no_context: bool,
+    pub codepage: Option<u32>,
+    ///Offset of text. Required for `/H` codes.
+    pub data: Option<Offset>,
+    ///Offset of value that separates threads.
+    pub split: Option<Offset>,
+    pub address: u64,
+    pub module: Option<String>,
+    ///Name of function or its ordinal as `#<number>`.
+    pub function: Option<String>,
+}
+
+fn parse_hex(text: &str, what: &str) -> Result<i64, String> {
+    let digits = match text.starts_with('-') || text.starts_with('+') {
+        true => &text[1..],
+        false => text,
+    };
+
+    if digits.len() == 0 || !digits.chars().all(|ch| ch.is_digit(16)) {
+        return Err(format!("Invalid {} '{}'. Expected hexadecimal number", what, text));
+    }
+
+    match i64::from_str_radix(digits, 16) {
+        Ok(value) if text.starts_with('-') => Ok(-value),
+        Ok(value) => Ok(value),
+        Err(_) => Err(format!("Invalid {} '{}'. Number is too big", what, text)),
+    }
+}
+
+fn parse_offset(text: &str, what: &str) -> Result<Offset, String> {
+    let mut parts = text.splitn(2, '*');
+    let offset = parse_hex(parts.next().unwrap_or(""), what)?;
+    let deref = match parts.next() {
+        Some(deref) => Some(parse_hex(deref, "dereference offset")?),
+        None => None
+    };
+
+    Ok(Offset { offset, deref })
+}
+
+fn write_hex(f: &mut fmt::Formatter, value: i64) -> fmt::Result {
+    match value < 0 {
+        true => write!(f, "-{:X}", -value),
+        false => write!(f, "{:X}", value),
+    }
+}
+
+impl fmt::Display for Offset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_hex(f, self.offset)?;
+
+        match self.deref {
+            Some(deref) => {
+                f.write_str("*")?;
+                write_hex(f, deref)
+            },
+            None => Ok(())
+        }
+    }
+}
+
+impl HookCode {
+    ///Parses single hook code.
+    pub fn parse(code: &str) -> Result<Self, String> {
+        let code = code.trim();
+        let prefix = code.get(..2).map(|prefix| prefix.to_uppercase()).unwrap_or_default();
+
+        let kind = match prefix.as_str() {
+            "/H" => Kind::Hook,
+            "/R" => Kind::Read,
+            _ => return Err(format!("Invalid hook code '{}'. It should start with /H or /R", code)),
+        };
+        let body = &code[2..];
+
+        let mut chars = body.chars();
+        let typ = match chars.next().map(|ch| ch.to_ascii_uppercase()) {
+            Some(typ) if kind.types().contains(typ) => typ,
+            Some(typ) => return Err(format!("Unknown type '{}' of {} code. Expected one of {}", typ, kind.prefix(), kind.types())),
+            None => return Err(format!("Missing type of {} code", kind.prefix())),
+        };
+        let mut body = chars.as_str();
+
+        let no_context = body.starts_with('N') || body.starts_with('n');
+        if no_context {
+            body = &body[1..];
+        }
+
+        let (location, address) = match body.find('@') {
+            Some(idx) => (&body[..idx], &body[idx+1..]),
+            None => return Err(format!("Missing address of hook code '{}'", code)),
+        };
+
+        let (codepage, location) = match location.find('#') {
+            Some(idx) => match location[..idx].parse::<u32>() {
+                Ok(codepage) => (Some(codepage), &location[idx+1..]),
+                Err(_) => return Err(format!("Invalid codepage '{}'", &location[..idx])),
+            },
+            None => (None, location),
+        };
+
+        let mut location = location.splitn(2, ':');
+        let data = match location.next() {
+            Some("") if kind == Kind::Read => None,
+            Some("") | None => return Err(format!("Missing data offset of hook code '{}'", code)),
+            Some(data) => Some(parse_offset(data, "data offset")?),
+        };
+        let split = match location.next() {
+            Some(split) => Some(parse_offset(split, "split offset")?),
+            None => None,
+        };
+
+        let mut address = address.splitn(3, ':');
+        let address_text = address.next().unwrap_or("");
+        let address_value = match address_text.len() > 0 && address_text.chars().all(|ch| ch.is_digit(16)) {
+            true => u64::from_str_radix(address_text, 16).map_err(|_| format!("Invalid address '{}'. Number is too big", address_text))?,
+            false => return Err(format!("Invalid address '{}'. Expected hexadecimal number", address_text)),
+        };
+        let module = address.next().map(|module| module.to_owned());
+        let function = address.next().map(|function| function.to_owned());
+
+        if module.as_ref().map(|module| module.len() == 0).unwrap_or(false) {
+            return Err(format!("Empty module name of hook code '{}'", code));
+        }
+        if function.as_ref().map(|function| function.len() == 0).unwrap_or(false) {
+            return Err(format!("Empty function name of hook code '{}'", code));
+        }
+
+        Ok(Self {
+            kind,
+            typ,
+            no_context,
+            codepage,
+            data,
+            split,
+            address: address_value,
+            module,
+            function,
+        })
+    }
+}
+
+impl fmt::Display for HookCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.kind.prefix(), self.typ)?;
+
+        if self.no_context {
+            f.write_str("N")?;
+        }
+        if let Some(codepage) = self.codepage {
+            write!(f, "{}#", codepage)?;
+        }
+        if let Some(ref data) = self.data {
+            write!(f, "{}", data)?;
+        }
+        if let Some(ref split) = self.split {
+            write!(f, ":{}", split)?;
+        }
+
+        write!(f, "@{:X}", self.address)?;
+
+        if let Some(ref module) = self.module {
+            write!(f, ":{}", module)?;
+        }
+        if let Some(ref function) = self.function {
+            write!(f, ":{}", function)?;
+        }
+
+        Ok(())
+    }
+}
+
+///Validates hook code, returning it in normalized form.
+///
+///Code may consist of several hook codes separated by whitespace.
+pub fn normalize(code: &str) -> Result<String, String> {
+    let mut codes = Vec::new();
+
+    for code in code.split_whitespace() {
+        codes.push(HookCode::parse(code)?.to_string());
+    }
+
+    match codes.len() {
+        0 => Err("Hook code is empty".to_owned()),
+        _ => Ok(codes.join(" ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HookCode, Kind, Offset, normalize};
+
+    #[test]
+    fn should_parse_hook_code() {
+        let code = HookCode::parse("/HA-1C*4:-8@4C24F8:game.exe:#12").expect("To parse");
+        assert_eq!(code.kind, Kind::Hook);
+        assert_eq!(code.typ, 'A');
+        assert!(!code.no_context);
+        assert_eq!(code.codepage, None);
+        assert_eq!(code.data, Some(Offset { offset: -0x1c, deref: Some(4) }));
+        assert_eq!(code.split, Some(Offset { offset: -8, deref: None }));
+        assert_eq!(code.address, 0x4c24f8);
+        assert_eq!(code.module.as_ref().map(|module| module.as_str()), Some("game.exe"));
+        assert_eq!(code.function.as_ref().map(|function| function.as_str()), Some("#12"));
+
+        let code = HookCode::parse("/HSN932#4@0").expect("To parse");
+        assert!(code.no_context);
+        assert_eq!(code.codepage, Some(932));
+        assert_eq!(code.data, Some(Offset { offset: 4, deref: None }));
+        assert_eq!(code.address, 0);
+    }
+
+    #[test]
+    fn should_parse_read_code() {
+        let code = HookCode::parse("/RS@44FD0").expect("To parse");
+        assert_eq!(code.kind, Kind::Read);
+        assert_eq!(code.typ, 'S');
+        assert_eq!(code.data, None);
+        assert_eq!(code.address, 0x44fd0);
+
+        let code = HookCode::parse("/RQ65001#-14@44FD0").expect("To parse");
+        assert_eq!(code.codepage, Some(65001));
+        assert_eq!(code.data, Some(Offset { offset: -0x14, deref: None }));
+    }
+
+    #[test]
+    fn should_normalize_code() {
+        assert_eq!(normalize(" /hs-8@0 ").expect("To normalize"), "/HS-8@0");
+        assert_eq!(normalize("/hwn-1c*0@004a1d0:Game.exe").expect("To normalize"), "/HWN-1C*0@4A1D0:Game.exe");
+        assert_eq!(normalize("/HS-8@0  /ha4@1234").expect("To normalize"), "/HS-8@0 /HA4@1234");
+    }
+
+    #[test]
+    fn should_reject_invalid_code() {
+        assert!(normalize("").is_err());
+        assert!(normalize("HS-8@0").is_err());
+        assert!(normalize("/HX-8@0").is_err());
+        assert!(normalize("/HS-8").is_err());
+        assert!(normalize("/HS@0").is_err());
+        assert!(normalize("/HS-8G@0").is_err());
+        assert!(normalize("/HS-8@XYZ").is_err());
+        assert!(normalize("/HS-8@0:").is_err());
+        assert!(normalize("/RA@0").is_err());
+        assert!(normalize("/HS-8@0 garbage").is_err());
+
+        let error = normalize("/HS-8@0x10").unwrap_err();
+        assert_eq!(error, "Invalid address '0x10'. Expected hexadecimal number");
+    }
+}
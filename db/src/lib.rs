@@ -4,27 +4,50 @@ extern crate diesel;
 extern crate log;
 
 extern crate utils;
+extern crate chrono;
 
 pub mod schema;
 pub mod models;
+pub mod migrations;
+pub mod search;
+pub mod hook_code;
 
 use diesel::{
-    sql_query,
     Connection
 };
+use diesel::sql_types::{BigInt, Text};
 pub use diesel::sqlite::SqliteConnection;
+pub use chrono::NaiveDateTime;
 
 pub use diesel::{
     result,
     RunQueryDsl,
     QueryDsl,
     ExpressionMethods,
+    BoolExpressionMethods,
     OptionalExtension,
     BelongingToDsl,
+    GroupedBy,
     TextExpressionMethods
 };
 
 pub const PATH: &'static str = "./roseline.db";
+///Maximum number of VNs returned by search.
+pub const SEARCH_LIMIT: i64 = 100;
+
+const SEARCH_VN: &'static str = "SELECT vns.id, vns.title FROM vn_search
+    INNER JOIN vns ON vns.id = vn_search.vn_id
+    WHERE vn_search MATCH ?
+    ORDER BY bm25(vn_search, 0.0, 10.0, 5.0, 2.0)
+    LIMIT ?";
+const INDEX_VN: &'static str = "INSERT INTO vn_search (vn_id, title, original, aliases) VALUES (?, ?, ?, ?)";
+const UNINDEX_VN: &'static str = "DELETE FROM vn_search WHERE vn_id = ?";
+
+#[derive(QueryableByName)]
+struct Count {
+    #[sql_type = "BigInt"]
+    count: i64
+}
 
 use utils::ResultExt;
 
@@ -33,59 +56,241 @@ pub struct Db {
 }
 
 impl Db {
+    #[inline]
     pub fn new() -> Result<Self, String> {
-        let conn = SqliteConnection::establish(PATH).format_err("To start DB")?;
-        sql_query(schema::create::VNS).execute(&conn).format_err("create table")?;
-        sql_query(schema::create::HOOKS).execute(&conn).format_err("create table")?;
+        Self::open(PATH)
+    }
 
-        Ok(Self {
+    ///Opens DB at specified path, applying pending migrations.
+    pub fn open(path: &str) -> Result<Self, String> {
+        let conn = SqliteConnection::establish(path).format_err("To start DB")?;
+        migrations::run(&conn).format_err("Migrate DB")?;
+
+        let db = Self {
             inner: conn
+        };
+
+        //Index is out of sync after migration or if DB is modified by older version.
+        if db.count_indexed().format_err("Count indexed VNs")? != db.count_vns().format_err("Count VNs")? {
+            db.reindex_vns().format_err("Index VNs")?;
+        }
+
+        Ok(db)
+    }
+
+    ///Adds VN to search index, replacing existing entry.
+    fn index_vn(&self, vn: &models::Vn) -> result::QueryResult<usize> {
+        let aliases = self.get_aliases(vn)?;
+        let (original, aliases): (Vec<_>, Vec<_>) = aliases.into_iter().partition(|alias| alias.kind() == models::AliasKind::Original);
+        let original = original.into_iter().map(|alias| alias.normalized).collect::<Vec<_>>().join(" ");
+        let aliases = aliases.into_iter().map(|alias| alias.normalized).collect::<Vec<_>>().join(" ");
+
+        diesel::sql_query(UNINDEX_VN).bind::<BigInt, _>(vn.id).execute(&self.inner)?;
+        diesel::sql_query(INDEX_VN).bind::<BigInt, _>(vn.id)
+                                   .bind::<Text, _>(search::normalize(&vn.title))
+                                   .bind::<Text, _>(original)
+                                   .bind::<Text, _>(aliases)
+                                   .execute(&self.inner)
+    }
+
+    ///Rebuilds search index of all VNs.
+    pub fn reindex_vns(&self) -> result::QueryResult<usize> {
+        self.inner.transaction(|| {
+            let vns = schema::vns::table.load::<models::Vn>(&self.inner)?;
+            info!("DB: indexing {} VNs", vns.len());
+
+            diesel::sql_query("DELETE FROM vn_search").execute(&self.inner)?;
+            for vn in vns.iter() {
+                self.index_vn(vn)?;
+            }
+
+            Ok(vns.len())
         })
     }
 
-    pub fn delete_vn(&self, id: i64) -> result::QueryResult<usize> {
-        info!("DB: delete VN v{}", id);
-        use schema::vns::dsl;
+    #[inline]
+    fn count_indexed(&self) -> result::QueryResult<i64> {
+        diesel::sql_query("SELECT COUNT(*) AS count FROM vn_search").get_result::<Count>(&self.inner).map(|result| result.count)
+    }
+
+    ///Removes VN alongside with all its hooks, recording each removed hook.
+    pub fn delete_vn(&self, id: i64, author: &models::Author) -> result::QueryResult<usize> {
+        info!("DB: {} deletes VN v{}", author, id);
+
+        self.inner.transaction(|| {
+            let vn = match self.get_vn(id)? {
+                Some(vn) => vn,
+                None => return Ok(0)
+            };
 
-        diesel::delete(dsl::vns.filter(dsl::id.eq(id))).execute(&self.inner)
+            let hooks = self.get_hooks(&vn)?;
+            let revisions = hooks.iter().map(|hook| models::NewHookRevision::new(&vn, &hook.version, Some(&hook.code), None, author))
+                                        .collect::<Vec<_>>();
+            diesel::insert_into(schema::hook_revisions::table).values(&revisions).execute(&self.inner)?;
+
+            //Foreign keys are not enforced so hooks must be removed explicitly.
+            diesel::delete(schema::hooks::table.filter(schema::hooks::vn_id.eq(id))).execute(&self.inner)?;
+            diesel::delete(models::Alias::belonging_to(&vn)).execute(&self.inner)?;
+            diesel::delete(schema::vn_sync::table.find(id)).execute(&self.inner)?;
+            diesel::delete(models::Release::belonging_to(&vn)).execute(&self.inner)?;
+            diesel::delete(models::Executable::belonging_to(&vn)).execute(&self.inner)?;
+            diesel::sql_query(UNINDEX_VN).bind::<BigInt, _>(id).execute(&self.inner)?;
+            diesel::delete(schema::vns::table.find(id)).execute(&self.inner)
+        })
     }
 
-    pub fn delete_hook(&self, vn: &models::Vn, version: &String) -> result::QueryResult<usize> {
-        info!("DB: delete version='{}' from v{}", &version, vn.id);
+    ///Removes hooks matching version, recording each removed hook.
+    pub fn delete_hook(&self, vn: &models::Vn, version: &String, author: &models::Author) -> result::QueryResult<usize> {
+        info!("DB: {} deletes version='{}' from v{}", author, &version, vn.id);
         use schema::hooks::dsl;
 
-        diesel::delete(dsl::hooks.filter(dsl::vn_id.eq(&vn.id))
-                                 .filter(dsl::version.like(version))).execute(&self.inner)
+        self.inner.transaction(|| {
+            let hooks = models::Hook::belonging_to(vn).filter(dsl::version.like(version))
+                                                      .load::<models::Hook>(&self.inner)?;
+
+            let revisions = hooks.iter().map(|hook| models::NewHookRevision::new(vn, &hook.version, Some(&hook.code), None, author))
+                                        .collect::<Vec<_>>();
+            diesel::insert_into(schema::hook_revisions::table).values(&revisions).execute(&self.inner)?;
+
+            diesel::delete(dsl::hooks.filter(dsl::vn_id.eq(&vn.id))
+                                     .filter(dsl::version.like(version))).execute(&self.inner)
+        })
     }
-    pub fn put_hook(&self, vn: &models::Vn, version: String, code: String) -> result::QueryResult<models::HookView> {
+
+    ///Adds or updates hook, recording change.
+    pub fn put_hook(&self, vn: &models::Vn, version: String, code: String, author: &models::Author) -> result::QueryResult<models::HookView> {
         debug!("DB: put hook='{}' for version='{}'", code, version);
         use schema::hooks::dsl;
-        let hook = models::Hook::belonging_to(vn).filter(dsl::version.like(&version))
-                                                 .first::<models::Hook>(&self.inner)
-                                                 .optional()?;
-
-        match hook {
-            Some(hook) => {
-                info!("DB: found existing hook, update {:?} with code={}", hook, &code);
-                diesel::update(dsl::hooks.filter(dsl::id.eq(hook.id)))
-                       .set(dsl::code.eq(&code))
-                       .execute(&self.inner).map(move |_| models::HookView { vn_id: hook.vn_id, version: hook.version, code: code })
-            }
-            None => {
-                let hook = models::HookView {
-                    vn_id: vn.id,
-                    version,
-                    code
-                };
-                info!("DB: adding new hook for v{}: version='{}' | hook='{}'", hook.vn_id, &hook.version, &hook.code);
-                diesel::insert_into(dsl::hooks).values(&hook)
-                                               .execute(&self.inner).map(|_| hook)
+
+        self.inner.transaction(|| {
+            let hook = models::Hook::belonging_to(vn).filter(dsl::version.like(&version))
+                                                     .first::<models::Hook>(&self.inner)
+                                                     .optional()?;
+
+            match hook {
+                Some(hook) => {
+                    if hook.code == code {
+                        return Ok(models::HookView {
+                            vn_id: hook.vn_id,
+                            version: hook.version,
+                            code: hook.code,
+                            author: hook.author,
+                            frontend: hook.frontend,
+                            created_at: hook.created_at,
+                            updated_at: hook.updated_at,
+                            release_id: hook.release_id
+                        });
+                    }
+
+                    info!("DB: {} updates {:?} with code={}", author, hook, &code);
+                    self.put_revision(models::NewHookRevision::new(vn, &hook.version, Some(&hook.code), Some(&code), author))?;
+
+                    let now = chrono::Utc::now().naive_utc();
+                    diesel::update(dsl::hooks.filter(dsl::id.eq(hook.id)))
+                           .set((dsl::code.eq(&code),
+                                 dsl::author.eq(&author.name),
+                                 dsl::frontend.eq(author.frontend.as_str()),
+                                 dsl::updated_at.eq(now)))
+                           .execute(&self.inner)?;
+
+                    Ok(models::HookView {
+                        vn_id: hook.vn_id,
+                        version: hook.version,
+                        code: code,
+                        author: Some(author.name.clone()),
+                        frontend: Some(author.frontend.as_str().to_owned()),
+                        created_at: hook.created_at,
+                        updated_at: Some(now),
+                        release_id: hook.release_id
+                    })
+                }
+                None => {
+                    //Hooks for known releases are linked to them.
+                    let release_id = match models::release_ref(&version) {
+                        Some(id) => schema::releases::table.find(id)
+                                                           .filter(schema::releases::vn_id.eq(vn.id))
+                                                           .select(schema::releases::id)
+                                                           .first::<i64>(&self.inner)
+                                                           .optional()?,
+                        None => None
+                    };
+
+                    let now = chrono::Utc::now().naive_utc();
+                    let hook = models::HookView {
+                        vn_id: vn.id,
+                        version,
+                        code,
+                        author: Some(author.name.clone()),
+                        frontend: Some(author.frontend.as_str().to_owned()),
+                        created_at: Some(now),
+                        updated_at: Some(now),
+                        release_id
+                    };
+                    info!("DB: {} adds new hook for v{}: version='{}' | hook='{}'", author, hook.vn_id, &hook.version, &hook.code);
+                    self.put_revision(models::NewHookRevision::new(vn, &hook.version, None, Some(&hook.code), author))?;
+                    diesel::insert_into(dsl::hooks).values(&hook)
+                                                   .execute(&self.inner).map(|_| hook)
+                }
             }
+        })
+    }
+
+    #[inline]
+    fn put_revision(&self, revision: models::NewHookRevision) -> result::QueryResult<usize> {
+        diesel::insert_into(schema::hook_revisions::table).values(&revision).execute(&self.inner)
+    }
+
+    ///Retrieves latest revisions, optionally only for particular VN.
+    pub fn get_revisions(&self, vn_id: Option<i64>, limit: i64) -> result::QueryResult<Vec<models::HookRevision>> {
+        use schema::hook_revisions::dsl;
+
+        match vn_id {
+            Some(vn_id) => dsl::hook_revisions.filter(dsl::vn_id.eq(vn_id))
+                                              .order(dsl::id.desc())
+                                              .limit(limit)
+                                              .load::<models::HookRevision>(&self.inner),
+            None => dsl::hook_revisions.order(dsl::id.desc())
+                                       .limit(limit)
+                                       .load::<models::HookRevision>(&self.inner)
         }
     }
 
+    #[inline]
+    pub fn get_revision(&self, id: i64) -> result::QueryResult<Option<models::HookRevision>> {
+        schema::hook_revisions::table.find(id).first::<models::HookRevision>(&self.inner).optional()
+    }
+
+    ///Reverts hook to the state it had before specified revision.
+    ///
+    ///VN is re-added if it has been removed since.
+    ///The restoration itself is recorded as new revision.
+    pub fn restore_revision(&self, id: i64, author: &models::Author) -> result::QueryResult<Restore> {
+        self.inner.transaction(|| {
+            let revision = match self.get_revision(id)? {
+                Some(revision) => revision,
+                None => return Ok(Restore::NotFound)
+            };
+
+            info!("DB: {} restores revision #{}", author, id);
+            let vn = self.put_vn(revision.vn_id, revision.vn_title)?;
+
+            match revision.old_code {
+                Some(code) => self.put_hook(&vn, revision.version, code, author).map(Restore::Put),
+                None => self.delete_hook(&vn, &revision.version, author).map(Restore::Removed),
+            }
+        })
+    }
+
     ///Inserts VN if it is missing, or return existing one.
+    #[inline]
     pub fn put_vn(&self, id: i64, title: String) -> result::QueryResult<models::Vn> {
+        self.put_vn_aliased(id, title, None, &[])
+    }
+
+    ///Inserts VN with its original title and aliases from VNDB if it is missing, or return existing one.
+    ///
+    ///Aliases of existing VN are left intact.
+    pub fn put_vn_aliased(&self, id: i64, title: String, original: Option<&str>, aliases: &[String]) -> result::QueryResult<models::Vn> {
         use schema::vns::dsl;
 
         let vn = self.get_vn(id)?;
@@ -96,16 +301,183 @@ impl Db {
                 let vn = models::Vn { id, title };
                 info!("DB: put {:?}", &vn);
 
-                diesel::insert_into(dsl::vns).values(&vn)
-                                             .execute(&self.inner).map(|_| vn)
+                self.inner.transaction(|| {
+                    diesel::insert_into(dsl::vns).values(&vn).execute(&self.inner)?;
+
+                    diesel::insert_into(schema::aliases::table).values(&vndb_aliases(&vn, original, aliases, &[]))
+                                                               .execute(&self.inner)?;
+
+                    self.index_vn(&vn)
+                }).map(|_| vn)
             }
         }
     }
 
+    ///Retrieves VNs that have not been synchronized with VNDB for `age` seconds.
+    ///
+    ///Never synchronized VNs go first, then the ones synchronized long ago.
+    pub fn get_unsynced_vns(&self, age: i64, limit: i64) -> result::QueryResult<Vec<models::Vn>> {
+        use schema::vn_sync::dsl;
+
+        let before = chrono::Utc::now().naive_utc() - chrono::Duration::seconds(age);
+
+        //SQLite puts NULLs first in ascending order.
+        schema::vns::table.left_join(dsl::vn_sync)
+                          .filter(dsl::synced_at.is_null().or(dsl::synced_at.lt(before)))
+                          .order((dsl::synced_at.asc(), schema::vns::id.asc()))
+                          .select(schema::vns::all_columns)
+                          .limit(limit)
+                          .load::<models::Vn>(&self.inner)
+    }
+
+    ///Updates VN's title, original title and aliases with VNDB's data.
+    ///
+    ///Aliases added by users are left intact.
+    pub fn sync_vn(&self, vn: &models::Vn, title: String, original: Option<&str>, aliases: &[String]) -> result::QueryResult<models::VnChanges> {
+        use schema::aliases::dsl;
+
+        self.inner.transaction(|| {
+            let mut changes = models::VnChanges::default();
+            let vn = match vn.title == title {
+                true => vn.clone(),
+                false => {
+                    info!("DB: v{} is renamed from '{}' to '{}'", vn.id, vn.title, title);
+                    diesel::update(schema::vns::table.find(vn.id)).set(schema::vns::title.eq(&title))
+                                                                 .execute(&self.inner)?;
+                    changes.renamed = Some(vn.title.clone());
+                    models::Vn { id: vn.id, title }
+                }
+            };
+
+            let (user, old): (Vec<_>, Vec<_>) = self.get_aliases(&vn)?.into_iter().partition(|alias| alias.kind() == models::AliasKind::User);
+            let user = user.iter().map(|alias| alias.alias.as_str()).collect::<Vec<_>>();
+            let new = vndb_aliases(&vn, original, aliases, &user);
+
+            let is_same = old.len() == new.len() && old.iter().zip(new.iter()).all(|(old, new)| old.alias == new.alias && old.kind == new.kind);
+            if !is_same {
+                info!("DB: update aliases of v{}", vn.id);
+                diesel::delete(models::Alias::belonging_to(&vn).filter(dsl::kind.ne(models::AliasKind::User.as_str()))).execute(&self.inner)?;
+                diesel::insert_into(dsl::aliases).values(&new).execute(&self.inner)?;
+                changes.aliases = true;
+            }
+
+            if changes.renamed.is_some() || changes.aliases {
+                self.index_vn(&vn)?;
+            }
+
+            diesel::replace_into(schema::vn_sync::table).values(&models::VnSync::new(&vn, false)).execute(&self.inner)?;
+            Ok(changes)
+        })
+    }
+
+    ///Flags VN as no longer known to VNDB.
+    ///
+    ///Returns whether VN has not been flagged before.
+    pub fn mark_vn_missing(&self, vn: &models::Vn) -> result::QueryResult<bool> {
+        self.inner.transaction(|| {
+            let sync = schema::vn_sync::table.find(vn.id).first::<models::VnSync>(&self.inner).optional()?;
+            let is_new = sync.map(|sync| !sync.missing).unwrap_or(true);
+
+            if is_new {
+                warn!("DB: v{} '{}' is missing on VNDB", vn.id, vn.title);
+            }
+
+            diesel::replace_into(schema::vn_sync::table).values(&models::VnSync::new(vn, true)).execute(&self.inner)?;
+            Ok(is_new)
+        })
+    }
+
+    #[inline]
+    pub fn get_aliases(&self, vn: &models::Vn) -> result::QueryResult<Vec<models::Alias>> {
+        use schema::aliases::dsl;
+
+        models::Alias::belonging_to(vn).order(dsl::id.asc()).load::<models::Alias>(&self.inner)
+    }
+
+    ///Adds user's alias to VN or returns existing one.
+    pub fn put_alias(&self, vn: &models::Vn, alias: &str, author: &models::Author) -> result::QueryResult<models::Alias> {
+        use schema::aliases::dsl;
+
+        self.inner.transaction(|| {
+            let existing = models::Alias::belonging_to(vn).filter(dsl::alias.eq(alias))
+                                                          .first::<models::Alias>(&self.inner)
+                                                          .optional()?;
+
+            if let Some(existing) = existing {
+                return Ok(existing);
+            }
+
+            info!("DB: {} adds alias '{}' to v{}", author, alias, vn.id);
+            diesel::insert_into(dsl::aliases).values(&models::NewAlias::user(vn, alias, author))
+                                             .execute(&self.inner)?;
+            self.index_vn(vn)?;
+            models::Alias::belonging_to(vn).filter(dsl::alias.eq(alias)).first::<models::Alias>(&self.inner)
+        })
+    }
+
+    ///Removes VN's alias.
+    pub fn delete_alias(&self, vn: &models::Vn, alias: &str, author: &models::Author) -> result::QueryResult<usize> {
+        use schema::aliases::dsl;
+
+        info!("DB: {} removes alias '{}' from v{}", author, alias, vn.id);
+        self.inner.transaction(|| {
+            let result = diesel::delete(models::Alias::belonging_to(vn).filter(dsl::alias.eq(alias))).execute(&self.inner)?;
+
+            if result > 0 {
+                self.index_vn(vn)?;
+            }
+
+            Ok(result)
+        })
+    }
+
+    ///Looks up VN by title, original title or alias.
+    ///
+    ///Exact matches of normalized title are returned alone, then VNs with exactly matching alias.
+    ///Otherwise returns search results.
+    pub fn find_vn(&self, title: &str) -> result::QueryResult<Vec<models::Vn>> {
+        use schema::aliases::dsl;
+
+        let normalized = search::normalize(title);
+        let vns = self.search_vn(title)?;
+
+        let (exact, vns): (Vec<_>, Vec<_>) = vns.into_iter().partition(|vn| search::normalize(&vn.title) == normalized);
+        if exact.len() > 0 {
+            return Ok(exact);
+        } else if normalized.len() == 0 {
+            return Ok(vns);
+        }
+
+        let aliased = schema::vns::table.inner_join(dsl::aliases)
+                                        .filter(dsl::normalized.eq(&normalized))
+                                        .select(schema::vns::all_columns)
+                                        .distinct()
+                                        .load::<models::Vn>(&self.inner)?;
+
+        match aliased.len() {
+            0 => Ok(vns),
+            _ => Ok(aliased)
+        }
+    }
+
+    ///Searches VNs by title, best matches first.
+    ///
+    ///Falls back to substring match when full-text search finds nothing.
     pub fn search_vn(&self, title: &str) -> result::QueryResult<Vec<models::Vn>> {
         use schema::vns::dsl;
 
-        schema::vns::table.filter(dsl::title.like(format!("%{}%", title)))
+        if let Some(query) = search::match_query(title) {
+            let vns = diesel::sql_query(SEARCH_VN).bind::<Text, _>(query)
+                                                  .bind::<BigInt, _>(SEARCH_LIMIT)
+                                                  .load::<models::Vn>(&self.inner)?;
+
+            if vns.len() > 0 {
+                return Ok(vns);
+            }
+        }
+
+        schema::vns::table.filter(dsl::title.like(format!("%{}%", title.trim())))
+                          .limit(SEARCH_LIMIT)
                           .load::<models::Vn>(&self.inner)
     }
 
@@ -119,6 +491,422 @@ impl Db {
         models::Hook::belonging_to(vn).get_results(&self.inner)
     }
 
+    ///Retrieves VNs that have at least one hook, ordered by id.
+    pub fn get_hooked_vns(&self) -> result::QueryResult<Vec<models::Vn>> {
+        use schema::vns::dsl;
+
+        let hooked = schema::hooks::table.select(schema::hooks::dsl::vn_id);
+        dsl::vns.filter(dsl::id.eq_any(hooked)).order(dsl::id.asc()).load::<models::Vn>(&self.inner)
+    }
+
+    ///Retrieves VNs that have at least one hook, ordered by id, along with their hooks, releases and executables.
+    ///
+    ///Each table is loaded by single query, regardless of number of VNs.
+    pub fn get_hooked_vns_data(&self) -> result::QueryResult<Vec<(models::Vn, Vec<models::Hook>, Vec<models::Release>, Vec<models::Executable>)>> {
+        use schema::hooks::dsl as hooks;
+        use schema::releases::dsl as releases;
+        use schema::executables::dsl as executables;
+
+        self.inner.transaction(|| {
+            let vns = self.get_hooked_vns()?;
+
+            let vn_hooks = hooks::hooks.order(hooks::id.asc())
+                                       .load::<models::Hook>(&self.inner)?
+                                       .grouped_by(&vns);
+            let vn_releases = releases::releases.filter(releases::vn_id.eq_any(hooks::hooks.select(hooks::vn_id)))
+                                                .order(releases::id.asc())
+                                                .load::<models::Release>(&self.inner)?
+                                                .grouped_by(&vns);
+            let vn_executables = executables::executables.filter(executables::vn_id.eq_any(hooks::hooks.select(hooks::vn_id)))
+                                                         .order((executables::name.asc(), executables::id.asc()))
+                                                         .load::<models::Executable>(&self.inner)?
+                                                         .grouped_by(&vns);
+
+            let data = vns.into_iter().zip(vn_hooks).zip(vn_releases).zip(vn_executables)
+                                      .map(|(((vn, hooks), releases), executables)| (vn, hooks, releases, executables))
+                                      .collect();
+
+            Ok(data)
+        })
+    }
+
+    #[inline]
+    pub fn get_release(&self, id: i64) -> result::QueryResult<Option<models::Release>> {
+        schema::releases::table.find(id).first::<models::Release>(&self.inner).optional()
+    }
+
+    #[inline]
+    pub fn get_releases(&self, vn: &models::Vn) -> result::QueryResult<Vec<models::Release>> {
+        use schema::releases::dsl;
+
+        models::Release::belonging_to(vn).order(dsl::id.asc()).load::<models::Release>(&self.inner)
+    }
+
+    ///Adds release or updates existing one.
+    ///
+    ///Existing hooks with release's version are linked to it.
+    pub fn put_release(&self, release: &models::Release) -> result::QueryResult<usize> {
+        use schema::hooks::dsl;
+
+        debug!("DB: put {:?}", release);
+        self.inner.transaction(|| {
+            diesel::replace_into(schema::releases::table).values(release).execute(&self.inner)?;
+            diesel::update(dsl::hooks.filter(dsl::vn_id.eq(release.vn_id)).filter(dsl::version.eq(release.version())))
+                   .set(dsl::release_id.eq(release.id))
+                   .execute(&self.inner)
+        })
+    }
+
+    #[inline]
+    pub fn get_executables(&self, vn: &models::Vn) -> result::QueryResult<Vec<models::Executable>> {
+        use schema::executables::dsl;
+
+        models::Executable::belonging_to(vn).order((dsl::name.asc(), dsl::id.asc())).load::<models::Executable>(&self.inner)
+    }
+
+    ///Looks up executables by normalized name.
+    ///
+    ///If hash is provided, executables with the same hash are preferred,
+    ///falling back to executables without hash.
+    pub fn find_executables(&self, name: &str, hash: Option<&str>) -> result::QueryResult<Vec<models::Executable>> {
+        use schema::executables::dsl;
+
+        let executables = dsl::executables.filter(dsl::name.eq(name)).order(dsl::id.asc()).load::<models::Executable>(&self.inner)?;
+
+        let hash = match hash {
+            Some(hash) => hash,
+            None => return Ok(executables)
+        };
+
+        match executables.iter().any(|executable| executable.hash.as_ref().map(|own| own.as_str()) == Some(hash)) {
+            true => Ok(executables.into_iter().filter(|executable| executable.hash.as_ref().map(|own| own.as_str()) == Some(hash)).collect()),
+            false => Ok(executables.into_iter().filter(|executable| executable.hash.is_none()).collect()),
+        }
+    }
+
+    fn get_executable(&self, vn: &models::Vn, name: &str, hash: Option<&str>) -> result::QueryResult<Option<models::Executable>> {
+        use schema::executables::dsl;
+
+        let executables = models::Executable::belonging_to(vn).filter(dsl::name.eq(name)).load::<models::Executable>(&self.inner)?;
+        Ok(executables.into_iter().find(|executable| executable.hash.as_ref().map(|own| own.as_str()) == hash))
+    }
+
+    ///Adds executable of VN or updates release of existing one.
+    ///
+    ///Name and hash are expected to be normalized.
+    pub fn put_executable(&self, vn: &models::Vn, name: &str, hash: Option<&str>, release_id: Option<i64>, author: &models::Author) -> result::QueryResult<models::Executable> {
+        use schema::executables::dsl;
+
+        self.inner.transaction(|| {
+            match self.get_executable(vn, name, hash)? {
+                Some(ref existing) if existing.release_id == release_id => return Ok(existing.clone()),
+                Some(existing) => {
+                    info!("DB: {} links executable {} to release {:?}", author, existing, release_id);
+                    diesel::update(dsl::executables.find(existing.id)).set(dsl::release_id.eq(release_id))
+                                                                      .execute(&self.inner)?;
+                },
+                None => {
+                    info!("DB: {} adds executable '{}' to v{}", author, name, vn.id);
+                    diesel::insert_into(dsl::executables).values(&models::NewExecutable::new(name, hash, vn, release_id, author))
+                                                         .execute(&self.inner)?;
+                }
+            }
+
+            self.get_executable(vn, name, hash)?.ok_or(result::Error::NotFound)
+        })
+    }
+
+    ///Removes executable of VN.
+    pub fn delete_executable(&self, vn: &models::Vn, name: &str, hash: Option<&str>, author: &models::Author) -> result::QueryResult<usize> {
+        use schema::executables::dsl;
+
+        self.inner.transaction(|| {
+            match self.get_executable(vn, name, hash)? {
+                Some(existing) => {
+                    info!("DB: {} removes executable {}", author, existing);
+                    diesel::delete(dsl::executables.find(existing.id)).execute(&self.inner)
+                },
+                None => Ok(0)
+            }
+        })
+    }
+
+    #[inline]
+    pub fn get_ignores(&self) -> result::QueryResult<Vec<models::Ignore>> {
+        schema::ignores::table.load::<models::Ignore>(&self.inner)
+    }
+
+    ///Adds mask to ignore list or returns existing entry.
+    pub fn put_ignore(&self, pattern: &str, reason: Option<&str>, author: &models::Author) -> result::QueryResult<models::Ignore> {
+        use schema::ignores::dsl;
+
+        self.inner.transaction(|| {
+            let ignore = dsl::ignores.filter(dsl::pattern.eq(pattern)).first::<models::Ignore>(&self.inner).optional()?;
+
+            if let Some(ignore) = ignore {
+                return Ok(ignore);
+            }
+
+            info!("DB: {} ignores '{}'", author, pattern);
+            diesel::insert_into(dsl::ignores).values(&models::NewIgnore::new(pattern, reason, author))
+                                             .execute(&self.inner)?;
+            dsl::ignores.filter(dsl::pattern.eq(pattern)).first::<models::Ignore>(&self.inner)
+        })
+    }
+
+    pub fn delete_ignore(&self, pattern: &str, author: &models::Author) -> result::QueryResult<usize> {
+        use schema::ignores::dsl;
+
+        info!("DB: {} removes '{}' from ignore list", author, pattern);
+        diesel::delete(dsl::ignores.filter(dsl::pattern.eq(pattern))).execute(&self.inner)
+    }
+
+    #[inline]
+    pub fn get_user(&self, name: &str) -> result::QueryResult<Option<models::User>> {
+        use schema::users::dsl;
+
+        dsl::users.filter(dsl::name.eq(name)).first::<models::User>(&self.inner).optional()
+    }
+
+    ///Adds user or updates password and role of existing one.
+    pub fn put_user(&self, name: &str, password: &str, role: models::Role) -> result::QueryResult<models::User> {
+        use schema::users::dsl;
+
+        self.inner.transaction(|| {
+            match self.get_user(name)? {
+                Some(user) => {
+                    info!("DB: update user '{}' with role={}", name, role);
+                    diesel::update(dsl::users.filter(dsl::id.eq(user.id)))
+                           .set((dsl::password.eq(password), dsl::role.eq(role.as_str())))
+                           .execute(&self.inner)?;
+                },
+                None => {
+                    info!("DB: add user '{}' with role={}", name, role);
+                    diesel::insert_into(dsl::users).values(&models::NewUser::new(name, password, role))
+                                                   .execute(&self.inner)?;
+                }
+            }
+
+            dsl::users.filter(dsl::name.eq(name)).first::<models::User>(&self.inner)
+        })
+    }
+
+    ///Removes user alongside with all its sessions.
+    pub fn delete_user(&self, name: &str) -> result::QueryResult<usize> {
+        use schema::users::dsl;
+
+        self.inner.transaction(|| {
+            let user = match self.get_user(name)? {
+                Some(user) => user,
+                None => return Ok(0)
+            };
+
+            info!("DB: remove user '{}'", name);
+            diesel::delete(models::Session::belonging_to(&user)).execute(&self.inner)?;
+            diesel::delete(models::ApiToken::belonging_to(&user)).execute(&self.inner)?;
+            diesel::delete(dsl::users.filter(dsl::id.eq(user.id))).execute(&self.inner)
+        })
+    }
+
+    #[inline]
+    pub fn put_session(&self, session: &models::Session) -> result::QueryResult<usize> {
+        diesel::insert_into(schema::sessions::table).values(session).execute(&self.inner)
+    }
+
+    ///Retrieves session that has not expired yet alongside with its user.
+    pub fn get_session(&self, id: &str) -> result::QueryResult<Option<(models::Session, models::User)>> {
+        use schema::sessions::dsl;
+
+        let now = chrono::Utc::now().naive_utc();
+        dsl::sessions.inner_join(schema::users::table)
+                     .filter(dsl::id.eq(id))
+                     .filter(dsl::expires_at.gt(now))
+                     .first::<(models::Session, models::User)>(&self.inner)
+                     .optional()
+    }
+
+    #[inline]
+    pub fn delete_session(&self, id: &str) -> result::QueryResult<usize> {
+        use schema::sessions::dsl;
+
+        diesel::delete(dsl::sessions.filter(dsl::id.eq(id))).execute(&self.inner)
+    }
+
+    #[inline]
+    pub fn delete_expired_sessions(&self) -> result::QueryResult<usize> {
+        use schema::sessions::dsl;
+
+        let now = chrono::Utc::now().naive_utc();
+        diesel::delete(dsl::sessions.filter(dsl::expires_at.le(now))).execute(&self.inner)
+    }
+
+    ///Stores new API token.
+    ///
+    ///`token` must be already hashed.
+    pub fn put_api_token(&self, user: &models::User, name: &str, token: &str, scope: models::Scope) -> result::QueryResult<models::ApiToken> {
+        use schema::api_tokens::dsl;
+
+        info!("DB: add API token '{}' with scope={} for user '{}'", name, scope, user.name);
+        self.inner.transaction(|| {
+            diesel::insert_into(dsl::api_tokens).values(&models::NewApiToken::new(user, name, token, scope))
+                                                .execute(&self.inner)?;
+            dsl::api_tokens.filter(dsl::token.eq(token)).first::<models::ApiToken>(&self.inner)
+        })
+    }
+
+    ///Retrieves API token by its hash alongside with its user.
+    pub fn get_api_token(&self, token: &str) -> result::QueryResult<Option<(models::ApiToken, models::User)>> {
+        use schema::api_tokens::dsl;
+
+        dsl::api_tokens.inner_join(schema::users::table)
+                       .filter(dsl::token.eq(token))
+                       .first::<(models::ApiToken, models::User)>(&self.inner)
+                       .optional()
+    }
+
+    #[inline]
+    pub fn get_api_tokens(&self, user: &models::User) -> result::QueryResult<Vec<models::ApiToken>> {
+        models::ApiToken::belonging_to(user).load::<models::ApiToken>(&self.inner)
+    }
+
+    #[inline]
+    pub fn delete_api_token(&self, id: i64) -> result::QueryResult<usize> {
+        use schema::api_tokens::dsl;
+
+        info!("DB: revoke API token #{}", id);
+        diesel::delete(dsl::api_tokens.filter(dsl::id.eq(id))).execute(&self.inner)
+    }
+
+    ///Queues hook for moderator's review.
+    pub fn put_pending_hook(&self, vn: &models::Vn, version: &str, code: &str, author: &models::Author, contact: Option<&str>) -> result::QueryResult<models::PendingHook> {
+        use schema::pending_hooks::dsl;
+
+        info!("DB: {} submits hook='{}' for v{} version='{}'", author, code, vn.id, version);
+        self.inner.transaction(|| {
+            diesel::insert_into(dsl::pending_hooks).values(&models::NewPendingHook::new(vn, version, code, author, contact))
+                                                   .execute(&self.inner)?;
+            dsl::pending_hooks.order(dsl::id.desc()).first::<models::PendingHook>(&self.inner)
+        })
+    }
+
+    #[inline]
+    pub fn get_pending_hook(&self, id: i64) -> result::QueryResult<Option<models::PendingHook>> {
+        schema::pending_hooks::table.find(id).first::<models::PendingHook>(&self.inner).optional()
+    }
+
+    ///Retrieves submissions awaiting review, oldest first.
+    pub fn get_pending_hooks(&self) -> result::QueryResult<Vec<models::PendingHook>> {
+        use schema::pending_hooks::dsl;
+
+        dsl::pending_hooks.filter(dsl::status.eq(models::Review::Pending.as_str()))
+                          .order(dsl::id.asc())
+                          .load::<models::PendingHook>(&self.inner)
+    }
+
+    ///Retrieves latest submissions of author, including reviewed ones.
+    pub fn get_submissions(&self, author: &models::Author, limit: i64) -> result::QueryResult<Vec<models::PendingHook>> {
+        use schema::pending_hooks::dsl;
+
+        dsl::pending_hooks.filter(dsl::author.eq(&author.name))
+                          .filter(dsl::frontend.eq(author.frontend.as_str()))
+                          .order(dsl::id.desc())
+                          .limit(limit)
+                          .load::<models::PendingHook>(&self.inner)
+    }
+
+    ///Accepts submission, optionally replacing its code, and stores hook on behalf of submitter.
+    ///
+    ///Returns `None` if there is no such submission awaiting review.
+    pub fn approve_pending_hook(&self, id: i64, code: Option<String>, reviewer: &models::Author) -> result::QueryResult<Option<(models::PendingHook, models::HookView)>> {
+        use schema::pending_hooks::dsl;
+
+        self.inner.transaction(|| {
+            let mut pending = match self.get_pending_hook(id)? {
+                Some(ref pending) if pending.review() != models::Review::Pending => return Ok(None),
+                Some(pending) => pending,
+                None => return Ok(None)
+            };
+
+            info!("DB: {} approves {}", reviewer, pending);
+            if let Some(code) = code {
+                pending.code = code;
+            }
+
+            let author = pending.submitter().unwrap_or_else(|| reviewer.clone());
+            let vn = self.put_vn(pending.vn_id, pending.vn_title.clone())?;
+            let hook = self.put_hook(&vn, pending.version.clone(), pending.code.clone(), &author)?;
+
+            let now = chrono::Utc::now().naive_utc();
+            diesel::update(dsl::pending_hooks.filter(dsl::id.eq(pending.id)))
+                   .set((dsl::code.eq(&pending.code),
+                         dsl::status.eq(models::Review::Approved.as_str()),
+                         dsl::reviewer.eq(&reviewer.name),
+                         dsl::reviewed_at.eq(now)))
+                   .execute(&self.inner)?;
+
+            pending.status = models::Review::Approved.as_str().to_owned();
+            pending.reviewer = Some(reviewer.name.clone());
+            pending.reviewed_at = Some(now);
+
+            Ok(Some((pending, hook)))
+        })
+    }
+
+    ///Rejects submission.
+    ///
+    ///Returns `None` if there is no such submission awaiting review.
+    pub fn reject_pending_hook(&self, id: i64, reason: Option<&str>, reviewer: &models::Author) -> result::QueryResult<Option<models::PendingHook>> {
+        use schema::pending_hooks::dsl;
+
+        self.inner.transaction(|| {
+            let mut pending = match self.get_pending_hook(id)? {
+                Some(ref pending) if pending.review() != models::Review::Pending => return Ok(None),
+                Some(pending) => pending,
+                None => return Ok(None)
+            };
+
+            info!("DB: {} rejects {}", reviewer, pending);
+            let now = chrono::Utc::now().naive_utc();
+            diesel::update(dsl::pending_hooks.filter(dsl::id.eq(pending.id)))
+                   .set((dsl::status.eq(models::Review::Rejected.as_str()),
+                         dsl::reviewer.eq(&reviewer.name),
+                         dsl::reason.eq(reason),
+                         dsl::reviewed_at.eq(now)))
+                   .execute(&self.inner)?;
+
+            pending.status = models::Review::Rejected.as_str().to_owned();
+            pending.reviewer = Some(reviewer.name.clone());
+            pending.reason = reason.map(|reason| reason.to_owned());
+            pending.reviewed_at = Some(now);
+
+            Ok(Some(pending))
+        })
+    }
+
+    ///Retrieves reviewed submissions whose authors can be notified via `frontends`,
+    ///marking them as notified.
+    pub fn take_reviewed_submissions(&self, frontends: &[models::Frontend]) -> result::QueryResult<Vec<models::PendingHook>> {
+        use schema::pending_hooks::dsl;
+
+        let frontends = frontends.iter().map(|frontend| frontend.as_str()).collect::<Vec<_>>();
+
+        self.inner.transaction(|| {
+            let reviewed = dsl::pending_hooks.filter(dsl::status.ne(models::Review::Pending.as_str()))
+                                             .filter(dsl::notified.eq(false))
+                                             .filter(dsl::contact.is_not_null())
+                                             .filter(dsl::frontend.eq_any(frontends))
+                                             .order(dsl::id.asc())
+                                             .load::<models::PendingHook>(&self.inner)?;
+
+            let ids = reviewed.iter().map(|pending| pending.id).collect::<Vec<_>>();
+            diesel::update(dsl::pending_hooks.filter(dsl::id.eq_any(ids)))
+                   .set(dsl::notified.eq(true))
+                   .execute(&self.inner)?;
+
+            Ok(reviewed)
+        })
+    }
+
     #[inline]
     pub fn count_vns(&self) -> result::QueryResult<i64> {
         Self::vns().count().get_result::<i64>(&self.inner)
@@ -142,6 +930,33 @@ impl Db {
     }
 }
 
+///Creates aliases of VN from VNDB's original title and aliases.
+///
+///Empty ones, duplicates and the ones matching title or `skip` are omitted.
+fn vndb_aliases<'a>(vn: &models::Vn, original: Option<&'a str>, aliases: &'a [String], skip: &[&str]) -> Vec<models::NewAlias<'a>> {
+    let mut result: Vec<models::NewAlias> = Vec::with_capacity(aliases.len() + 1);
+    let originals = original.into_iter().map(|alias| (alias, models::AliasKind::Original));
+    let aliases = aliases.iter().map(|alias| (alias.as_str(), models::AliasKind::Vndb));
+
+    for (alias, kind) in originals.chain(aliases) {
+        if alias.trim().len() > 0 && alias != vn.title && !skip.contains(&alias) && !result.iter().any(|new| new.alias == alias) {
+            result.push(models::NewAlias::vndb(vn, alias, kind));
+        }
+    }
+
+    result
+}
+
+///Outcome of revision restoration.
+pub enum Restore {
+    ///No such revision.
+    NotFound,
+    ///Hook's code is restored.
+    Put(models::HookView),
+    ///Hook is removed as revision added it.
+    Removed(usize),
+}
+
 impl ::std::ops::Deref for Db {
     type Target = SqliteConnection;
 
@@ -149,3 +964,137 @@ impl ::std::ops::Deref for Db {
         &self.inner
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Db;
+    use super::models::{Author, Frontend, Review, Vn};
+
+    fn submit(db: &Db, contact: Option<&str>) -> i64 {
+        let vn = db.put_vn(1, "Ever17".to_owned()).expect("To put VN");
+        let author = Author::new("user", Frontend::Irc);
+
+        db.put_pending_hook(&vn, "v1.0", "/HS-4@0:test.exe", &author, contact).expect("To submit hook").id
+    }
+
+    #[test]
+    fn should_approve_pending_hook() {
+        let db = Db::open(":memory:").expect("To open DB");
+        let id = submit(&db, Some("user"));
+        let reviewer = Author::new("moderator", Frontend::Irc);
+
+        let (pending, hook) = db.approve_pending_hook(id, Some("/HS-8@0:test.exe".to_owned()), &reviewer).expect("To approve").expect("To find submission");
+        assert_eq!(pending.review(), Review::Approved);
+        assert_eq!(pending.reviewer.as_ref().map(|reviewer| reviewer.as_str()), Some("moderator"));
+        assert_eq!(hook.code, "/HS-8@0:test.exe");
+        assert_eq!(hook.author.as_ref().map(|author| author.as_str()), Some("user"));
+
+        let revisions = db.get_revisions(Some(1), 10).expect("To get revisions");
+        assert_eq!(revisions.len(), 1);
+        assert_eq!(revisions[0].new_code.as_ref().map(|code| code.as_str()), Some("/HS-8@0:test.exe"));
+        assert_eq!(revisions[0].author, "user");
+
+        //Reviewed submission cannot be approved again.
+        assert!(db.approve_pending_hook(id, None, &reviewer).expect("To approve again").is_none());
+        assert!(db.get_pending_hooks().expect("To get pending").is_empty());
+    }
+
+    #[test]
+    fn should_store_vn_of_submission_only_on_approval() {
+        let db = Db::open(":memory:").expect("To open DB");
+        let vn = Vn { id: 5, title: "Remember11".to_owned() };
+        let author = Author::new("user", Frontend::Web);
+        let reviewer = Author::new("moderator", Frontend::Web);
+
+        let id = db.put_pending_hook(&vn, "v1.0", "/HS-4@0:test.exe", &author, None).expect("To submit hook").id;
+        assert_eq!(db.count_vns().expect("To count VNs"), 0);
+        assert!(db.get_vn(5).expect("To get VN").is_none());
+
+        db.approve_pending_hook(id, None, &reviewer).expect("To approve").expect("To find submission");
+        assert_eq!(db.get_vn(5).expect("To get VN").map(|vn| vn.title), Some("Remember11".to_owned()));
+    }
+
+    #[test]
+    fn should_reject_pending_hook() {
+        let db = Db::open(":memory:").expect("To open DB");
+        let id = submit(&db, None);
+        let reviewer = Author::new("moderator", Frontend::Irc);
+
+        let pending = db.reject_pending_hook(id, Some("wrong game"), &reviewer).expect("To reject").expect("To find submission");
+        assert_eq!(pending.review(), Review::Rejected);
+        assert_eq!(pending.reason.as_ref().map(|reason| reason.as_str()), Some("wrong game"));
+
+        let pending = db.get_pending_hook(id).expect("To get submission").expect("To find submission");
+        assert_eq!(pending.review(), Review::Rejected);
+        assert_eq!(pending.reason.as_ref().map(|reason| reason.as_str()), Some("wrong game"));
+        assert!(db.get_revisions(Some(1), 10).expect("To get revisions").is_empty());
+
+        assert!(db.reject_pending_hook(id, None, &reviewer).expect("To reject again").is_none());
+    }
+
+    #[test]
+    fn should_take_reviewed_submissions_once() {
+        let db = Db::open(":memory:").expect("To open DB");
+        let with_contact = submit(&db, Some("user"));
+        let without_contact = submit(&db, None);
+        let pending = submit(&db, Some("user"));
+        let reviewer = Author::new("moderator", Frontend::Irc);
+
+        db.reject_pending_hook(with_contact, None, &reviewer).expect("To reject").expect("To find submission");
+        db.reject_pending_hook(without_contact, None, &reviewer).expect("To reject").expect("To find submission");
+
+        assert!(db.take_reviewed_submissions(&[Frontend::Discord]).expect("To take reviewed").is_empty());
+
+        let reviewed = db.take_reviewed_submissions(&[Frontend::Irc]).expect("To take reviewed");
+        assert_eq!(reviewed.len(), 1);
+        assert_eq!(reviewed[0].id, with_contact);
+        assert_eq!(reviewed[0].contact.as_ref().map(|contact| contact.as_str()), Some("user"));
+
+        assert!(db.take_reviewed_submissions(&[Frontend::Irc]).expect("To take reviewed again").is_empty());
+        assert_eq!(db.get_pending_hook(pending).expect("To get submission").expect("To find submission").review(), Review::Pending);
+    }
+
+    #[test]
+    fn should_group_hooked_vns_data() {
+        let db = Db::open(":memory:").expect("To open DB");
+        let author = Author::new("user", Frontend::Irc);
+
+        let ever17 = db.put_vn(1, "Ever17".to_owned()).expect("To put VN");
+        let never7 = db.put_vn(2, "Never7".to_owned()).expect("To put VN");
+        let remember11 = db.put_vn(3, "Remember11".to_owned()).expect("To put VN");
+
+        db.put_hook(&never7, "v1.0".to_owned(), "/HS-4@0:never7.exe".to_owned(), &author).expect("To put hook");
+        db.put_hook(&ever17, "v1.0".to_owned(), "/HS-4@0:ever17.exe".to_owned(), &author).expect("To put hook");
+        db.put_hook(&ever17, "v2.0".to_owned(), "/HS-8@0:ever17.exe".to_owned(), &author).expect("To put hook");
+        db.put_executable(&ever17, "ever17.exe", None, None, &author).expect("To put executable");
+        db.put_executable(&remember11, "remember11.exe", None, None, &author).expect("To put executable");
+
+        let data = db.get_hooked_vns_data().expect("To get data");
+        assert_eq!(data.len(), 2);
+
+        let (ref vn, ref hooks, ref releases, ref executables) = data[0];
+        assert_eq!(vn.id, 1);
+        assert_eq!(hooks.iter().map(|hook| hook.version.as_str()).collect::<Vec<_>>(), ["v1.0", "v2.0"]);
+        assert!(releases.is_empty());
+        assert_eq!(executables.iter().map(|exe| exe.name.as_str()).collect::<Vec<_>>(), ["ever17.exe"]);
+
+        let (ref vn, ref hooks, _, ref executables) = data[1];
+        assert_eq!(vn.id, 2);
+        assert_eq!(hooks.len(), 1);
+        assert!(executables.is_empty());
+    }
+
+    #[test]
+    fn should_find_only_exact_title() {
+        let db = Db::open(":memory:").expect("To open DB");
+
+        db.put_vn(1, "Ever17 -the out of infinity-".to_owned()).expect("To put VN");
+        db.put_vn(2, "Ever17 -the out of infinity- Premium Edition".to_owned()).expect("To put VN");
+
+        let vns = db.find_vn("Ever17 -the out of infinity-").expect("To find VN");
+        assert_eq!(vns.iter().map(|vn| vn.id).collect::<Vec<_>>(), [1]);
+
+        let vns = db.find_vn("Ever17").expect("To find VN");
+        assert_eq!(vns.len(), 2);
+    }
+}
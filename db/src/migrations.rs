@@ -0,0 +1,152 @@
+//!Versioned schema migrations.
+//!
+//!Each migration is applied once, in order, inside its own transaction
+//!and recorded in `schema_version` table.
+
+use diesel::{
+    self,
+    Connection,
+    RunQueryDsl,
+    QueryDsl,
+    ExpressionMethods,
+};
+use diesel::connection::SimpleConnection;
+use diesel::sqlite::SqliteConnection;
+use diesel::result::{self, QueryResult};
+
+use ::schema::schema_version;
+
+pub struct Migration {
+    pub version: i32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+///Ordered list of all migrations.
+///
+///New migrations must be appended with the next version.
+///Never modify already released ones.
+pub const MIGRATIONS: &'static [Migration] = &[
+    Migration { version: 1, name: "init", sql: include_str!("../migrations/0001_init.sql") },
+    Migration { version: 2, name: "hook_revisions", sql: include_str!("../migrations/0002_hook_revisions.sql") },
+    Migration { version: 3, name: "hook_author", sql: include_str!("../migrations/0003_hook_author.sql") },
+    Migration { version: 4, name: "ignores", sql: include_str!("../migrations/0004_ignores.sql") },
+    Migration { version: 5, name: "users", sql: include_str!("../migrations/0005_users.sql") },
+    Migration { version: 6, name: "api_tokens", sql: include_str!("../migrations/0006_api_tokens.sql") },
+    Migration { version: 7, name: "pending_hooks", sql: include_str!("../migrations/0007_pending_hooks.sql") },
+    Migration { version: 8, name: "vn_search", sql: include_str!("../migrations/0008_vn_search.sql") },
+    Migration { version: 9, name: "aliases", sql: include_str!("../migrations/0009_aliases.sql") },
+    Migration { version: 10, name: "vn_sync", sql: include_str!("../migrations/0010_vn_sync.sql") },
+    Migration { version: 11, name: "releases", sql: include_str!("../migrations/0011_releases.sql") },
+    Migration { version: 12, name: "executables", sql: include_str!("../migrations/0012_executables.sql") },
+];
+
+const CREATE_SCHEMA_VERSION: &'static str = "CREATE TABLE IF NOT EXISTS schema_version (
+    version INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    applied_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (version)
+)";
+
+#[inline]
+///Returns latest version known to this build.
+pub fn latest() -> i32 {
+    MIGRATIONS.last().map(|migration| migration.version).unwrap_or(0)
+}
+
+///Returns version of schema in DB or 0 if no migration has been applied.
+pub fn current(conn: &SqliteConnection) -> QueryResult<i32> {
+    use self::schema_version::dsl;
+
+    schema_version::table.select(diesel::dsl::max(dsl::version))
+                         .get_result::<Option<i32>>(conn)
+                         .map(|version| version.unwrap_or(0))
+}
+
+///Applies migration unless it is already applied.
+///
+///Returns whether migration is applied by this call.
+fn apply(conn: &SqliteConnection, migration: &Migration) -> QueryResult<bool> {
+    use self::schema_version::dsl;
+
+    conn.transaction::<_, result::Error, _>(|| {
+        //Bot and web share the same DB so another process might have migrated it already.
+        if current(conn)? >= migration.version {
+            return Ok(false);
+        }
+
+        info!("DB: applying migration {:04}_{}", migration.version, migration.name);
+        conn.batch_execute(migration.sql)?;
+        diesel::insert_into(schema_version::table).values((dsl::version.eq(migration.version), dsl::name.eq(migration.name)))
+                                                  .execute(conn)?;
+
+        Ok(true)
+    })
+}
+
+///Applies all pending migrations.
+///
+///Returns resulting schema version.
+pub fn run(conn: &SqliteConnection) -> QueryResult<i32> {
+    conn.batch_execute(CREATE_SCHEMA_VERSION)?;
+
+    let current = current(conn)?;
+    let latest = latest();
+
+    if current > latest {
+        warn!("DB: schema version {} is newer than supported {}. Update Roseline!", current, latest);
+        return Ok(current);
+    }
+
+    let mut applied = 0;
+    for migration in MIGRATIONS.iter().filter(|migration| migration.version > current) {
+        if apply(conn, migration)? {
+            applied += 1;
+        }
+    }
+
+    match applied {
+        0 => info!("DB: schema version {} is up to date", latest),
+        applied => info!("DB: schema upgraded to version {} by {} migration(s)", latest, applied),
+    }
+
+    Ok(latest)
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::Connection;
+    use diesel::sqlite::SqliteConnection;
+
+    use super::{run, apply, current, latest, MIGRATIONS};
+
+    #[test]
+    fn should_have_ordered_migrations() {
+        for (idx, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version as usize, idx + 1);
+        }
+    }
+
+    #[test]
+    fn should_apply_migrations_once() {
+        let conn = SqliteConnection::establish(":memory:").expect("To open DB");
+
+        assert_eq!(run(&conn).expect("To migrate"), latest());
+        assert_eq!(current(&conn).expect("To get version"), latest());
+
+        //Second run must be no-op
+        assert_eq!(run(&conn).expect("To migrate again"), latest());
+        assert_eq!(current(&conn).expect("To get version"), latest());
+    }
+
+    #[test]
+    fn should_skip_migration_applied_by_other_process() {
+        let conn = SqliteConnection::establish(":memory:").expect("To open DB");
+        run(&conn).expect("To migrate");
+
+        for migration in MIGRATIONS.iter() {
+            assert!(!apply(&conn, migration).expect("To skip migration"));
+        }
+        assert_eq!(current(&conn).expect("To get version"), latest());
+    }
+}
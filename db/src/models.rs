@@ -1,6 +1,10 @@
-use ::schema::{vns, hooks};
+use ::schema::{vns, hooks, hook_revisions, ignores, users, sessions, api_tokens, pending_hooks, aliases, vn_sync, releases, executables};
 
-#[derive(Identifiable, Insertable, Queryable, Debug)]
+use ::chrono::NaiveDateTime;
+
+use std::fmt;
+
+#[derive(Identifiable, Insertable, Queryable, QueryableByName, Clone, Debug)]
 #[table_name = "vns"]
 pub struct Vn {
     pub id: i64,
@@ -14,7 +18,30 @@ pub struct Hook {
     pub id: i64,
     pub vn_id: i64,
     pub version: String,
-    pub code: String
+    pub code: String,
+    pub author: Option<String>,
+    pub frontend: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    ///VNDB release, if hook is set for particular release.
+    pub release_id: Option<i64>,
+}
+
+impl Hook {
+    ///Returns who submitted current code, if known.
+    pub fn submitter(&self) -> Option<String> {
+        match (self.author.as_ref(), self.frontend.as_ref()) {
+            (Some(author), Some(frontend)) => Some(format!("{} via {}", author, frontend)),
+            (Some(author), None) => Some(author.clone()),
+            (None, _) => None,
+        }
+    }
+
+    #[inline]
+    ///Returns time of last modification, if known.
+    pub fn last_modified(&self) -> Option<NaiveDateTime> {
+        self.updated_at.or(self.created_at)
+    }
 }
 
 #[derive(Associations, Insertable, Queryable, Debug)]
@@ -23,5 +50,716 @@ pub struct Hook {
 pub struct HookView {
     pub vn_id: i64,
     pub version: String,
-    pub code: String
+    pub code: String,
+    pub author: Option<String>,
+    pub frontend: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    ///VNDB release, if hook is set for particular release.
+    pub release_id: Option<i64>,
+}
+
+///Frontend through which modification is performed.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Frontend {
+    Irc,
+    Discord,
+    Web,
+    Console,
+}
+
+impl Frontend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            &Frontend::Irc => "IRC",
+            &Frontend::Discord => "Discord",
+            &Frontend::Web => "Web",
+            &Frontend::Console => "Console",
+        }
+    }
+
+    pub fn from_str(text: &str) -> Option<Self> {
+        match text {
+            "IRC" => Some(Frontend::Irc),
+            "Discord" => Some(Frontend::Discord),
+            "Web" => Some(Frontend::Web),
+            "Console" => Some(Frontend::Console),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for Frontend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+///Author of modification.
+#[derive(Clone, Debug)]
+pub struct Author {
+    pub name: String,
+    pub frontend: Frontend,
+}
+
+impl Author {
+    pub fn new<T: Into<String>>(name: T, frontend: Frontend) -> Self {
+        Self {
+            name: name.into(),
+            frontend
+        }
+    }
+}
+
+impl fmt::Display for Author {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.frontend)
+    }
+}
+
+///Record of single change to hook.
+///
+///`old_code` is missing when hook is added.
+///`new_code` is missing when hook is removed.
+#[derive(Identifiable, Queryable, Debug)]
+#[table_name = "hook_revisions"]
+pub struct HookRevision {
+    pub id: i64,
+    pub vn_id: i64,
+    pub vn_title: String,
+    pub version: String,
+    pub old_code: Option<String>,
+    pub new_code: Option<String>,
+    pub author: String,
+    pub frontend: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl fmt::Display for HookRevision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{} {} by {} ({}) - {}: ", self.id, self.created_at.format("%Y-%m-%d %H:%M"), self.author, self.frontend, self.version)?;
+
+        match (self.old_code.as_ref(), self.new_code.as_ref()) {
+            (None, Some(new)) => write!(f, "added '{}'", new),
+            (Some(old), Some(new)) => write!(f, "'{}' -> '{}'", old, new),
+            (Some(old), None) => write!(f, "removed '{}'", old),
+            (None, None) => write!(f, "no change"),
+        }
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "hook_revisions"]
+pub struct NewHookRevision<'a> {
+    pub vn_id: i64,
+    pub vn_title: &'a str,
+    pub version: &'a str,
+    pub old_code: Option<&'a str>,
+    pub new_code: Option<&'a str>,
+    pub author: &'a str,
+    pub frontend: &'a str,
+    pub created_at: NaiveDateTime,
+}
+
+impl<'a> NewHookRevision<'a> {
+    pub fn new(vn: &'a Vn, version: &'a str, old_code: Option<&'a str>, new_code: Option<&'a str>, author: &'a Author) -> Self {
+        Self {
+            vn_id: vn.id,
+            vn_title: &vn.title,
+            version,
+            old_code,
+            new_code,
+            author: &author.name,
+            frontend: author.frontend.as_str(),
+            created_at: ::chrono::Utc::now().naive_utc(),
+        }
+    }
+}
+
+///Ignored user's mask.
+#[derive(Identifiable, Queryable, Clone, Debug)]
+#[table_name = "ignores"]
+pub struct Ignore {
+    pub id: i64,
+    pub pattern: String,
+    pub reason: Option<String>,
+    pub author: String,
+    pub frontend: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "ignores"]
+pub struct NewIgnore<'a> {
+    pub pattern: &'a str,
+    pub reason: Option<&'a str>,
+    pub author: &'a str,
+    pub frontend: &'a str,
+    pub created_at: NaiveDateTime,
+}
+
+impl<'a> NewIgnore<'a> {
+    pub fn new(pattern: &'a str, reason: Option<&'a str>, author: &'a Author) -> Self {
+        Self {
+            pattern,
+            reason,
+            author: &author.name,
+            frontend: author.frontend.as_str(),
+            created_at: ::chrono::Utc::now().naive_utc(),
+        }
+    }
+}
+
+///User's role.
+///
+///Roles are ordered so that every role includes permissions of lower ones.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Role {
+    Everyone,
+    Trusted,
+    Moderator,
+    Owner,
+}
+
+impl Role {
+    ///Roles that can be granted, from highest to lowest.
+    pub const GRANTED: [Role; 3] = [Role::Owner, Role::Moderator, Role::Trusted];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            &Role::Everyone => "everyone",
+            &Role::Trusted => "trusted",
+            &Role::Moderator => "moderator",
+            &Role::Owner => "owner",
+        }
+    }
+
+    pub fn from_str(text: &str) -> Option<Self> {
+        match text {
+            "everyone" => Some(Role::Everyone),
+            "trusted" => Some(Role::Trusted),
+            "moderator" => Some(Role::Moderator),
+            "owner" => Some(Role::Owner),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+///Web user.
+///
+///`password` contains encoded hash of password.
+#[derive(Identifiable, Queryable, Clone, Debug)]
+#[table_name = "users"]
+pub struct User {
+    pub id: i64,
+    pub name: String,
+    pub password: String,
+    pub role: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl User {
+    #[inline]
+    ///Returns user's role, unknown roles grant nothing.
+    pub fn role(&self) -> Role {
+        Role::from_str(&self.role).unwrap_or(Role::Everyone)
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "users"]
+pub struct NewUser<'a> {
+    pub name: &'a str,
+    pub password: &'a str,
+    pub role: &'static str,
+    pub created_at: NaiveDateTime,
+}
+
+impl<'a> NewUser<'a> {
+    pub fn new(name: &'a str, password: &'a str, role: Role) -> Self {
+        Self {
+            name,
+            password,
+            role: role.as_str(),
+            created_at: ::chrono::Utc::now().naive_utc(),
+        }
+    }
+}
+
+///Web session of logged in user.
+///
+///`id` is the token stored in user's cookie.
+///`csrf` is the token that must accompany each modification.
+#[derive(Identifiable, Queryable, Insertable, Associations, Clone, Debug)]
+#[belongs_to(User)]
+#[table_name = "sessions"]
+pub struct Session {
+    pub id: String,
+    pub user_id: i64,
+    pub csrf: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: NaiveDateTime,
+}
+
+impl Session {
+    #[inline]
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= ::chrono::Utc::now().naive_utc()
+    }
+}
+
+///Permissions of API token in addition to user's role.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Scope {
+    ///Only retrieval of data.
+    Read,
+    ///Modification of data, limited by user's role.
+    Write,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            &Scope::Read => "read",
+            &Scope::Write => "write",
+        }
+    }
+
+    pub fn from_str(text: &str) -> Option<Self> {
+        match text {
+            "read" => Some(Scope::Read),
+            "write" => Some(Scope::Write),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+///API token of user.
+///
+///`token` contains hash of token, the token itself is never stored.
+#[derive(Identifiable, Queryable, Associations, Clone, Debug)]
+#[belongs_to(User)]
+#[table_name = "api_tokens"]
+pub struct ApiToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    pub token: String,
+    pub scope: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl ApiToken {
+    #[inline]
+    ///Returns token's scope, unknown scopes are considered read-only.
+    pub fn scope(&self) -> Scope {
+        Scope::from_str(&self.scope).unwrap_or(Scope::Read)
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "api_tokens"]
+pub struct NewApiToken<'a> {
+    pub user_id: i64,
+    pub name: &'a str,
+    pub token: &'a str,
+    pub scope: &'static str,
+    pub created_at: NaiveDateTime,
+}
+
+impl<'a> NewApiToken<'a> {
+    pub fn new(user: &User, name: &'a str, token: &'a str, scope: Scope) -> Self {
+        Self {
+            user_id: user.id,
+            name,
+            token,
+            scope: scope.as_str(),
+            created_at: ::chrono::Utc::now().naive_utc(),
+        }
+    }
+}
+
+///State of submitted hook.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Review {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl Review {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            &Review::Pending => "pending",
+            &Review::Approved => "approved",
+            &Review::Rejected => "rejected",
+        }
+    }
+
+    pub fn from_str(text: &str) -> Option<Self> {
+        match text {
+            "pending" => Some(Review::Pending),
+            "approved" => Some(Review::Approved),
+            "rejected" => Some(Review::Rejected),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for Review {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+///Hook submitted by untrusted user, awaiting moderator's review.
+///
+///`contact` is frontend specific address to notify submitter about outcome.
+///Web users have none and see outcome on their submissions page instead.
+#[derive(Identifiable, Queryable, Clone, Debug)]
+#[table_name = "pending_hooks"]
+pub struct PendingHook {
+    pub id: i64,
+    pub vn_id: i64,
+    pub vn_title: String,
+    pub version: String,
+    pub code: String,
+    pub author: String,
+    pub frontend: String,
+    pub contact: Option<String>,
+    pub status: String,
+    pub reviewer: Option<String>,
+    pub reason: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub reviewed_at: Option<NaiveDateTime>,
+    ///Whether submitter is notified about outcome.
+    pub notified: bool,
+}
+
+impl PendingHook {
+    #[inline]
+    pub fn review(&self) -> Review {
+        Review::from_str(&self.status).unwrap_or(Review::Pending)
+    }
+
+    #[inline]
+    ///Returns submitter, if frontend is known.
+    pub fn submitter(&self) -> Option<Author> {
+        Frontend::from_str(&self.frontend).map(|frontend| Author::new(self.author.clone(), frontend))
+    }
+}
+
+impl fmt::Display for PendingHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{} v{} {} - {}: '{}' by {} ({})", self.id, self.vn_id, self.vn_title, self.version, self.code, self.author, self.frontend)
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "pending_hooks"]
+pub struct NewPendingHook<'a> {
+    pub vn_id: i64,
+    pub vn_title: &'a str,
+    pub version: &'a str,
+    pub code: &'a str,
+    pub author: &'a str,
+    pub frontend: &'a str,
+    pub contact: Option<&'a str>,
+    pub status: &'a str,
+    pub created_at: NaiveDateTime,
+}
+
+impl<'a> NewPendingHook<'a> {
+    pub fn new(vn: &'a Vn, version: &'a str, code: &'a str, author: &'a Author, contact: Option<&'a str>) -> Self {
+        Self {
+            vn_id: vn.id,
+            vn_title: &vn.title,
+            version,
+            code,
+            author: &author.name,
+            frontend: author.frontend.as_str(),
+            contact,
+            status: Review::Pending.as_str(),
+            created_at: ::chrono::Utc::now().naive_utc(),
+        }
+    }
+}
+
+///Origin of VN's alias.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AliasKind {
+    ///Original title from VNDB.
+    Original,
+    ///Alias from VNDB.
+    Vndb,
+    ///Added by user.
+    User,
+}
+
+impl AliasKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            &AliasKind::Original => "original",
+            &AliasKind::Vndb => "vndb",
+            &AliasKind::User => "user",
+        }
+    }
+
+    pub fn from_str(text: &str) -> Option<Self> {
+        match text {
+            "original" => Some(AliasKind::Original),
+            "vndb" => Some(AliasKind::Vndb),
+            "user" => Some(AliasKind::User),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for AliasKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+///Alternative title of VN.
+///
+///Only user-added aliases have author.
+#[derive(Identifiable, Queryable, Associations, Clone, Debug)]
+#[belongs_to(Vn)]
+#[table_name = "aliases"]
+pub struct Alias {
+    pub id: i64,
+    pub vn_id: i64,
+    pub alias: String,
+    pub normalized: String,
+    pub kind: String,
+    pub author: Option<String>,
+    pub frontend: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl Alias {
+    #[inline]
+    pub fn kind(&self) -> AliasKind {
+        AliasKind::from_str(&self.kind).unwrap_or(AliasKind::User)
+    }
+}
+
+impl fmt::Display for Alias {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.author {
+            Some(ref author) => write!(f, "{} (by {})", self.alias, author),
+            None => write!(f, "{} ({})", self.alias, self.kind),
+        }
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[table_name = "aliases"]
+pub struct NewAlias<'a> {
+    pub vn_id: i64,
+    pub alias: &'a str,
+    pub normalized: String,
+    pub kind: &'a str,
+    pub author: Option<&'a str>,
+    pub frontend: Option<&'a str>,
+    pub created_at: NaiveDateTime,
+}
+
+impl<'a> NewAlias<'a> {
+    ///Creates alias from VNDB.
+    pub fn vndb(vn: &Vn, alias: &'a str, kind: AliasKind) -> Self {
+        Self {
+            vn_id: vn.id,
+            alias,
+            normalized: ::search::normalize(alias),
+            kind: kind.as_str(),
+            author: None,
+            frontend: None,
+            created_at: ::chrono::Utc::now().naive_utc(),
+        }
+    }
+
+    ///Creates alias added by user.
+    pub fn user(vn: &Vn, alias: &'a str, author: &'a Author) -> Self {
+        Self {
+            vn_id: vn.id,
+            alias,
+            normalized: ::search::normalize(alias),
+            kind: AliasKind::User.as_str(),
+            author: Some(&author.name),
+            frontend: Some(author.frontend.as_str()),
+            created_at: ::chrono::Utc::now().naive_utc(),
+        }
+    }
+}
+
+///State of VN's synchronization with VNDB.
+#[derive(Identifiable, Insertable, Queryable, AsChangeset, Clone, Debug)]
+#[primary_key(vn_id)]
+#[table_name = "vn_sync"]
+pub struct VnSync {
+    pub vn_id: i64,
+    pub synced_at: NaiveDateTime,
+    ///VNDB no longer knows VN.
+    pub missing: bool,
+}
+
+impl VnSync {
+    pub fn new(vn: &Vn, missing: bool) -> Self {
+        Self {
+            vn_id: vn.id,
+            synced_at: ::chrono::Utc::now().naive_utc(),
+            missing
+        }
+    }
+}
+
+///Changes made to VN by synchronization with VNDB.
+#[derive(Default, Debug)]
+pub struct VnChanges {
+    ///Previous title, if VN is renamed.
+    pub renamed: Option<String>,
+    ///Whether original title or aliases from VNDB are changed.
+    pub aliases: bool,
+}
+
+///Extracts ID of VNDB release from hook's version.
+///
+///Hooks for releases use release reference, like `r123`, as their version.
+pub fn release_ref(version: &str) -> Option<i64> {
+    match version.starts_with('r') {
+        true => version[1..].parse::<i64>().ok().filter(|id| *id > 0),
+        false => None
+    }
+}
+
+///VNDB release of VN.
+#[derive(Identifiable, Insertable, Queryable, Associations, Clone, Debug)]
+#[belongs_to(Vn)]
+#[table_name = "releases"]
+pub struct Release {
+    pub id: i64,
+    pub vn_id: i64,
+    pub title: String,
+    ///Comma separated platforms.
+    pub platforms: String,
+    ///Comma separated languages.
+    pub languages: String,
+}
+
+impl Release {
+    ///Returns version of hooks for this release.
+    #[inline]
+    pub fn version(&self) -> String {
+        format!("r{}", self.id)
+    }
+}
+
+impl fmt::Display for Release {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "r{} {}", self.id, self.title)?;
+
+        match (self.platforms.len(), self.languages.len()) {
+            (0, 0) => Ok(()),
+            (_, 0) => write!(f, " ({})", self.platforms),
+            (0, _) => write!(f, " ({})", self.languages),
+            (_, _) => write!(f, " ({}; {})", self.platforms, self.languages),
+        }
+    }
+}
+
+///Normalizes name of executable.
+///
+///Path is stripped and name is lowercased, as Windows file names are case insensitive.
+pub fn executable_name(name: &str) -> Result<String, String> {
+    let name = name.trim().rsplit(|ch| ch == '/' || ch == '\\').next().unwrap_or("").trim();
+
+    match name.len() {
+        0 => Err("Executable name cannot be empty".to_owned()),
+        _ => Ok(name.to_lowercase()),
+    }
+}
+
+///Normalizes hash of executable.
+///
+///Accepted are hex digests of MD5, SHA-1 and SHA-256.
+pub fn file_hash(hash: &str) -> Result<String, String> {
+    let hash = hash.trim();
+
+    match hash.len() {
+        32 | 40 | 64 if hash.chars().all(|ch| ch.is_digit(16)) => Ok(hash.to_lowercase()),
+        _ => Err(format!("Invalid hash '{}'. Expected hex digest of MD5, SHA-1 or SHA-256", hash)),
+    }
+}
+
+///Executable of game, which is used by text hookers to identify it.
+#[derive(Identifiable, Queryable, Associations, Clone, Debug)]
+#[belongs_to(Vn)]
+#[table_name = "executables"]
+pub struct Executable {
+    pub id: i64,
+    ///Lowercase file name.
+    pub name: String,
+    ///Lowercase hex digest of file.
+    pub hash: Option<String>,
+    pub vn_id: i64,
+    pub release_id: Option<i64>,
+    pub author: Option<String>,
+    pub frontend: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl fmt::Display for Executable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.name)?;
+
+        if let Some(ref hash) = self.hash {
+            write!(f, " [{}]", hash)?;
+        }
+
+        match self.release_id {
+            Some(release) => write!(f, " -> v{} (r{})", self.vn_id, release),
+            None => write!(f, " -> v{}", self.vn_id),
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "executables"]
+pub struct NewExecutable<'a> {
+    pub name: &'a str,
+    pub hash: Option<&'a str>,
+    pub vn_id: i64,
+    pub release_id: Option<i64>,
+    pub author: Option<&'a str>,
+    pub frontend: Option<&'a str>,
+    pub created_at: NaiveDateTime,
+}
+
+impl<'a> NewExecutable<'a> {
+    ///Creates executable from normalized name and hash.
+    pub fn new(name: &'a str, hash: Option<&'a str>, vn: &Vn, release_id: Option<i64>, author: &'a Author) -> Self {
+        Self {
+            name,
+            hash,
+            vn_id: vn.id,
+            release_id,
+            author: Some(&author.name),
+            frontend: Some(author.frontend.as_str()),
+            created_at: ::chrono::Utc::now().naive_utc(),
+        }
+    }
 }
@@ -1,19 +1,10 @@
 extern crate diesel;
 
-pub mod create {
-    pub const VNS: &'static str = "CREATE TABLE IF NOT EXISTS vns (
-        id INTEGER,
-        title TEXT NOT NULL,
-        PRIMARY KEY (id)
-)";
-    pub const HOOKS: &'static str = "CREATE TABLE IF NOT EXISTS hooks (
-        id INTEGER,
-        vn_id INTEGER NOT NULL,
-        version TEXT NOT NULL,
-        code TEXT NOT NULL,
-        PRIMARY KEY (id),
-        FOREIGN KEY (vn_id) REFERENCES vns (id) ON DELETE CASCADE ON UPDATE NO ACTION
-)";
+table! {
+    schema_version(version) {
+        version -> Integer,
+        name -> Text,
+    }
 }
 
 table! {
@@ -29,5 +20,139 @@ table! {
         vn_id -> BigInt,
         version -> Text,
         code -> Text,
+        author -> Nullable<Text>,
+        frontend -> Nullable<Text>,
+        created_at -> Nullable<Timestamp>,
+        updated_at -> Nullable<Timestamp>,
+        release_id -> Nullable<BigInt>,
+    }
+}
+
+table! {
+    hook_revisions(id) {
+        id -> BigInt,
+        vn_id -> BigInt,
+        vn_title -> Text,
+        version -> Text,
+        old_code -> Nullable<Text>,
+        new_code -> Nullable<Text>,
+        author -> Text,
+        frontend -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    ignores(id) {
+        id -> BigInt,
+        pattern -> Text,
+        reason -> Nullable<Text>,
+        author -> Text,
+        frontend -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    users(id) {
+        id -> BigInt,
+        name -> Text,
+        password -> Text,
+        role -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    sessions(id) {
+        id -> Text,
+        user_id -> BigInt,
+        csrf -> Text,
+        created_at -> Timestamp,
+        expires_at -> Timestamp,
+    }
+}
+
+table! {
+    api_tokens(id) {
+        id -> BigInt,
+        user_id -> BigInt,
+        name -> Text,
+        token -> Text,
+        scope -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    pending_hooks(id) {
+        id -> BigInt,
+        vn_id -> BigInt,
+        vn_title -> Text,
+        version -> Text,
+        code -> Text,
+        author -> Text,
+        frontend -> Text,
+        contact -> Nullable<Text>,
+        status -> Text,
+        reviewer -> Nullable<Text>,
+        reason -> Nullable<Text>,
+        created_at -> Timestamp,
+        reviewed_at -> Nullable<Timestamp>,
+        notified -> Bool,
+    }
+}
+
+table! {
+    aliases(id) {
+        id -> BigInt,
+        vn_id -> BigInt,
+        alias -> Text,
+        normalized -> Text,
+        kind -> Text,
+        author -> Nullable<Text>,
+        frontend -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    vn_sync(vn_id) {
+        vn_id -> BigInt,
+        synced_at -> Timestamp,
+        missing -> Bool,
     }
 }
+
+table! {
+    releases(id) {
+        id -> BigInt,
+        vn_id -> BigInt,
+        title -> Text,
+        platforms -> Text,
+        languages -> Text,
+    }
+}
+
+table! {
+    executables(id) {
+        id -> BigInt,
+        name -> Text,
+        hash -> Nullable<Text>,
+        vn_id -> BigInt,
+        release_id -> Nullable<BigInt>,
+        author -> Nullable<Text>,
+        frontend -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+joinable!(sessions -> users (user_id));
+joinable!(api_tokens -> users (user_id));
+allow_tables_to_appear_in_same_query!(users, sessions, api_tokens);
+
+joinable!(hooks -> vns (vn_id));
+joinable!(aliases -> vns (vn_id));
+joinable!(vn_sync -> vns (vn_id));
+joinable!(executables -> vns (vn_id));
+allow_tables_to_appear_in_same_query!(vns, hooks, aliases, vn_sync, executables);
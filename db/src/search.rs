@@ -0,0 +1,256 @@
+//!Normalization of titles for full-text search.
+//!
+//!Titles and queries are normalized the same way before they reach FTS index:
+//!
+//!- Fullwidth and halfwidth forms are folded into regular ones.
+//!- Text is lowercased and punctuation is replaced with spaces.
+//!- Kana is transliterated into romaji, so kana and romaji spelling match each other.
+//!- Each CJK ideograph becomes separate word, as they are not delimited by spaces.
+
+///Halfwidth katakana starting from `U+FF66` in fullwidth form.
+const HALFWIDTH_KATAKANA: &'static str = "ヲァィゥェォャュョッーアイウエオカキクケコサシスセソタチツテトナニヌネノハヒフヘホマミムメモヤユヨラリルレロワン";
+
+///Romaji of hiragana.
+///
+///Combinations go first, so that the longest match is taken.
+const ROMAJI: &'static [(&'static str, &'static str)] = &[
+    ("きゃ", "kya"), ("きゅ", "kyu"), ("きょ", "kyo"),
+    ("ぎゃ", "gya"), ("ぎゅ", "gyu"), ("ぎょ", "gyo"),
+    ("しゃ", "sha"), ("しゅ", "shu"), ("しょ", "sho"), ("しぇ", "she"),
+    ("じゃ", "ja"), ("じゅ", "ju"), ("じょ", "jo"), ("じぇ", "je"),
+    ("ちゃ", "cha"), ("ちゅ", "chu"), ("ちょ", "cho"), ("ちぇ", "che"),
+    ("ぢゃ", "ja"), ("ぢゅ", "ju"), ("ぢょ", "jo"),
+    ("にゃ", "nya"), ("にゅ", "nyu"), ("にょ", "nyo"),
+    ("ひゃ", "hya"), ("ひゅ", "hyu"), ("ひょ", "hyo"),
+    ("びゃ", "bya"), ("びゅ", "byu"), ("びょ", "byo"),
+    ("ぴゃ", "pya"), ("ぴゅ", "pyu"), ("ぴょ", "pyo"),
+    ("みゃ", "mya"), ("みゅ", "myu"), ("みょ", "myo"),
+    ("りゃ", "rya"), ("りゅ", "ryu"), ("りょ", "ryo"),
+    ("ふぁ", "fa"), ("ふぃ", "fi"), ("ふぇ", "fe"), ("ふぉ", "fo"),
+    ("うぃ", "wi"), ("うぇ", "we"), ("うぉ", "wo"),
+    ("ゔぁ", "va"), ("ゔぃ", "vi"), ("ゔぇ", "ve"), ("ゔぉ", "vo"),
+    ("てぃ", "ti"), ("でぃ", "di"), ("とぅ", "tu"), ("どぅ", "du"),
+    ("あ", "a"), ("い", "i"), ("う", "u"), ("え", "e"), ("お", "o"),
+    ("か", "ka"), ("き", "ki"), ("く", "ku"), ("け", "ke"), ("こ", "ko"),
+    ("が", "ga"), ("ぎ", "gi"), ("ぐ", "gu"), ("げ", "ge"), ("ご", "go"),
+    ("さ", "sa"), ("し", "shi"), ("す", "su"), ("せ", "se"), ("そ", "so"),
+    ("ざ", "za"), ("じ", "ji"), ("ず", "zu"), ("ぜ", "ze"), ("ぞ", "zo"),
+    ("た", "ta"), ("ち", "chi"), ("つ", "tsu"), ("て", "te"), ("と", "to"),
+    ("だ", "da"), ("ぢ", "ji"), ("づ", "zu"), ("で", "de"), ("ど", "do"),
+    ("な", "na"), ("に", "ni"), ("ぬ", "nu"), ("ね", "ne"), ("の", "no"),
+    ("は", "ha"), ("ひ", "hi"), ("ふ", "fu"), ("へ", "he"), ("ほ", "ho"),
+    ("ば", "ba"), ("び", "bi"), ("ぶ", "bu"), ("べ", "be"), ("ぼ", "bo"),
+    ("ぱ", "pa"), ("ぴ", "pi"), ("ぷ", "pu"), ("ぺ", "pe"), ("ぽ", "po"),
+    ("ま", "ma"), ("み", "mi"), ("む", "mu"), ("め", "me"), ("も", "mo"),
+    ("や", "ya"), ("ゆ", "yu"), ("よ", "yo"),
+    ("ら", "ra"), ("り", "ri"), ("る", "ru"), ("れ", "re"), ("ろ", "ro"),
+    ("わ", "wa"), ("ゐ", "i"), ("ゑ", "e"), ("を", "o"), ("ん", "n"),
+    ("ぁ", "a"), ("ぃ", "i"), ("ぅ", "u"), ("ぇ", "e"), ("ぉ", "o"),
+    ("ゃ", "ya"), ("ゅ", "yu"), ("ょ", "yo"), ("ゎ", "wa"),
+    ("ゔ", "vu"), ("ゕ", "ka"), ("ゖ", "ke"),
+];
+
+#[inline]
+fn is_hiragana(ch: char) -> bool {
+    ch >= '\u{3041}' && ch <= '\u{3096}'
+}
+
+#[inline]
+fn is_ideograph(ch: char) -> bool {
+    (ch >= '\u{3400}' && ch <= '\u{9fff}') || (ch >= '\u{f900}' && ch <= '\u{faff}') || ch == '々'
+}
+
+#[inline]
+fn shift(ch: char, offset: i32) -> char {
+    ::std::char::from_u32((ch as i32 + offset) as u32).unwrap_or(ch)
+}
+
+///Converts fullwidth, halfwidth and katakana forms.
+fn fold_char(ch: char) -> char {
+    if ch == '\u{3000}' {
+        ' '
+    }
+    else if ch >= '\u{ff01}' && ch <= '\u{ff5e}' {
+        shift(ch, -0xfee0)
+    }
+    else if ch >= '\u{ff66}' && ch <= '\u{ff9d}' {
+        HALFWIDTH_KATAKANA.chars().nth(ch as usize - 0xff66).map(fold_char).unwrap_or(ch)
+    }
+    else if ch >= '\u{30a1}' && ch <= '\u{30f6}' {
+        shift(ch, -0x60)
+    }
+    else {
+        ch
+    }
+}
+
+///Applies voiced (dakuten) or semi-voiced (handakuten) sound mark to hiragana.
+fn voice(ch: char, semi: bool) -> char {
+    let is_ha_row = ch >= 'は' && ch <= 'ほ' && (ch as u32 - 'は' as u32) % 3 == 0;
+
+    match semi {
+        true if is_ha_row => shift(ch, 2),
+        true => ch,
+        false if is_ha_row => shift(ch, 1),
+        false if ch == 'う' => 'ゔ',
+        false if ch >= 'か' && ch <= 'ち' && (ch as u32) % 2 == 1 => shift(ch, 1),
+        false if ch == 'つ' || ch == 'て' || ch == 'と' => shift(ch, 1),
+        false => ch,
+    }
+}
+
+///Folds width variants and katakana into regular characters and hiragana.
+fn fold(text: &str) -> Vec<char> {
+    let mut result: Vec<char> = Vec::with_capacity(text.len());
+
+    for ch in text.chars() {
+        //Sound marks modify previous kana, either as separate or combining character.
+        let semi = match ch {
+            '\u{ff9e}' | '\u{3099}' | '\u{309b}' => false,
+            '\u{ff9f}' | '\u{309a}' | '\u{309c}' => true,
+            ch => {
+                result.extend(fold_char(ch).to_lowercase());
+                continue;
+            }
+        };
+
+        if let Some(last) = result.last_mut() {
+            *last = voice(*last, semi);
+        }
+    }
+
+    result
+}
+
+///Transliterates run of hiragana into romaji.
+fn romanize(kana: &[char], result: &mut String) {
+    let kana = kana.iter().collect::<String>();
+    let mut kana = kana.as_str();
+    //Small tsu doubles following consonant.
+    let mut double = false;
+
+    while let Some(ch) = kana.chars().next() {
+        if ch == 'っ' {
+            double = true;
+            kana = &kana[ch.len_utf8()..];
+            continue;
+        }
+
+        match ROMAJI.iter().find(|&&(hiragana, _)| kana.starts_with(hiragana)) {
+            Some(&(hiragana, romaji)) => {
+                if double {
+                    match romaji.starts_with("ch") {
+                        true => result.push('t'),
+                        false => result.extend(romaji.chars().next().filter(|ch| !"aiueon".contains(*ch))),
+                    }
+                }
+
+                result.push_str(romaji);
+                kana = &kana[hiragana.len()..];
+            },
+            //Iteration marks and the rest are dropped.
+            None => kana = &kana[ch.len_utf8()..],
+        }
+
+        double = false;
+    }
+}
+
+///Normalizes text for search.
+///
+///Resulting text consists of words separated by single space.
+pub fn normalize(text: &str) -> String {
+    let text = fold(text);
+    let mut result = String::with_capacity(text.len());
+    let mut idx = 0;
+
+    while idx < text.len() {
+        let ch = text[idx];
+
+        if is_hiragana(ch) {
+            let end = text[idx..].iter().position(|ch| !is_hiragana(*ch) && *ch != 'ー' && *ch != 'ゝ' && *ch != 'ゞ')
+                                     .map(|len| idx + len)
+                                     .unwrap_or(text.len());
+            result.push(' ');
+            romanize(&text[idx..end], &mut result);
+            result.push(' ');
+            idx = end;
+            continue;
+        }
+
+        if is_ideograph(ch) {
+            result.push(' ');
+            result.push(ch);
+            result.push(' ');
+        }
+        else if ch.is_alphanumeric() {
+            result.push(ch);
+        }
+        else {
+            result.push(' ');
+        }
+
+        idx += 1;
+    }
+
+    result.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+///Creates FTS query that matches all words of text as prefixes.
+///
+///Returns `None` if there is nothing to search for.
+pub fn match_query(text: &str) -> Option<String> {
+    let text = normalize(text);
+
+    match text.len() {
+        0 => None,
+        _ => Some(text.split(' ').map(|word| format!("\"{}\"*", word)).collect::<Vec<_>>().join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize, match_query};
+
+    #[test]
+    fn should_normalize_case_and_punctuation() {
+        assert_eq!(normalize("Fate/stay night"), "fate stay night");
+        assert_eq!(normalize("  Fate Stay   Night "), "fate stay night");
+        assert_eq!(normalize("Ever17 -the out of infinity-"), "ever17 the out of infinity");
+        assert_eq!(normalize("!?"), "");
+    }
+
+    #[test]
+    fn should_normalize_width() {
+        assert_eq!(normalize("ＦＡＴＥ／ｓｔａｙ　ｎｉｇｈｔ"), "fate stay night");
+        assert_eq!(normalize("ｶﾉﾝ"), normalize("カノン"));
+        assert_eq!(normalize("ｶﾞｯｺｳ"), normalize("ガッコウ"));
+        assert_eq!(normalize("ﾊﾟﾊﾟ"), "papa");
+    }
+
+    #[test]
+    fn should_normalize_kana_to_romaji() {
+        assert_eq!(normalize("カノン"), "kanon");
+        assert_eq!(normalize("かのん"), "kanon");
+        assert_eq!(normalize("ひぐらしのなく頃に"), "higurashinonaku 頃 ni");
+        assert_eq!(normalize("ちょっと"), "chotto");
+        assert_eq!(normalize("まっちゃ"), "matcha");
+        assert_eq!(normalize("セーラー"), "sera");
+        assert_eq!(normalize("ヴァルキリー"), "varukiri");
+        assert_eq!(normalize("シュタインズ・ゲート"), "shutainzu geto");
+        assert_eq!(normalize("Fateのstay"), "fate no stay");
+    }
+
+    #[test]
+    fn should_split_ideographs() {
+        assert_eq!(normalize("素晴らしき日々"), "素 晴 rashiki 日 々");
+        assert_eq!(normalize("月姫"), "月 姫");
+    }
+
+    #[test]
+    fn should_create_match_query() {
+        assert_eq!(match_query("Fate/stay"), Some("\"fate\"* \"stay\"*".to_owned()));
+        assert_eq!(match_query("月姫"), Some("\"月\"* \"姫\"*".to_owned()));
+        assert_eq!(match_query(" - "), None);
+    }
+}
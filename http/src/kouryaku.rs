@@ -4,10 +4,10 @@ use ::regex::Regex;
 use ::futures::{future, Future};
 use ::encoding::codec::japanese::Windows31JEncoding as ShiftJS;
 use ::encoding::types::{Encoding, DecoderTrap};
-use ::{HttpDate, ResponseError, Request, IfModifiedSince, AutoClient};
+use ::{HttpDate, ResponseError, Request, IfModifiedSince, AutoClient, header};
 
-const WALK_ROOT: &'static str = "http://seiya-saiga.com/game/";
 const WALK_PAGE: &'static str = "http://seiya-saiga.com/game/kouryaku.html";
+const MAX_REDIRECTS: usize = 3;
 
 pub struct Entry {
     pub title: String,
@@ -23,18 +23,121 @@ impl Entry {
     }
 }
 
+///Downloaded list of walkthroughs.
+struct Page {
+    url: String,
+    html: String,
+    last_modified: Option<HttpDate>
+}
+
 struct Cache {
-    date: HttpDate,
+    //Directory of page to which entries' URIs are relative.
+    root: String,
+    date: Option<HttpDate>,
     content: Vec<Entry>
 }
 
+///Returns directory part of URL.
+fn url_dir(url: &str) -> &str {
+    match url.rfind('/') {
+        Some(idx) => &url[..idx+1],
+        None => url
+    }
+}
+
+///Resolves `Location` of redirect against URL that has been requested.
+fn resolve_location(url: &str, location: &str) -> String {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        return location.to_string();
+    }
+
+    if location.starts_with('/') {
+        let host_start = url.find("://").map(|idx| idx + 3).unwrap_or(0);
+        let host_end = url[host_start..].find('/').map(|idx| host_start + idx).unwrap_or(url.len());
+
+        return format!("{}{}", &url[..host_end], location);
+    }
+
+    format!("{}{}", url_dir(url), location)
+}
+
+///Downloads page, following redirects.
+///
+///Returns `None` if page hasn't been modified since `date`.
+fn fetch(url: String, date: Option<HttpDate>, redirects: usize) -> Box<Future<Item=Option<Page>, Error=String>> {
+    let request = match Request::get(url.as_str()) {
+        Ok(request) => request,
+        Err(error) => return Box::new(future::err(format!("Invalid URL '{}'. Error: {:?}", url, error)))
+    };
+    let request = match date {
+        Some(ref date) => request.set_date(date, IfModifiedSince).empty(),
+        None => request.empty()
+    };
+
+    let result = request.send()
+                        .map_err(|error| match error {
+                            ResponseError::Timeout(_) => format!("Request timedout"),
+                            ResponseError::Timer(_, _) => format!("Request timedout"),
+                            ResponseError::HyperError(error) => format!("Request failed. Error: {}", error),
+                        })
+                        .and_then(move |rsp| -> Box<Future<Item=Option<Page>, Error=String>> {
+                            if rsp.status().as_u16() == 304 {
+                                Box::new(future::ok(None))
+                            } else if rsp.is_redirect() {
+                                let location = rsp.headers().get(header::LOCATION)
+                                                            .and_then(|location| location.to_str().ok())
+                                                            .map(|location| resolve_location(&url, location));
+
+                                match location {
+                                    Some(_) if redirects >= MAX_REDIRECTS => Box::new(future::err("Too many redirects".to_string())),
+                                    Some(location) => {
+                                        debug!("HTTP: Kouryaku is redirected to {}", location);
+                                        fetch(location, date, redirects + 1)
+                                    },
+                                    None => Box::new(future::err(format!("Request failed. Status: {} without Location", rsp.status())))
+                                }
+                            } else if rsp.is_success() {
+                                let last_modified = rsp.last_modified();
+                                if last_modified.is_none() {
+                                    warn!("HTTP: Kouryaku has no Last-Modified");
+                                }
+
+                                let res = rsp.body().limit(u64::max_value())
+                                                    .map_err(|error| {
+                                                        warn!("HTTP: Error while reading body: {:?}", error);
+                                                        "Unable to read HTTP body from Kouryaku".to_string()
+                                                    }).and_then(move |body| {
+                                                        ShiftJS.decode(&body, DecoderTrap::Strict).map_err(|error| {
+                                                            warn!("HTTP: Unable to decode using ShiftJS: {:?}", error);
+                                                            "Kouryaku has invalid encoding".to_string()
+                                                        }).map(|html| Some(Page { url, html, last_modified }))
+                                                    });
+                                Box::new(res)
+                            } else {
+                                Box::new(future::err(format!("Request failed. Status: {}", rsp.status())))
+                            }
+                        });
+
+    Box::new(result)
+}
+
 ///Provides Kouryaku getter service
 pub struct Kouryaku {
+    page: String,
     cache: Option<Cache>,
     regex: Regex
 }
 
 impl Kouryaku {
+    ///Creates service that looks up walkthroughs on `page`.
+    pub fn new<T: Into<String>>(page: T) -> Self {
+        Self {
+            page: page.into(),
+            cache: None,
+            regex: Regex::new("<td align=\"left\"><B><A href=\"(?P<url>[^\"]+)\">(?P<title>[^<]+)</A></B></td>").expect("To create regex")
+        }
+    }
+
     fn find_iter(&self, title: &str) -> Option<impl Iterator<Item=&Entry>> {
         match self.cache.as_ref() {
             Some(cache) => {
@@ -45,64 +148,41 @@ impl Kouryaku {
         }
     }
 
-    fn update_cache(&mut self, html: String, last_modified: HttpDate) {
+    ///Returns title and URL of first matching walkthrough.
+    fn find(&self, title: &str) -> Option<(String, String)> {
+        let root = match self.cache.as_ref() {
+            Some(cache) => &cache.root,
+            None => return None
+        };
+
+        self.find_iter(title).and_then(|mut iter| iter.next())
+                             .map(|entry| (entry.title.clone(), format!("{}{}", root, &entry.uri)))
+    }
+
+    fn update_cache(&mut self, page: Page) {
         let mut entries = Vec::with_capacity(1024);
-        for caps in self.regex.captures_iter(&html) {
+        for caps in self.regex.captures_iter(&page.html) {
             let entry = Entry::new(caps["title"].to_string(), caps["url"].to_string());
             entries.push(entry);
         }
 
         self.cache = Some(Cache {
-            date: last_modified,
+            root: url_dir(&page.url).to_string(),
+            date: page.last_modified,
             content: entries
         });
     }
 
-    fn prepare_request(&self) -> Request {
-        match self.cache.as_ref() {
-            Some(cache) => Request::get(WALK_PAGE).expect("Create request").set_date(&cache.date, IfModifiedSince).empty(),
-            None => Request::get(WALK_PAGE).expect("Create request").empty(),
-        }
-    }
+    fn request_kor(&self) -> impl Future<Item=Option<Page>, Error=String> {
+        let date = self.cache.as_ref().and_then(|cache| cache.date.clone());
 
-    fn request_kor(&self) -> impl Future<Item=Option<(String, HttpDate)>, Error=String> {
-        self.prepare_request()
-            .send()
-            .map_err(|error| match error {
-                ResponseError::Timeout(_) => format!("Request timedout"),
-                ResponseError::Timer(_, _) => format!("Request timedout"),
-                ResponseError::HyperError(error) => format!("Request failed. Error: {}", error),
-            })
-            .and_then(|rsp| -> Box<Future<Item=Option<(String, HttpDate)>, Error=String>> {
-                if rsp.is_redirect() {
-                    Box::new(future::ok(None))
-                } else if rsp.is_success() {
-                    let last_modified = rsp.last_modified().expect("To have Last-Modified");
-
-                    let res = rsp.body().limit(u64::max_value())
-                                        .map_err(|error| {
-                                            warn!("HTTP: Error while reading body: {:?}", error);
-                                            "Unable to read HTTP body from Kouryaku".to_string()
-                                        }).and_then(move |body| {
-                                            ShiftJS.decode(&body, DecoderTrap::Strict).map_err(|error| {
-                                                warn!("HTTP: Unable to decode using ShiftJS: {:?}", error);
-                                                "Kouryaku has invalid encoding".to_string()
-                                            }).map(|text| Some((text, last_modified)))
-                                        });
-                    Box::new(res)
-                } else {
-                    Box::new(future::err(format!("Request failed. Status: {}", rsp.status())))
-                }
-            })
+        fetch(self.page.clone(), date, 0)
     }
 }
 
 impl Default for Kouryaku {
     fn default() -> Self {
-        Self {
-            cache: None,
-            regex: Regex::new("<td align=\"left\"><B><A href=\"(?P<url>[^\"]+)\">(?P<title>[^<]+)</A></B></td>").expect("To create regex")
-        }
+        Self::new(WALK_PAGE)
     }
 }
 
@@ -127,17 +207,10 @@ impl Handler<Find> for Kouryaku {
         let title = msg.0;
         let req = self.request_kor()
                       .into_actor(self)
-                      .map(|new_entry, act, _ctx| match new_entry {
-                          Some((new, last_modified)) => {
-                              act.update_cache(new, last_modified);
-                          },
+                      .map(|page, act, _ctx| match page {
+                          Some(page) => act.update_cache(page),
                           None => ()
-                      }).map(move |_, act, _ctx| {
-                          act.find_iter(&title)
-                             .and_then(|mut iter| {
-                                iter.next().map(|entry| (entry.title.clone(), format!("{}{}", WALK_ROOT, &entry.uri)))
-                             })
-                      });
+                      }).map(move |_, act, _ctx| act.find(&title));
 
         Box::new(req)
     }
@@ -145,41 +218,113 @@ impl Handler<Find> for Kouryaku {
 
 #[cfg(test)]
 mod tests {
-    use super::{Kouryaku, Find, Future};
+    extern crate mock;
+
+    use super::{Kouryaku, Find, resolve_location};
     use ::init;
-    use ::actix::{spawn, System};
+    use ::actix::{Actor, Addr, System, SystemRunner};
+    use self::mock::http::{Server, Response};
+
+    const FIXTURE: &'static [u8] = include_bytes!("../tests/fixtures/kouryaku.html");
+    const LAST_MODIFIED: &'static str = "Sat, 01 Sep 2018 10:00:00 GMT";
+
+    fn find(system: &mut SystemRunner, kouryaku: &Addr<Kouryaku>, title: &str) -> Result<Option<(String, String)>, String> {
+        system.block_on(kouryaku.send(Find(title.to_string()))).expect("To deliver message")
+    }
 
     #[test]
-    fn test_kouryaku() {
-        const MIRAI_RADIO: &'static str = "未来ラジオと人工";
-        const DRAC: &'static str = "ドラクリウス";
+    fn should_resolve_location() {
+        assert_eq!(resolve_location("http://host/game/page.html", "https://other/page.html"), "https://other/page.html");
+        assert_eq!(resolve_location("http://host/game/page.html", "/new/page.html"), "http://host/new/page.html");
+        assert_eq!(resolve_location("http://host:8080/game/page.html", "new.html"), "http://host:8080/game/new.html");
+    }
 
+    fn should_use_cache_when_not_modified(system: &mut SystemRunner) {
+        let server = Server::start(|request| match request.header("If-Modified-Since") {
+            Some(LAST_MODIFIED) => Response::new(304),
+            _ => Response::new(200).header("Last-Modified", LAST_MODIFIED).body(FIXTURE)
+        }).expect("To start server");
+        let kouryaku = Kouryaku::new(server.url("/game/kouryaku.html")).start();
 
-        System::run(|| {
-            init();
+        let drac = find(system, &kouryaku, "ドラクリウス").expect("To find Duraculis").expect("To have Duraculis");
+        assert_eq!(drac.0, "ドラクリウス");
+        assert_eq!(drac.1, server.url("/game/meroq/drac.html"));
 
-            let kouryaku = System::current().registry().get::<Kouryaku>();
+        let mirai_radio = find(system, &kouryaku, "未来ラジオと人工").expect("To find Mirai Radio").expect("To have Mirai Radio");
+        assert_eq!(mirai_radio.0, "未来ラジオと人工鳩");
+        assert_eq!(mirai_radio.1, server.url("/game/laplacian/mirairadio.html"));
 
-            let drac = kouryaku.send(Find(DRAC.to_string())).map(move |drac| {
-                let drac = drac.expect("Expect to successfully find Duraculis");
-                let drac = drac.expect("Expect to find some Duraculis");
+        let clannad = find(system, &kouryaku, "Clannad").expect("To find Clannad").expect("To have Clannad");
+        assert_eq!(clannad.0, "clannad");
 
-                assert_eq!(drac.0, "ドラクリウス");
-                assert_eq!(drac.1, "http://seiya-saiga.com/game/meroq/drac.html");
+        let none = find(system, &kouryaku, "Unknown").expect("To search for unknown");
+        assert!(none.is_none());
 
-                System::current().stop();
-            }).map_err(|_| panic!("Unable to send msg!"));
+        let requests = server.requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[0].header("If-Modified-Since"), None);
+        for request in requests.iter().skip(1) {
+            assert_eq!(request.header("If-Modified-Since"), Some(LAST_MODIFIED));
+        }
+    }
 
-            let mirai = kouryaku.send(Find(MIRAI_RADIO.to_string())).map(move |mirai_radio| {
-                let mirai_radio = mirai_radio.expect("Expect to successfully find Mirai Radio");
-                let mirai_radio = mirai_radio.expect("Expect to find some Mirai Radio");
+    fn should_handle_missing_last_modified(system: &mut SystemRunner) {
+        let server = Server::start(|_| Response::new(200).body(FIXTURE)).expect("To start server");
+        let kouryaku = Kouryaku::new(server.url("/game/kouryaku.html")).start();
 
-                assert_eq!(mirai_radio.0, "未来ラジオと人工鳩");
-                assert_eq!(mirai_radio.1, "http://seiya-saiga.com/game/laplacian/mirairadio.html");
-                spawn(drac);
-            }).map_err(|_| panic!("Unable to send msg!"));
+        for _ in 0..2 {
+            let drac = find(system, &kouryaku, "ドラクリウス").expect("To find Duraculis");
+            assert!(drac.is_some());
+        }
 
-            spawn(mirai);
-        });
+        for request in server.requests() {
+            assert_eq!(request.header("If-Modified-Since"), None);
+        }
+    }
+
+    fn should_follow_redirects(system: &mut SystemRunner) {
+        let server = Server::start(|request| match request.path.as_str() {
+            "/old/kouryaku.html" => Response::new(301).header("Location", "/game/kouryaku.html"),
+            "/game/kouryaku.html" => Response::new(200).header("Last-Modified", LAST_MODIFIED).body(FIXTURE),
+            "/loop.html" => Response::new(302).header("Location", "loop.html"),
+            "/nowhere.html" => Response::new(302),
+            _ => Response::new(404)
+        }).expect("To start server");
+
+        let kouryaku = Kouryaku::new(server.url("/old/kouryaku.html")).start();
+        let drac = find(system, &kouryaku, "ドラクリウス").expect("To find Duraculis").expect("To have Duraculis");
+        assert_eq!(drac.1, server.url("/game/meroq/drac.html"));
+
+        let kouryaku = Kouryaku::new(server.url("/loop.html")).start();
+        let error = find(system, &kouryaku, "ドラクリウス").expect_err("To fail on redirect loop");
+        assert_eq!(error, "Too many redirects");
+
+        let kouryaku = Kouryaku::new(server.url("/nowhere.html")).start();
+        let error = find(system, &kouryaku, "ドラクリウス").expect_err("To fail without Location");
+        assert!(error.ends_with("without Location"));
+
+        let kouryaku = Kouryaku::new(server.url("/missing.html")).start();
+        let error = find(system, &kouryaku, "ドラクリウス").expect_err("To fail on missing page");
+        assert!(error.starts_with("Request failed. Status: 404"));
+    }
+
+    fn should_reject_bad_encoding(system: &mut SystemRunner) {
+        let server = Server::start(|_| Response::new(200).body(&b"<html>\xff\xfe</html>"[..])).expect("To start server");
+        let kouryaku = Kouryaku::new(server.url("/game/kouryaku.html")).start();
+
+        let error = find(system, &kouryaku, "ドラクリウス").expect_err("To fail on bad encoding");
+        assert_eq!(error, "Kouryaku has invalid encoding");
+    }
+
+    #[test]
+    fn test_kouryaku() {
+        //HTTP client is global so scenarios are run one after another.
+        let mut system = System::new("test");
+        init();
+
+        should_use_cache_when_not_modified(&mut system);
+        should_handle_missing_last_modified(&mut system);
+        should_follow_redirects(&mut system);
+        should_reject_bad_encoding(&mut system);
     }
 }
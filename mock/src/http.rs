@@ -0,0 +1,170 @@
+//!Minimal HTTP/1.1 server with scripted responses.
+//!
+//!Each connection serves single request and is closed afterwards.
+
+use ::std::net::{TcpListener, TcpStream, SocketAddr};
+use ::std::io::{self, BufRead, BufReader, Read, Write};
+use ::std::sync::{Arc, Mutex};
+use ::std::thread;
+
+///Received request.
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    ///Returns value of header, ignoring case of its name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|&&(ref key, _)| key.eq_ignore_ascii_case(name))
+                           .map(|&(_, ref value)| value.as_str())
+    }
+}
+
+///Response to send.
+pub struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn header<N: Into<String>, V: Into<String>>(mut self, name: N, value: V) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn body<B: Into<Vec<u8>>>(mut self, body: B) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            301 => "Moved Permanently",
+            302 => "Found",
+            304 => "Not Modified",
+            400 => "Bad Request",
+            404 => "Not Found",
+            429 => "Too Many Requests",
+            500 => "Internal Server Error",
+            _ => "Unknown",
+        }
+    }
+}
+
+///Running fake server.
+///
+///It is stopped together with test process.
+pub struct Server {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl Server {
+    ///Starts server on random local port, which responds using `handler`.
+    pub fn start<F: Fn(&Request) -> Response + Send + Sync + 'static>(handler: F) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let handler = Arc::new(handler);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let server_requests = requests.clone();
+        thread::spawn(move || for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => break,
+            };
+
+            let handler = handler.clone();
+            let requests = server_requests.clone();
+            thread::spawn(move || serve(stream, &*handler, &requests));
+        });
+
+        Ok(Self {
+            addr,
+            requests
+        })
+    }
+
+    #[inline]
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    ///Returns URL of `path` on this server.
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    ///Returns all requests received so far.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn read_request(stream: &TcpStream) -> io::Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or("").to_owned();
+    let path = parts.next().unwrap_or("").to_owned();
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+
+        let header = line.trim_right();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some(idx) = header.find(':') {
+            headers.push((header[..idx].trim().to_owned(), header[idx+1..].trim().to_owned()));
+        }
+    }
+
+    let len = headers.iter().find(|&&(ref key, _)| key.eq_ignore_ascii_case("Content-Length"))
+                            .and_then(|&(_, ref value)| value.parse::<usize>().ok())
+                            .unwrap_or(0);
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body)?;
+
+    Ok(Request {
+        method,
+        path,
+        headers,
+        body
+    })
+}
+
+fn serve<F: Fn(&Request) -> Response>(mut stream: TcpStream, handler: &F, requests: &Mutex<Vec<Request>>) -> io::Result<()> {
+    let request = read_request(&stream)?;
+    let response = handler(&request);
+    requests.lock().unwrap().push(request);
+
+    write!(stream, "HTTP/1.1 {} {}\r\n", response.status, response.reason())?;
+    for &(ref name, ref value) in response.headers.iter() {
+        write!(stream, "{}: {}\r\n", name, value)?;
+    }
+    write!(stream, "Content-Length: {}\r\nConnection: close\r\n\r\n", response.body.len())?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
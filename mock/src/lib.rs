@@ -0,0 +1,7 @@
+//!Test support utilities.
+
+#[macro_use]
+extern crate serde_json;
+
+pub mod vndb;
+pub mod http;
@@ -0,0 +1,672 @@
+//!Fake VNDB TCP API server.
+//!
+//!Understands `login` and `get` commands with filters and paging,
+//!which is enough to exercise VNDB client without network.
+//!
+//!The same objects can be served over JSON HTTP API (Kana), which supports filtering by ID and search.
+
+use ::serde_json;
+use ::serde_json::Value;
+
+use ::std::collections::HashMap;
+use ::std::net::{TcpListener, TcpStream, SocketAddr};
+use ::std::io::{self, BufRead, BufReader, Write};
+use ::std::sync::{Arc, Mutex};
+use ::std::time::Duration;
+use ::std::thread;
+
+use ::http;
+
+const TERMINATOR: u8 = 0x04;
+const KINDS: [&'static str; 5] = ["vn", "release", "character", "producer", "user"];
+///Number of results per page, unless specified by request.
+const PAGE_SIZE: u64 = 10;
+///Time for which unresponsive Kana server holds request.
+const UNRESPONSIVE_SECS: u64 = 60;
+
+///Objects served by `Server`.
+#[derive(Clone, Default)]
+pub struct Data {
+    objects: HashMap<String, Vec<Value>>,
+    ///Number of first `get` requests that are throttled.
+    throttled: usize,
+    ///Seconds to wait after throttling.
+    minwait: f64,
+    ///Whether `get` requests are left without response.
+    unresponsive: bool,
+}
+
+impl Data {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///Adds arbitrary object of `kind`, which must have `id` field.
+    pub fn add(mut self, kind: &str, item: Value) -> Self {
+        self.objects.entry(kind.to_owned()).or_insert_with(Vec::new).push(item);
+        self
+    }
+
+    pub fn vn(self, id: u64, title: &str, original: Option<&str>) -> Self {
+        self.vn_aliased(id, title, original, None)
+    }
+
+    ///Adds VN with aliases, which VNDB separates by new line.
+    pub fn vn_aliased(self, id: u64, title: &str, original: Option<&str>, aliases: Option<&str>) -> Self {
+        self.add("vn", json!({
+            "id": id,
+            "title": title,
+            "original": original,
+            "released": null,
+            "languages": [],
+            "orig_lang": [],
+            "platforms": [],
+            "aliases": aliases
+        }))
+    }
+
+    pub fn release(self, id: u64, title: &str, vn: u64) -> Self {
+        self.release_on(id, title, vn, &[], &[])
+    }
+
+    ///Adds release for particular platforms and languages.
+    pub fn release_on(self, id: u64, title: &str, vn: u64, platforms: &[&str], languages: &[&str]) -> Self {
+        self.add("release", json!({
+            "id": id,
+            "title": title,
+            "original": null,
+            "released": null,
+            "type": "complete",
+            "patch": false,
+            "freeware": false,
+            "doujin": false,
+            "languages": languages,
+            "platforms": platforms,
+            "vn": [{"id": vn}]
+        }))
+    }
+
+    pub fn character(self, id: u64, name: &str) -> Self {
+        self.add("character", json!({
+            "id": id,
+            "name": name,
+            "original": null,
+            "gender": null,
+            "bloodt": null,
+            "birthday": [null, null]
+        }))
+    }
+
+    pub fn producer(self, id: u64, name: &str) -> Self {
+        self.add("producer", json!({
+            "id": id,
+            "name": name,
+            "original": null,
+            "type": "co",
+            "language": "ja"
+        }))
+    }
+
+    pub fn user(self, id: u64, username: &str) -> Self {
+        self.add("user", json!({
+            "id": id,
+            "username": username
+        }))
+    }
+
+    ///Responds to first `count` `get` requests with throttling error.
+    pub fn throttle(mut self, count: usize, minwait: f64) -> Self {
+        self.throttled = count;
+        self.minwait = minwait;
+        self
+    }
+
+    ///Never responds to `get` requests.
+    pub fn unresponsive(mut self) -> Self {
+        self.unresponsive = true;
+        self
+    }
+
+    fn get(&self, kind: &str) -> Option<&[Value]> {
+        match KINDS.contains(&kind) {
+            true => Some(self.objects.get(kind).map(|items| items.as_slice()).unwrap_or(&[])),
+            false => None,
+        }
+    }
+}
+
+///Running fake server.
+///
+///It is stopped together with test process.
+pub struct Server {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl Server {
+    ///Starts server on random local port.
+    pub fn start(data: Data) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let data = Arc::new(data);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let server_requests = requests.clone();
+        thread::spawn(move || for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => break,
+            };
+
+            let data = data.clone();
+            let requests = server_requests.clone();
+            thread::spawn(move || serve(stream, &data, &requests));
+        });
+
+        Ok(Self {
+            addr,
+            requests
+        })
+    }
+
+    #[inline]
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    ///Returns all requests received so far, without terminator.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    ///Returns base URL of Kana's server.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    ///Returns number of received queries, i.e. `get` requests or requests to Kana.
+    pub fn gets(&self) -> usize {
+        self.requests.lock().unwrap().iter().filter(|request| is_query(request)).count()
+    }
+
+    ///Starts server of JSON HTTP API (Kana) on random local port.
+    ///
+    ///Requests are recorded as `<method> <path> <body>`.
+    pub fn start_kana(data: Data) -> io::Result<Self> {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let server_requests = requests.clone();
+        let server = http::Server::start(move |request| {
+            let body = String::from_utf8_lossy(&request.body).into_owned();
+            let queries = {
+                let mut requests = server_requests.lock().unwrap();
+                let queries = requests.iter().filter(|request| is_query(request)).count();
+                requests.push(format!("{} {} {}", request.method, request.path, body));
+                queries
+            };
+
+            if data.unresponsive {
+                thread::sleep(Duration::from_secs(UNRESPONSIVE_SECS));
+                return http::Response::new(500);
+            } else if queries < data.throttled {
+                return http::Response::new(429).header("Retry-After", format!("{}", data.minwait)).body("Throttled");
+            }
+
+            match respond_kana(&request.method, &request.path, &body, &data) {
+                Ok(result) => http::Response::new(200).header("Content-Type", "application/json").body(format!("{}", result)),
+                Err(msg) => http::Response::new(400).body(msg),
+            }
+        })?;
+
+        Ok(Self {
+            addr: server.addr(),
+            requests
+        })
+    }
+}
+
+fn is_query(request: &str) -> bool {
+    request.starts_with("get ") || request.starts_with("GET ") || request.starts_with("POST ")
+}
+
+fn serve(stream: TcpStream, data: &Data, requests: &Mutex<Vec<String>>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    let mut buffer = Vec::new();
+
+    loop {
+        buffer.clear();
+        if reader.read_until(TERMINATOR, &mut buffer)? == 0 {
+            return Ok(());
+        }
+
+        if buffer.last() == Some(&TERMINATOR) {
+            buffer.pop();
+        }
+
+        let request = String::from_utf8_lossy(&buffer).into_owned();
+        let response = {
+            let mut requests = requests.lock().unwrap();
+            let is_get = request.starts_with("get ");
+            let gets = requests.iter().filter(|request| request.starts_with("get ")).count();
+            let response = match is_get {
+                true if data.unresponsive => None,
+                true if gets < data.throttled => Some(throttled(data.minwait)),
+                _ => Some(respond(&request, data)),
+            };
+            requests.push(request);
+            response
+        };
+
+        let response = match response {
+            Some(response) => response,
+            None => continue,
+        };
+
+        writer.write_all(response.as_bytes())?;
+        writer.write_all(&[TERMINATOR])?;
+    }
+}
+
+fn error(id: &str, msg: &str) -> String {
+    format!("error {}", json!({"id": id, "msg": msg}))
+}
+
+fn throttled(minwait: f64) -> String {
+    format!("error {}", json!({
+        "id": "throttled",
+        "msg": "Throttle limit reached.",
+        "type": "cmd",
+        "minwait": minwait,
+        "fullwait": minwait * 10.0
+    }))
+}
+
+///Produces response to a single request.
+pub fn respond(request: &str, data: &Data) -> String {
+    let (command, args) = split_word(request.trim());
+
+    match command {
+        "login" => "ok".to_owned(),
+        "get" => match get(args, data) {
+            Ok(results) => format!("results {}", results),
+            Err(msg) => error("parse", &msg),
+        },
+        _ => error("parse", "Unknown command"),
+    }
+}
+
+///Converts object into its representation in Kana.
+fn kana_item(kind: &str, item: &Value) -> Value {
+    let id = format!("{}{}", &kind[..1], item["id"]);
+
+    match kind {
+        "vn" => {
+            let aliases = item["aliases"].as_str().map(|aliases| aliases.split('\n').collect::<Vec<_>>()).unwrap_or_default();
+
+            json!({
+                "id": id,
+                "title": item["title"],
+                "alttitle": item["original"],
+                "aliases": aliases,
+                "released": item["released"],
+                "languages": item["languages"],
+                "olang": item["orig_lang"][0],
+                "platforms": item["platforms"]
+            })
+        },
+        "release" => {
+            let languages = item["languages"].as_array().map(|languages| languages.iter().map(|lang| json!({"lang": lang})).collect::<Vec<_>>())
+                                                        .unwrap_or_default();
+            let vns = item["vn"].as_array().map(|vns| vns.iter().map(|vn| json!({"id": format!("v{}", vn["id"]), "rtype": item["type"]})).collect::<Vec<_>>())
+                                           .unwrap_or_default();
+
+            json!({
+                "id": id,
+                "title": item["title"],
+                "alttitle": item["original"],
+                "released": item["released"],
+                "patch": item["patch"],
+                "freeware": item["freeware"],
+                "languages": languages,
+                "platforms": item["platforms"],
+                "vns": vns
+            })
+        },
+        "character" => {
+            let sex = match item["gender"] {
+                Value::Null => Value::Null,
+                ref gender => json!([gender, gender]),
+            };
+            let birthday = match (&item["birthday"][0], &item["birthday"][1]) {
+                (&Value::Null, &Value::Null) => Value::Null,
+                (day, month) => json!([month, day]),
+            };
+
+            json!({
+                "id": id,
+                "name": item["name"],
+                "original": item["original"],
+                "sex": sex,
+                "blood_type": item["bloodt"],
+                "birthday": birthday
+            })
+        },
+        "producer" => json!({
+            "id": id,
+            "name": item["name"],
+            "original": item["original"],
+            "type": item["type"],
+            "lang": item["language"]
+        }),
+        _ => json!({
+            "id": id,
+            "username": item["username"]
+        }),
+    }
+}
+
+///Produces response to a single Kana request.
+pub fn respond_kana(method: &str, path: &str, body: &str, data: &Data) -> Result<Value, String> {
+    //Users are looked up by ID without filters.
+    if method == "GET" && path.starts_with("/user?") {
+        let users = data.get("user").unwrap_or(&[]);
+        let mut result = serde_json::Map::new();
+
+        for id in path["/user?".len()..].split('&').filter(|param| param.starts_with("q=")).map(|param| &param[2..]) {
+            let user = users.iter().find(|user| format!("u{}", user["id"]) == id).map(|user| kana_item("user", user));
+            result.insert(id.to_owned(), user.unwrap_or(Value::Null));
+        }
+
+        return Ok(Value::Object(result));
+    }
+
+    let kind = match (method, path) {
+        ("POST", "/vn") => "vn",
+        ("POST", "/release") => "release",
+        ("POST", "/character") => "character",
+        ("POST", "/producer") => "producer",
+        _ => return Err(format!("Unknown endpoint {} {}", method, path)),
+    };
+
+    let query: Value = serde_json::from_str(body).map_err(|error| format!("Invalid query: {}", error))?;
+    let filter = Filter::from_kana(&query["filters"])?;
+    let page = query["page"].as_u64().unwrap_or(1).max(1);
+    let results = query["results"].as_u64().unwrap_or(PAGE_SIZE);
+
+    let found = data.get(kind).unwrap_or(&[]).iter().filter(|item| filter.matches(item)).collect::<Vec<_>>();
+    let skip = ((page - 1) * results) as usize;
+    let items = found.iter().skip(skip).take(results as usize).map(|item| kana_item(kind, item)).collect::<Vec<_>>();
+
+    Ok(json!({
+        "results": items,
+        "more": found.len() > skip + items.len()
+    }))
+}
+
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim_left();
+
+    match text.find(char::is_whitespace) {
+        Some(idx) => (&text[..idx], &text[idx..]),
+        None => (text, ""),
+    }
+}
+
+fn get(args: &str, data: &Data) -> Result<Value, String> {
+    let (kind, args) = split_word(args);
+    let (_flags, args) = split_word(args);
+    let (filters, options) = split_group(args.trim()).ok_or_else(|| "Filters are missing".to_owned())?;
+
+    let items = data.get(kind).ok_or_else(|| format!("Unknown type '{}'", kind))?;
+    let filter = Filter::parse(filters)?;
+
+    let options = match options.trim() {
+        "" => json!({}),
+        options => serde_json::from_str(options).map_err(|error| format!("Invalid options: {}", error))?,
+    };
+    let page = options.get("page").and_then(Value::as_u64).unwrap_or(1).max(1);
+    let results = options.get("results").and_then(Value::as_u64).unwrap_or(PAGE_SIZE);
+
+    let found = items.iter().filter(|item| filter.matches(item)).collect::<Vec<_>>();
+    let skip = ((page - 1) * results) as usize;
+    let items = found.iter().skip(skip).take(results as usize).map(|item| (*item).clone()).collect::<Vec<_>>();
+
+    Ok(json!({
+        "num": items.len(),
+        "more": found.len() > skip + items.len(),
+        "items": items
+    }))
+}
+
+///Splits text starting with `(` into content of group and remaining text.
+fn split_group(text: &str) -> Option<(&str, &str)> {
+    if !text.starts_with('(') {
+        return None;
+    }
+
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escape = false;
+
+    for (idx, ch) in text.char_indices() {
+        match ch {
+            _ if escape => escape = false,
+            '\\' if in_string => escape = true,
+            '"' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((&text[1..idx], &text[idx+1..]));
+                }
+            },
+            _ => (),
+        }
+    }
+
+    None
+}
+
+///Splits text by keyword outside of strings and groups.
+fn split_keyword<'a>(text: &'a str, keyword: &str) -> Vec<&'a str> {
+    let keyword = format!(" {} ", keyword);
+    let mut result = Vec::new();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escape = false;
+    let mut start = 0;
+
+    for (idx, ch) in text.char_indices() {
+        match ch {
+            _ if escape => escape = false,
+            '\\' if in_string => escape = true,
+            '"' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => depth -= 1,
+            ' ' if !in_string && depth == 0 && idx >= start && text[idx..].starts_with(&keyword) => {
+                result.push(&text[start..idx]);
+                start = idx + keyword.len();
+            },
+            _ => (),
+        }
+    }
+
+    result.push(&text[start..]);
+    result
+}
+
+enum Filter {
+    Or(Vec<Filter>),
+    And(Vec<Filter>),
+    Clause {
+        field: String,
+        op: String,
+        value: Value,
+    }
+}
+
+impl Filter {
+    fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+
+        if let Some((inner, "")) = split_group(text) {
+            return Self::parse(inner);
+        }
+
+        let parts = split_keyword(text, "or");
+        if parts.len() > 1 {
+            return parts.into_iter().map(Self::parse).collect::<Result<Vec<_>, _>>().map(Filter::Or);
+        }
+
+        let parts = split_keyword(text, "and");
+        if parts.len() > 1 {
+            return parts.into_iter().map(Self::parse).collect::<Result<Vec<_>, _>>().map(Filter::And);
+        }
+
+        let op_start = text.find(|ch: char| ch == '=' || ch == '!' || ch == '~').ok_or_else(|| format!("Invalid filter '{}'", text))?;
+        let op_end = text[op_start..].find(|ch: char| ch != '=' && ch != '!' && ch != '~').map(|idx| op_start + idx).unwrap_or(text.len());
+
+        let field = text[..op_start].trim().to_owned();
+        let op = text[op_start..op_end].to_owned();
+        let value = serde_json::from_str(text[op_end..].trim()).map_err(|error| format!("Invalid filter value '{}': {}", text, error))?;
+
+        match op.as_str() {
+            "=" | "!=" | "~" => Ok(Filter::Clause { field, op, value }),
+            _ => Err(format!("Unsupported operator '{}'", op)),
+        }
+    }
+
+    ///Converts Kana's filters, of which only `id` and `search` are supported.
+    fn from_kana(filters: &Value) -> Result<Self, String> {
+        let invalid = || format!("Invalid filters {}", filters);
+        let filters = filters.as_array().ok_or_else(invalid)?;
+
+        match filters.first().and_then(Value::as_str) {
+            Some("or") => filters[1..].iter().map(Self::from_kana).collect::<Result<Vec<_>, _>>().map(Filter::Or),
+            Some("and") => filters[1..].iter().map(Self::from_kana).collect::<Result<Vec<_>, _>>().map(Filter::And),
+            Some("id") => {
+                let op = filters.get(1).and_then(Value::as_str).ok_or_else(invalid)?;
+                let id = filters.get(2).and_then(Value::as_str)
+                                       .and_then(|id| id.get(1..))
+                                       .and_then(|id| id.parse::<u64>().ok())
+                                       .ok_or_else(invalid)?;
+
+                Ok(Filter::Clause { field: "id".to_owned(), op: op.to_owned(), value: json!(id) })
+            },
+            Some("search") => {
+                let value = filters.get(2).cloned().ok_or_else(invalid)?;
+
+                Ok(Filter::Clause { field: "search".to_owned(), op: "~".to_owned(), value })
+            },
+            _ => Err(invalid()),
+        }
+    }
+
+    fn matches(&self, item: &Value) -> bool {
+        match self {
+            &Filter::Or(ref filters) => filters.iter().any(|filter| filter.matches(item)),
+            &Filter::And(ref filters) => filters.iter().all(|filter| filter.matches(item)),
+            &Filter::Clause { ref field, ref op, ref value } => {
+                //`search` looks into all names of the object.
+                let fields = match field.as_str() {
+                    "search" => vec!["title", "original", "name", "aliases"],
+                    field => vec![field],
+                };
+
+                let is_match = fields.iter().filter_map(|field| item.get(*field)).any(|field| match (op.as_str(), value) {
+                    ("~", &Value::String(ref value)) => field.as_str().map(|field| field.to_lowercase().contains(&value.to_lowercase()))
+                                                                      .unwrap_or(false),
+                    (_, &Value::Array(ref values)) => values.contains(field),
+                    (_, value) => field == value,
+                });
+
+                match op.as_str() {
+                    "!=" => !is_match,
+                    _ => is_match
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{respond, respond_kana, Data};
+    use ::serde_json::{self, Value};
+
+    fn results(response: String) -> Value {
+        assert!(response.starts_with("results "), "Unexpected response '{}'", response);
+        serde_json::from_str(&response["results ".len()..]).expect("Valid JSON")
+    }
+
+    fn data() -> Data {
+        Data::new().vn(1, "Ever17 -the out of infinity-", Some("Ever17"))
+                   .vn(2, "Never7 -the end of infinity-", None)
+                   .vn(3, "Remember11 -the age of infinity-", None)
+                   .user(1, "yorhel")
+    }
+
+    #[test]
+    fn should_login() {
+        assert_eq!(respond("login {\"protocol\":1,\"client\":\"test\",\"clientver\":0.1}", &data()), "ok");
+    }
+
+    #[test]
+    fn should_get_by_id() {
+        let result = results(respond("get vn basic (id = 2)", &data()));
+        assert_eq!(result["num"], 1);
+        assert_eq!(result["items"][0]["title"], "Never7 -the end of infinity-");
+
+        let result = results(respond("get vn basic (id = [1,3])", &data()));
+        assert_eq!(result["num"], 2);
+    }
+
+    #[test]
+    fn should_get_by_title() {
+        let result = results(respond("get vn basic (title = \"Ever17\" or original = \"Ever17\")", &data()));
+        assert_eq!(result["num"], 1);
+        assert_eq!(result["items"][0]["id"], 1);
+
+        let result = results(respond("get vn basic (title ~ \"infinity\" or original ~ \"infinity\")", &data()));
+        assert_eq!(result["num"], 3);
+
+        let result = results(respond("get user basic (username = \"yorhel\")", &data()));
+        assert_eq!(result["items"][0]["id"], 1);
+    }
+
+    #[test]
+    fn should_page_results() {
+        let result = results(respond("get vn basic (title ~ \"infinity\") {\"page\":1,\"results\":2}", &data()));
+        assert_eq!(result["num"], 2);
+        assert_eq!(result["more"], true);
+
+        let result = results(respond("get vn basic (title ~ \"infinity\") {\"page\":2,\"results\":2}", &data()));
+        assert_eq!(result["num"], 1);
+        assert_eq!(result["more"], false);
+    }
+
+    #[test]
+    fn should_reject_invalid_request() {
+        assert!(respond("get vn basic", &data()).starts_with("error "));
+        assert!(respond("get unknown basic (id = 1)", &data()).starts_with("error "));
+        assert!(respond("set vn", &data()).starts_with("error "));
+    }
+
+    #[test]
+    fn should_respond_to_kana() {
+        let result = respond_kana("POST", "/vn", "{\"filters\":[\"or\",[\"id\",\"=\",\"v1\"],[\"id\",\"=\",\"v3\"]],\"results\":1}", &data()).expect("To respond");
+        assert_eq!(result["results"][0]["id"], "v1");
+        assert_eq!(result["results"][0]["alttitle"], "Ever17");
+        assert_eq!(result["more"], true);
+
+        let result = respond_kana("POST", "/vn", "{\"filters\":[\"search\",\"=\",\"never\"]}", &data()).expect("To respond");
+        assert_eq!(result["results"][0]["id"], "v2");
+
+        let result = respond_kana("GET", "/user?q=u1", "", &data()).expect("To respond");
+        assert_eq!(result["u1"]["username"], "yorhel");
+
+        assert!(respond_kana("POST", "/vn", "{\"filters\":[\"title\",\"=\",\"Ever17\"]}", &data()).is_err());
+        assert!(respond_kana("POST", "/unknown", "{}", &data()).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//!Management of web users and their API tokens from command line.
+
+extern crate db;
+
+use self::db::models::{Role, Scope};
+
+use ::auth;
+
+use std::io::{self, BufRead};
+
+pub const USAGE: &'static str = "Usage:
+    roseline-web user add <name> <role> - Adds user or updates existing one. Password is read from stdin.
+    roseline-web user del <name> - Removes user.
+    roseline-web token add <user> <name> <scope> - Creates API token for user.
+    roseline-web token list <user> - Lists API tokens of user.
+    roseline-web token del <id> - Revokes API token.
+
+Roles: everyone, trusted, moderator, owner
+Scopes: read, write";
+
+fn read_password() -> Result<String, String> {
+    let stdin = io::stdin();
+    let mut password = String::new();
+
+    eprint!("Password: ");
+    stdin.lock().read_line(&mut password).map_err(|error| format!("Unable to read password: {}", error))?;
+
+    let password = password.trim_right_matches(|ch: char| ch == '\r' || ch == '\n').to_owned();
+    match password.chars().count() >= auth::PASSWORD_MIN_LEN {
+        true => Ok(password),
+        false => Err(format!("Password should contain at least {} characters", auth::PASSWORD_MIN_LEN))
+    }
+}
+
+fn add(name: &str, role: &str) -> Result<String, String> {
+    let role = match Role::from_str(role) {
+        Some(role) => role,
+        None => return Err(format!("Invalid role '{}'\n{}", role, USAGE)),
+    };
+
+    let password = auth::hash_password(&read_password()?)?;
+    let db = db::Db::new()?;
+
+    db.put_user(name, &password, role).map(|user| format!("User '{}' is {}", user.name, user.role))
+                                      .map_err(|error| format!("Unable to add user: {}", error))
+}
+
+fn del(name: &str) -> Result<String, String> {
+    let db = db::Db::new()?;
+
+    match db.delete_user(name) {
+        Ok(0) => Err(format!("No such user '{}'", name)),
+        Ok(_) => Ok(format!("User '{}' is removed", name)),
+        Err(error) => Err(format!("Unable to remove user: {}", error)),
+    }
+}
+
+fn get_user(db: &db::Db, name: &str) -> Result<db::models::User, String> {
+    match db.get_user(name) {
+        Ok(Some(user)) => Ok(user),
+        Ok(None) => Err(format!("No such user '{}'", name)),
+        Err(error) => Err(format!("Unable to get user: {}", error)),
+    }
+}
+
+fn add_token(user: &str, name: &str, scope: &str) -> Result<String, String> {
+    let scope = match Scope::from_str(scope) {
+        Some(scope) => scope,
+        None => return Err(format!("Invalid scope '{}'\n{}", scope, USAGE)),
+    };
+
+    let db = db::Db::new()?;
+    let user = get_user(&db, user)?;
+
+    let token = auth::random_token();
+    db.put_api_token(&user, name, &auth::hash_token(&token), scope)
+      .map(|api_token| format!("Created API token #{} with scope {}. Store it now, it cannot be shown again:\n{}", api_token.id, api_token.scope, token))
+      .map_err(|error| format!("Unable to create API token: {}", error))
+}
+
+fn list_tokens(user: &str) -> Result<String, String> {
+    let db = db::Db::new()?;
+    let user = get_user(&db, user)?;
+
+    let tokens = db.get_api_tokens(&user).map_err(|error| format!("Unable to get API tokens: {}", error))?;
+    match tokens.len() {
+        0 => Ok(format!("User '{}' has no API tokens", user.name)),
+        _ => Ok(tokens.iter().map(|token| format!("#{} {} ({}) created {}", token.id, token.name, token.scope, token.created_at.format("%Y-%m-%d %H:%M")))
+                           .collect::<Vec<_>>()
+                           .join("\n"))
+    }
+}
+
+fn del_token(id: &str) -> Result<String, String> {
+    let id = id.parse::<i64>().map_err(|_| format!("Invalid token id '{}'", id))?;
+    let db = db::Db::new()?;
+
+    match db.delete_api_token(id) {
+        Ok(0) => Err(format!("No such API token #{}", id)),
+        Ok(_) => Ok(format!("API token #{} is revoked", id)),
+        Err(error) => Err(format!("Unable to revoke API token: {}", error)),
+    }
+}
+
+fn print_result(result: Result<String, String>) -> i32 {
+    match result {
+        Ok(text) => {
+            println!("{}", text);
+            0
+        },
+        Err(error) => {
+            eprintln!("{}", error);
+            1
+        }
+    }
+}
+
+///Executes `token` command.
+///
+///Returns exit code.
+pub fn token(args: &[String]) -> i32 {
+    let result = match (args.get(0).map(|arg| arg.as_str()), args.get(1), args.get(2), args.get(3)) {
+        (Some("add"), Some(user), Some(name), Some(scope)) if args.len() == 4 => add_token(user, name, scope),
+        (Some("list"), Some(user), None, None) => list_tokens(user),
+        (Some("del"), Some(id), None, None) => del_token(id),
+        _ => Err(USAGE.to_owned()),
+    };
+
+    print_result(result)
+}
+
+///Executes `user` command.
+///
+///Returns exit code.
+pub fn user(args: &[String]) -> i32 {
+    let result = match (args.get(0).map(|arg| arg.as_str()), args.get(1), args.get(2)) {
+        (Some("add"), Some(name), Some(role)) if args.len() == 3 => add(name, role),
+        (Some("del"), Some(name), None) => del(name),
+        _ => Err(USAGE.to_owned()),
+    };
+
+    print_result(result)
+}
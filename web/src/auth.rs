@@ -0,0 +1,281 @@
+//!Web users' authentication.
+//!
+//!Logged in user is identified by session cookie.
+//!Each modification must additionally carry session's CSRF token,
+//!either as `csrf` form field or `X-CSRF-Token` header.
+//!
+//!Scripts can use API tokens instead, passed as `Authorization: Bearer <token>`.
+
+extern crate argon2;
+extern crate rand;
+extern crate sha2;
+extern crate actix;
+extern crate actix_web;
+extern crate futures;
+extern crate chrono;
+extern crate cookie;
+
+extern crate actors;
+
+use self::futures::{future, Future};
+use self::rand::Rng;
+use self::sha2::{Sha256, Digest};
+use self::actix_web::HttpRequest;
+use self::actix_web::http::Cookie;
+use self::cookie::SameSite;
+use self::actors::db::models::{self, Role, Scope, Author, Frontend};
+
+use ::fmt;
+
+pub const SESSION_COOKIE: &'static str = "roseline_session";
+pub const CSRF_HEADER: &'static str = "X-CSRF-Token";
+///Minimal length of password.
+pub const PASSWORD_MIN_LEN: usize = 8;
+const SESSION_DAYS: i64 = 7;
+const TOKEN_LEN: usize = 32;
+
+///Generates random token as hex string.
+pub fn random_token() -> String {
+    let mut bytes = [0u8; TOKEN_LEN];
+    rand::thread_rng().fill(&mut bytes);
+
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+///Hashes password with random salt.
+pub fn hash_password(password: &str) -> Result<String, String> {
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill(&mut salt);
+
+    argon2::hash_encoded(password.as_bytes(), &salt, &argon2::Config::default()).map_err(|error| format!("Unable to hash password: {}", error))
+}
+
+///Checks password against hash produced by `hash_password`.
+pub fn verify_password(hash: &str, password: &str) -> bool {
+    match argon2::verify_encoded(hash, password.as_bytes()) {
+        Ok(result) => result,
+        Err(error) => {
+            warn!("Auth: unable to verify password: {}", error);
+            false
+        }
+    }
+}
+
+///Hashes API token for storage.
+///
+///Tokens are random so fast hash is enough.
+pub fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+///Extracts token from value of `Authorization` header.
+pub fn bearer_token(header: &str) -> Option<&str> {
+    let mut parts = header.trim().splitn(2, ' ');
+
+    match (parts.next(), parts.next()) {
+        (Some(scheme), Some(token)) if scheme.eq_ignore_ascii_case("Bearer") && token.trim().len() > 0 => Some(token.trim()),
+        _ => None
+    }
+}
+
+///Compares tokens in constant time.
+fn token_eq(left: &str, right: &str) -> bool {
+    let (left, right) = (left.as_bytes(), right.as_bytes());
+
+    left.len() == right.len() && left.iter().zip(right.iter()).fold(0, |acc, (left, right)| acc | (left ^ right)) == 0
+}
+
+///Creates new session for user.
+pub fn new_session(user: &models::User) -> models::Session {
+    let now = chrono::Utc::now().naive_utc();
+
+    models::Session {
+        id: random_token(),
+        user_id: user.id,
+        csrf: random_token(),
+        created_at: now,
+        expires_at: now + chrono::Duration::days(SESSION_DAYS),
+    }
+}
+
+///Creates cookie with session's token.
+///
+///Cookie is not sent with cross-site requests, and only over HTTPS if `secure` is set.
+pub fn session_cookie(session: &models::Session, secure: bool) -> Cookie<'static> {
+    Cookie::build(SESSION_COOKIE, session.id.clone()).path("/")
+                                                     .http_only(true)
+                                                     .same_site(SameSite::Strict)
+                                                     .secure(secure)
+                                                     .finish()
+}
+
+///Returns whether request is made over HTTPS, including via reverse proxy that sets `Forwarded` or `X-Forwarded-Proto`.
+pub fn is_https<S>(req: &HttpRequest<S>) -> bool {
+    req.connection_info().scheme() == "https"
+}
+
+///Returns CSRF token passed via header.
+pub fn csrf_header<S>(req: &HttpRequest<S>) -> String {
+    req.headers().get(CSRF_HEADER).and_then(|value| value.to_str().ok())
+                                  .unwrap_or("")
+                                  .to_owned()
+}
+
+#[inline]
+pub fn author(user: &models::User) -> Author {
+    Author::new(user.name.clone(), Frontend::Web)
+}
+
+///Logged in user.
+pub struct Identity {
+    pub user: models::User,
+    pub session: models::Session,
+}
+
+impl Identity {
+    #[inline]
+    pub fn role(&self) -> Role {
+        self.user.role()
+    }
+
+    #[inline]
+    pub fn author(&self) -> Author {
+        author(&self.user)
+    }
+
+    #[inline]
+    pub fn check_csrf(&self, token: &str) -> bool {
+        token_eq(&self.session.csrf, token)
+    }
+}
+
+///User identified by API token.
+///
+///Stored in request's extensions by `middleware::ApiToken`.
+#[derive(Clone)]
+pub struct TokenIdentity {
+    pub user: models::User,
+    pub token: models::ApiToken,
+}
+
+///Reasons to reject request.
+pub enum AuthError {
+    ///Request has no valid session.
+    Anonymous,
+    ///User's role is lower than required one.
+    Forbidden(Role),
+    ///CSRF token is missing or doesn't match session.
+    BadCsrf,
+    ///API token doesn't allow modifications.
+    ReadOnly,
+    ///Unable to retrieve session.
+    Internal(String),
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &AuthError::Anonymous => write!(f, "You need to log in"),
+            &AuthError::Forbidden(ref role) => write!(f, "You're not allowed to do this. Required role: {}", role),
+            &AuthError::BadCsrf => write!(f, "Invalid CSRF token"),
+            &AuthError::ReadOnly => write!(f, "API token is read-only"),
+            &AuthError::Internal(ref error) => write!(f, "{}", error),
+        }
+    }
+}
+
+///Identifies sender of request and checks that it has `required` role.
+///
+///If `csrf` is provided, it must match session's token.
+pub fn authorize<S>(req: &HttpRequest<S>, db: &actix::Addr<actors::db::Db>, required: Role, csrf: Option<String>) -> Box<Future<Item=Identity, Error=AuthError>> {
+    let token = match req.cookie(SESSION_COOKIE) {
+        Some(cookie) => cookie.value().to_owned(),
+        None => return Box::new(future::err(AuthError::Anonymous))
+    };
+
+    let get_session = db.send(actors::db::GetSession(token)).then(move |result| match result {
+        Ok(Ok(Some((session, user)))) => {
+            let identity = Identity { user, session };
+
+            if identity.role() < required {
+                return Err(AuthError::Forbidden(required));
+            }
+
+            match csrf {
+                Some(ref csrf) if !identity.check_csrf(csrf) => Err(AuthError::BadCsrf),
+                _ => Ok(identity)
+            }
+        },
+        Ok(Ok(None)) => Err(AuthError::Anonymous),
+        Ok(Err(error)) => Err(AuthError::Internal(format!("{}", error))),
+        Err(error) => Err(AuthError::Internal(format!("{}", error))),
+    });
+
+    Box::new(get_session)
+}
+
+///Identifies sender of API request that modifies data.
+///
+///API token with write scope is used if present,
+///otherwise session must be accompanied by CSRF token in `X-CSRF-Token` header.
+pub fn authorize_api<S>(req: &HttpRequest<S>, db: &actix::Addr<actors::db::Db>, required: Role) -> Box<Future<Item=models::User, Error=AuthError>> {
+    let identity = req.extensions().get::<TokenIdentity>().cloned();
+
+    match identity {
+        Some(TokenIdentity { user, token }) => match token.scope() {
+            Scope::Read => Box::new(future::err(AuthError::ReadOnly)),
+            Scope::Write if user.role() < required => Box::new(future::err(AuthError::Forbidden(required))),
+            Scope::Write => Box::new(future::ok(user)),
+        },
+        None => Box::new(authorize(req, db, required, Some(csrf_header(req))).map(|identity| identity.user))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{random_token, hash_password, verify_password, hash_token, bearer_token, token_eq, TOKEN_LEN};
+
+    #[test]
+    fn should_generate_random_tokens() {
+        let token = random_token();
+        assert_eq!(token.len(), TOKEN_LEN * 2);
+        assert!(token.chars().all(|ch| ch.is_digit(16)));
+        assert_ne!(token, random_token());
+    }
+
+    #[test]
+    fn should_verify_password() {
+        let hash = hash_password("password").expect("To hash password");
+
+        assert_ne!(hash, "password");
+        assert!(verify_password(&hash, "password"));
+        assert!(!verify_password(&hash, "Password"));
+        assert!(!verify_password("garbage", "password"));
+    }
+
+    #[test]
+    fn should_hash_token() {
+        let hash = hash_token("token");
+
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, hash_token("token"));
+        assert_ne!(hash, hash_token("token2"));
+    }
+
+    #[test]
+    fn should_parse_bearer_token() {
+        assert_eq!(bearer_token("Bearer abc"), Some("abc"));
+        assert_eq!(bearer_token("bearer  abc "), Some("abc"));
+        assert_eq!(bearer_token("Basic abc"), None);
+        assert_eq!(bearer_token("Bearer"), None);
+        assert_eq!(bearer_token("Bearer "), None);
+    }
+
+    #[test]
+    fn should_compare_tokens() {
+        assert!(token_eq("abc", "abc"));
+        assert!(!token_eq("abc", "abd"));
+        assert!(!token_eq("abc", "ab"));
+        assert!(!token_eq("", "abc"));
+    }
+}
@@ -6,18 +6,32 @@ extern crate log;
 extern crate serde_derive;
 
 extern crate utils;
+extern crate chrono;
 
 use std::fmt;
 use std::cmp;
 use std::net;
+use std::env;
+use std::process;
 
 mod templates;
+mod auth;
+mod admin;
 mod server;
 
 fn main() {
     utils::ssl::init();
     let _log_guard = utils::log::init();
 
-    server::start();
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    match args.first().map(|arg| arg.as_str()) {
+        Some("user") => process::exit(admin::user(&args[1..])),
+        Some("token") => process::exit(admin::token(&args[1..])),
+        Some(_) => {
+            eprintln!("{}", admin::USAGE);
+            process::exit(1);
+        },
+        None => server::start(),
+    }
 }
 
@@ -0,0 +1,510 @@
+//!JSON API.
+//!
+//!All endpoints are placed under `/api/v1`.
+//!Errors are returned as `{"message": "..."}` with corresponding status code.
+//!
+//!Modifications require user with sufficient role, identified either by API token with write scope
+//!or by session cookie with session's CSRF token in `X-CSRF-Token` header.
+
+extern crate actix;
+extern crate actix_web;
+extern crate futures;
+
+extern crate actors;
+
+use self::futures::{
+    future,
+    Future
+};
+use self::actix_web::{
+    App,
+    HttpRequest,
+    HttpResponse,
+    AsyncResponder,
+    State,
+    Path,
+    Query,
+    Json,
+};
+use self::actix_web::http::{
+    Method,
+    StatusCode,
+};
+use self::actors::db;
+use self::actors::db::models::{self, Role};
+use self::actors::exec::ResponseError;
+
+use ::fmt::Display;
+
+use ::auth::{self, AuthError};
+
+use super::{AppState, FutureHttpResponse, middleware};
+
+///Default number of changes to return.
+const CHANGES_LIMIT: i64 = 50;
+///Maximum number of changes to return.
+const CHANGES_MAX_LIMIT: i64 = 500;
+
+#[derive(Serialize)]
+struct Error {
+    message: String
+}
+
+pub fn error<T: Display>(status: StatusCode, message: T) -> HttpResponse {
+    HttpResponse::build(status).json(Error { message: format!("{}", message) })
+}
+
+#[inline]
+fn internal_error<T: Display>(message: T) -> HttpResponse {
+    error(StatusCode::INTERNAL_SERVER_ERROR, message)
+}
+
+fn exec_status(error: &ResponseError) -> StatusCode {
+    match error {
+        &ResponseError::UnknownVn => StatusCode::NOT_FOUND,
+        &ResponseError::UnknownRelease => StatusCode::NOT_FOUND,
+        &ResponseError::UnknownObject(_, _) => StatusCode::NOT_FOUND,
+        &ResponseError::TooMany(_, _) => StatusCode::BAD_REQUEST,
+        &ResponseError::TooManyDb(_) => StatusCode::BAD_REQUEST,
+        &ResponseError::InvalidVnId(_, _) => StatusCode::BAD_REQUEST,
+        &ResponseError::InvalidCode(_) => StatusCode::BAD_REQUEST,
+        &ResponseError::InvalidExecutable(_) => StatusCode::BAD_REQUEST,
+        &ResponseError::BadVndb => StatusCode::BAD_GATEWAY,
+        &ResponseError::BadVndbResponse => StatusCode::BAD_GATEWAY,
+        &ResponseError::VndbTimeout => StatusCode::GATEWAY_TIMEOUT,
+        &ResponseError::Throttled(_) => StatusCode::SERVICE_UNAVAILABLE,
+        &ResponseError::VndbParse(_) => StatusCode::BAD_GATEWAY,
+        &ResponseError::VndbMissing(_) => StatusCode::BAD_GATEWAY,
+        &ResponseError::VndbRejected(_, _) => StatusCode::BAD_GATEWAY,
+        &ResponseError::Db(ref error) => db_status(error),
+        &ResponseError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+///Maps DB error to status, so that only server's failures are reported as internal errors.
+pub fn db_status(error: &db::Error) -> StatusCode {
+    match error {
+        &db::Error::NotFound => StatusCode::NOT_FOUND,
+        &db::Error::Constraint => StatusCode::CONFLICT,
+        &db::Error::Busy => StatusCode::SERVICE_UNAVAILABLE,
+        &db::Error::Io => StatusCode::SERVICE_UNAVAILABLE,
+        &db::Error::Invalid(_) => StatusCode::BAD_REQUEST,
+        &db::Error::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn auth_status(error: &AuthError) -> StatusCode {
+    match error {
+        &AuthError::Anonymous => StatusCode::UNAUTHORIZED,
+        &AuthError::Forbidden(_) => StatusCode::FORBIDDEN,
+        &AuthError::BadCsrf => StatusCode::FORBIDDEN,
+        &AuthError::ReadOnly => StatusCode::FORBIDDEN,
+        &AuthError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[inline]
+fn auth_error(error: AuthError) -> HttpResponse {
+    self::error(auth_status(&error), error)
+}
+
+#[inline]
+fn exec_error(error: ResponseError) -> HttpResponse {
+    self::error(exec_status(&error), error)
+}
+
+fn format_date(date: &::chrono::NaiveDateTime) -> String {
+    date.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+#[derive(Serialize)]
+struct Vn<'a> {
+    id: i64,
+    title: &'a str,
+}
+
+impl<'a> From<&'a models::Vn> for Vn<'a> {
+    fn from(vn: &'a models::Vn) -> Self {
+        Self {
+            id: vn.id,
+            title: &vn.title
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Hook<'a> {
+    vn_id: i64,
+    version: &'a str,
+    code: &'a str,
+    author: Option<&'a str>,
+    frontend: Option<&'a str>,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+    ///VNDB release, if hook is set for particular release.
+    release_id: Option<i64>,
+}
+
+impl<'a> From<&'a models::Hook> for Hook<'a> {
+    fn from(hook: &'a models::Hook) -> Self {
+        Self {
+            vn_id: hook.vn_id,
+            version: &hook.version,
+            code: &hook.code,
+            author: hook.author.as_ref().map(|author| author.as_str()),
+            frontend: hook.frontend.as_ref().map(|frontend| frontend.as_str()),
+            created_at: hook.created_at.as_ref().map(format_date),
+            updated_at: hook.updated_at.as_ref().map(format_date),
+            release_id: hook.release_id,
+        }
+    }
+}
+
+impl<'a> From<&'a models::HookView> for Hook<'a> {
+    fn from(hook: &'a models::HookView) -> Self {
+        Self {
+            vn_id: hook.vn_id,
+            version: &hook.version,
+            code: &hook.code,
+            author: hook.author.as_ref().map(|author| author.as_str()),
+            frontend: hook.frontend.as_ref().map(|frontend| frontend.as_str()),
+            created_at: hook.created_at.as_ref().map(format_date),
+            updated_at: hook.updated_at.as_ref().map(format_date),
+            release_id: hook.release_id,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Release<'a> {
+    id: i64,
+    title: &'a str,
+    platforms: Vec<&'a str>,
+    languages: Vec<&'a str>,
+}
+
+impl<'a> From<&'a models::Release> for Release<'a> {
+    fn from(release: &'a models::Release) -> Self {
+        Self {
+            id: release.id,
+            title: &release.title,
+            platforms: release.platforms.split(',').filter(|platform| platform.len() > 0).collect(),
+            languages: release.languages.split(',').filter(|language| language.len() > 0).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Executable<'a> {
+    name: &'a str,
+    hash: Option<&'a str>,
+    ///VNDB release, if executable belongs to particular release.
+    release_id: Option<i64>,
+}
+
+impl<'a> From<&'a models::Executable> for Executable<'a> {
+    fn from(executable: &'a models::Executable) -> Self {
+        Self {
+            name: &executable.name,
+            hash: executable.hash.as_ref().map(|hash| hash.as_str()),
+            release_id: executable.release_id,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub(super) struct VnData<'a> {
+    id: i64,
+    title: &'a str,
+    hooks: Vec<Hook<'a>>,
+    releases: Vec<Release<'a>>,
+    executables: Vec<Executable<'a>>,
+}
+
+impl<'a> From<&'a actors::db::VnData> for VnData<'a> {
+    fn from(data: &'a actors::db::VnData) -> Self {
+        Self {
+            id: data.data.id,
+            title: &data.data.title,
+            hooks: data.hooks.iter().map(Hook::from).collect(),
+            releases: data.releases.iter().map(Release::from).collect(),
+            executables: data.executables.iter().map(Executable::from).collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ExecutableData<'a> {
+    executable: Executable<'a>,
+    vn: VnData<'a>,
+}
+
+impl<'a> From<&'a actors::db::ExecutableData> for ExecutableData<'a> {
+    fn from(data: &'a actors::db::ExecutableData) -> Self {
+        Self {
+            executable: Executable::from(&data.executable),
+            vn: VnData::from(&data.data),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Revision<'a> {
+    id: i64,
+    vn_id: i64,
+    vn_title: &'a str,
+    version: &'a str,
+    old_code: Option<&'a str>,
+    new_code: Option<&'a str>,
+    author: &'a str,
+    frontend: &'a str,
+    created_at: String,
+}
+
+impl<'a> From<&'a models::HookRevision> for Revision<'a> {
+    fn from(revision: &'a models::HookRevision) -> Self {
+        Self {
+            id: revision.id,
+            vn_id: revision.vn_id,
+            vn_title: &revision.vn_title,
+            version: &revision.version,
+            old_code: revision.old_code.as_ref().map(|code| code.as_str()),
+            new_code: revision.new_code.as_ref().map(|code| code.as_str()),
+            author: &revision.author,
+            frontend: &revision.frontend,
+            created_at: format_date(&revision.created_at),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Submission<'a> {
+    id: i64,
+    vn_id: i64,
+    version: &'a str,
+    code: &'a str,
+    status: &'a str,
+}
+
+impl<'a> From<&'a models::PendingHook> for Submission<'a> {
+    fn from(pending: &'a models::PendingHook) -> Self {
+        Self {
+            id: pending.id,
+            vn_id: pending.vn_id,
+            version: &pending.version,
+            code: &pending.code,
+            status: &pending.status,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchQuery {
+    query: Option<String>
+}
+
+fn search((query, state): (Query<SearchQuery>, State<AppState>)) -> FutureHttpResponse {
+    let query = match query.into_inner().query {
+        Some(ref query) if query.trim().len() > 0 => query.trim().to_string(),
+        _ => return Box::new(future::ok(error(StatusCode::BAD_REQUEST, "Missing query parameter"))),
+    };
+
+    state.db.send(actors::db::SearchVn(query))
+            .then(|result| match result {
+                Ok(Ok(vns)) => Ok(HttpResponse::Ok().json(vns.iter().map(Vn::from).collect::<Vec<_>>())),
+                Ok(Err(error)) => Ok(internal_error(error)),
+                Err(error) => Ok(internal_error(error)),
+            }).responder()
+}
+
+fn vn((path, state): (Path<u64>, State<AppState>)) -> FutureHttpResponse {
+    let id = path.into_inner();
+
+    state.db.send(actors::db::GetVnData(id))
+            .then(|result| match result {
+                Ok(Ok(Some(data))) => Ok(HttpResponse::Ok().json(VnData::from(&data))),
+                Ok(Ok(None)) => Ok(error(StatusCode::NOT_FOUND, "No such VN")),
+                Ok(Err(error)) => Ok(internal_error(error)),
+                Err(error) => Ok(internal_error(error)),
+            }).responder()
+}
+
+#[derive(Deserialize)]
+struct ChangesQuery {
+    vn: Option<u64>,
+    limit: Option<i64>,
+}
+
+fn changes((query, state): (Query<ChangesQuery>, State<AppState>)) -> FutureHttpResponse {
+    let ChangesQuery{vn, limit} = query.into_inner();
+    let limit = match limit {
+        Some(limit) if limit > 0 && limit <= CHANGES_MAX_LIMIT => limit,
+        Some(_) => return Box::new(future::ok(error(StatusCode::BAD_REQUEST, format!("Limit should be within 1..{}", CHANGES_MAX_LIMIT)))),
+        None => CHANGES_LIMIT
+    };
+
+    state.db.send(actors::db::GetRevisions { vn, limit })
+            .then(|result| match result {
+                Ok(Ok(revisions)) => Ok(HttpResponse::Ok().json(revisions.iter().map(Revision::from).collect::<Vec<_>>())),
+                Ok(Err(error)) => Ok(internal_error(error)),
+                Err(error) => Ok(internal_error(error)),
+            }).responder()
+}
+
+#[derive(Deserialize)]
+struct ExeQuery {
+    hash: Option<String>,
+}
+
+///Looks up VNs by executable, preferring those registered with the same hash.
+fn exe((path, query, state): (Path<String>, Query<ExeQuery>, State<AppState>)) -> FutureHttpResponse {
+    let name = path.into_inner();
+    let hash = query.into_inner().hash.filter(|hash| hash.trim().len() > 0);
+
+    state.executor.send(actors::exec::FindExecutable::new(name, hash))
+                  .then(|result| match result {
+                      Ok(Ok(found)) => Ok(HttpResponse::Ok().json(found.iter().map(ExecutableData::from).collect::<Vec<_>>())),
+                      Ok(Err(error)) => Ok(exec_error(error)),
+                      Err(error) => Ok(internal_error(error)),
+                  }).responder()
+}
+
+#[derive(Deserialize)]
+struct PutHook {
+    version: String,
+    code: String,
+}
+
+fn put_hook((req, path, body, state): (HttpRequest<AppState>, Path<u64>, Json<PutHook>, State<AppState>)) -> FutureHttpResponse {
+    let id = path.into_inner();
+    let PutHook{version, code} = body.into_inner();
+
+    let version = version.trim().to_string();
+    let code = code.trim().to_string();
+    if version.len() == 0 || code.len() == 0 {
+        return Box::new(future::ok(error(StatusCode::BAD_REQUEST, "Version and code cannot be empty")));
+    }
+
+    let executor = state.executor.clone();
+    auth::authorize_api(&req, &state.db, Role::Everyone).then(move |result| -> FutureHttpResponse { match result {
+        Ok(ref user) if user.role() < Role::Trusted => {
+            let submit = actors::exec::SubmitHook::new(format!("v{}", id), version, code, auth::author(user), None);
+            let submit = executor.send(submit).then(|result| match result {
+                Ok(Ok(pending)) => Ok(HttpResponse::Accepted().json(Submission::from(&pending))),
+                Ok(Err(error)) => Ok(exec_error(error)),
+                Err(error) => Ok(internal_error(error)),
+            });
+
+            Box::new(submit)
+        },
+        Ok(user) => {
+            let set_hook = actors::exec::SetHook::new(format!("v{}", id), version, code, auth::author(&user));
+            let set_hook = executor.send(set_hook).then(|result| match result {
+                Ok(Ok(hook)) => Ok(HttpResponse::Ok().json(Hook::from(&hook))),
+                Ok(Err(error)) => Ok(exec_error(error)),
+                Err(error) => Ok(internal_error(error)),
+            });
+
+            Box::new(set_hook)
+        },
+        Err(error) => Box::new(future::ok(auth_error(error))),
+    }}).responder()
+}
+
+#[derive(Deserialize)]
+struct DelHookQuery {
+    version: Option<String>,
+}
+
+fn del_hook((req, path, query, state): (HttpRequest<AppState>, Path<u64>, Query<DelHookQuery>, State<AppState>)) -> FutureHttpResponse {
+    let id = path.into_inner();
+    let version = match query.into_inner().version {
+        Some(version) => version,
+        None => return Box::new(future::ok(error(StatusCode::BAD_REQUEST, "Missing version parameter"))),
+    };
+
+    let executor = state.executor.clone();
+    auth::authorize_api(&req, &state.db, Role::Moderator).then(move |result| -> FutureHttpResponse { match result {
+        Ok(user) => {
+            let del_hook = actors::exec::DelHook::new(format!("v{}", id), version, auth::author(&user));
+            let del_hook = executor.send(del_hook).then(|result| match result {
+                Ok(Ok(0)) => Ok(error(StatusCode::NOT_FOUND, "No such hook")),
+                Ok(Ok(_)) => Ok(HttpResponse::NoContent().finish()),
+                Ok(Err(error)) => Ok(exec_error(error)),
+                Err(error) => Ok(internal_error(error)),
+            });
+
+            Box::new(del_hook)
+        },
+        Err(error) => Box::new(future::ok(auth_error(error))),
+    }}).responder()
+}
+
+fn not_allowed<S>(_: &HttpRequest<S>) -> HttpResponse {
+    error(StatusCode::METHOD_NOT_ALLOWED, "Method is not allowed")
+}
+
+fn not_found<S>(_: &HttpRequest<S>) -> HttpResponse {
+    error(StatusCode::NOT_FOUND, "No such endpoint")
+}
+
+pub fn config(app: App<AppState>) -> App<AppState> {
+    app.scope("/api/v1", |scope| {
+        scope.middleware(middleware::ApiToken).resource("/vn", |res| {
+            res.method(Method::GET).with_async(search);
+            res.route().f(not_allowed);
+        }).resource("/vn/{id:[0-9]+}", |res| {
+            res.method(Method::GET).with_async(vn);
+            res.route().f(not_allowed);
+        }).resource("/vn/{id:[0-9]+}/hooks", |res| {
+            res.method(Method::PUT).with_async(put_hook);
+            res.method(Method::DELETE).with_async(del_hook);
+            res.route().f(not_allowed);
+        }).resource("/exe/{name}", |res| {
+            res.method(Method::GET).with_async(exe);
+            res.route().f(not_allowed);
+        }).resource("/changes", |res| {
+            res.method(Method::GET).with_async(changes);
+            res.route().f(not_allowed);
+        }).default_resource(|res| {
+            res.route().f(not_found);
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{exec_status, auth_status, format_date, StatusCode, AuthError, Role};
+    use super::actors::exec::ResponseError;
+    use super::actors::db;
+
+    #[test]
+    fn should_map_exec_errors_to_status() {
+        assert_eq!(exec_status(&ResponseError::UnknownVn), StatusCode::NOT_FOUND);
+        assert_eq!(exec_status(&ResponseError::TooManyDb(5)), StatusCode::BAD_REQUEST);
+        assert_eq!(exec_status(&ResponseError::InvalidCode("".to_owned())), StatusCode::BAD_REQUEST);
+        assert_eq!(exec_status(&ResponseError::InvalidExecutable("".to_owned())), StatusCode::BAD_REQUEST);
+        assert_eq!(exec_status(&ResponseError::BadVndb), StatusCode::BAD_GATEWAY);
+        assert_eq!(exec_status(&ResponseError::VndbTimeout), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(exec_status(&ResponseError::Throttled(60)), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(exec_status(&ResponseError::VndbParse("".to_owned())), StatusCode::BAD_GATEWAY);
+        assert_eq!(exec_status(&ResponseError::Db(db::Error::NotFound)), StatusCode::NOT_FOUND);
+        assert_eq!(exec_status(&ResponseError::Db(db::Error::Constraint)), StatusCode::CONFLICT);
+        assert_eq!(exec_status(&ResponseError::Db(db::Error::Busy)), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(exec_status(&ResponseError::Db(db::Error::Invalid("".to_owned()))), StatusCode::BAD_REQUEST);
+        assert_eq!(exec_status(&ResponseError::Internal("".to_owned())), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn should_map_auth_errors_to_status() {
+        assert_eq!(auth_status(&AuthError::Anonymous), StatusCode::UNAUTHORIZED);
+        assert_eq!(auth_status(&AuthError::Forbidden(Role::Moderator)), StatusCode::FORBIDDEN);
+        assert_eq!(auth_status(&AuthError::BadCsrf), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn should_format_date_as_rfc3339() {
+        let date = ::chrono::NaiveDate::from_ymd(2018, 9, 1).and_hms(10, 5, 0);
+        assert_eq!(format_date(&date), "2018-09-01T10:05:00Z");
+    }
+}
@@ -6,13 +6,13 @@ use self::actix_web::{
 
 #[derive(Serialize)]
 pub struct ClientError {
-    message: &'static str
+    message: String
 }
 
 impl ClientError {
-    pub fn new(message: &'static str) -> Self {
+    pub fn new<T: Into<String>>(message: T) -> Self {
         Self {
-            message
+            message: message.into()
         }
     }
 }
@@ -1,11 +1,20 @@
 extern crate actix_web;
 extern crate http;
 extern crate bytes;
+extern crate futures;
+
+extern crate actors;
 
 use self::http::header;
+use self::futures::Future;
 use self::actix_web::{HttpRequest, Result, HttpResponse};
+use self::actix_web::http::StatusCode;
 use self::actix_web::middleware::{Middleware, Response, Started, Finished};
 
+use ::auth;
+
+use super::{AppState, api};
+
 ///Default headers middleware
 pub struct DefaultHeaders;
 
@@ -32,6 +41,43 @@ impl<S> Middleware<S> for DefaultHeaders {
     }
 }
 
+///Identifies users by API token.
+///
+///Requests with `Authorization: Bearer <token>` header are rejected unless token is valid.
+///Otherwise `auth::TokenIdentity` is stored in request's extensions.
+pub struct ApiToken;
+
+impl Middleware<AppState> for ApiToken {
+    fn start(&self, req: &HttpRequest<AppState>) -> Result<Started> {
+        let token = {
+            let header = req.headers().get(header::AUTHORIZATION).and_then(|header| header.to_str().ok());
+
+            match header {
+                Some(header) => match auth::bearer_token(header) {
+                    Some(token) => auth::hash_token(token),
+                    None => return Ok(Started::Response(api::error(StatusCode::UNAUTHORIZED, "Invalid Authorization header"))),
+                },
+                None => return Ok(Started::Done)
+            }
+        };
+
+        let get_token = req.state().db.send(actors::db::GetApiToken(token));
+        let req = req.clone();
+        let get_token = get_token.then(move |result| match result {
+            Ok(Ok(Some((token, user)))) => {
+                debug!("HTTP: API token #{} of '{}'", token.id, user.name);
+                req.extensions_mut().insert(auth::TokenIdentity { user, token });
+                Ok(None)
+            },
+            Ok(Ok(None)) => Ok(Some(api::error(StatusCode::UNAUTHORIZED, "Invalid API token"))),
+            Ok(Err(error)) => Ok(Some(api::error(StatusCode::INTERNAL_SERVER_ERROR, error))),
+            Err(error) => Ok(Some(api::error(StatusCode::INTERNAL_SERVER_ERROR, error))),
+        });
+
+        Ok(Started::Future(Box::new(get_token)))
+    }
+}
+
 ///Debug only logger.
 ///
 ///Prints to info category but disabled unless `debug_assertions` are on
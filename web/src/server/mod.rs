@@ -27,6 +27,7 @@ use self::actix_web::{
 use self::actix_web::server::HttpServer;
 use self::actix_web::http::{
     Method,
+    StatusCode,
 };
 use self::http::Error as HttpError;
 use self::http::header;
@@ -35,6 +36,9 @@ use ::cmp;
 use ::net;
 
 use ::templates;
+use ::auth::{self, AuthError, Identity};
+use self::actors::db::models::Role;
+use self::actors::db::{hook_code, models};
 
 use templates::{
     ServeTemplate
@@ -43,13 +47,14 @@ use templates::{
 mod middleware;
 mod statics;
 mod error_rsp;
+mod api;
 
 use self::error_rsp::ClientError;
 
 type FutureHttpResponse = Box<Future<Item=HttpResponse, Error=HttpError>>;
 
 #[derive(Clone)]
-struct AppState {
+pub struct AppState {
     pub executor: self::actix::Addr<actors::exec::Executor>,
     pub db: self::actix::Addr<actors::db::Db>,
 }
@@ -58,6 +63,28 @@ pub fn not_allowed<S>(_: &HttpRequest<S>) -> HttpResponse {
     HttpResponse::MethodNotAllowed().finish()
 }
 
+///Turns rejection of request into response.
+///
+///Anonymous users are asked to log in.
+fn auth_error(error: AuthError) -> HttpResponse {
+    match error {
+        AuthError::Anonymous => redirect("/login"),
+        AuthError::Internal(error) => templates::InternalError::new(error).response(),
+        error => templates::Forbidden::new(error).response(),
+    }
+}
+
+///Turns DB error into response with status corresponding to its kind.
+fn db_error(error: actors::db::Error) -> HttpResponse {
+    match error {
+        actors::db::Error::NotFound => templates::NotFound::new().response(),
+        error => {
+            let status = api::db_status(&error);
+            templates::InternalError::new(error).response_with(status)
+        }
+    }
+}
+
 fn redirect(to: &str) -> HttpResponse {
     HttpResponse::Found().header(header::LOCATION, to)
                          .finish()
@@ -88,7 +115,7 @@ fn search((query, state): (Query<SearchQuery>, State<AppState>)) -> FutureHttpRe
                     let template = templates::Search::new(&query, result);
                     Ok(template.serve_ok())
                 },
-                Err(error) => Ok(templates::InternalError::new(error).response()),
+                Err(error) => Ok(db_error(error)),
             }).or_else(|error| Ok(templates::InternalError::new(error).response()))
             .responder()
 }
@@ -115,11 +142,11 @@ fn vn((path, state):  (Path<u64>, State<AppState>)) -> FutureHttpResponse {
     state.db.send(actors::db::GetVnData(id))
             .and_then(|result| match result {
                 Ok(Some(result)) => {
-                    let template = templates::Vn::new(result.data.id as u64, &result.data.title, result.hooks);
+                    let template = templates::Vn::new(result.data.id as u64, &result.data.title, result.hooks, result.releases, result.executables);
                     Ok(template.serve_ok())
                 },
                 Ok(None) => Ok(templates::NotFound::new().response()),
-                Err(error) => Ok(templates::InternalError::new(error).response()),
+                Err(error) => Ok(db_error(error)),
             }).or_else(|error| Ok(templates::InternalError::new(error).response()))
             .responder()
 }
@@ -129,18 +156,27 @@ struct AddHook {
     id: u64,
     title: String,
     version: Option<String>,
-    code: Option<String>
+    code: Option<String>,
+    csrf: Option<String>,
 }
 
-fn add_hook_get(query: Query<AddHook>) -> HttpResponse {
-    let mut template = templates::AddHook::new(query.id, &query.title);
-    template.version = query.version.as_ref().map(|version| version.as_str());
-    template.code = query.code.as_ref().map(|version| version.as_str());
-    template.serve_ok()
+fn add_hook_get((req, query, state): (HttpRequest<AppState>, Query<AddHook>, State<AppState>)) -> FutureHttpResponse {
+    let query = query.into_inner();
+
+    auth::authorize(&req, &state.db, Role::Everyone, None).then(move |result| match result {
+        Ok(identity) => {
+            let mut template = templates::AddHook::new(query.id, &query.title, &identity.session.csrf);
+            template.version = query.version.as_ref().map(|version| version.as_str());
+            template.code = query.code.as_ref().map(|version| version.as_str());
+            template.review = identity.role() < Role::Trusted;
+            Ok(template.serve_ok())
+        },
+        Err(error) => Ok(auth_error(error)),
+    }).responder()
 }
 
-fn add_hook_post((query, state): (Form<AddHook>, State<AppState>)) -> FutureHttpResponse {
-    let AddHook{id, title, version, code} = query.into_inner();
+fn add_hook_post((req, query, state): (HttpRequest<AppState>, Form<AddHook>, State<AppState>)) -> FutureHttpResponse {
+    let AddHook{id, title, version, code, csrf} = query.into_inner();
 
     let version = match version {
         Some(version) => version,
@@ -150,84 +186,456 @@ fn add_hook_post((query, state): (Form<AddHook>, State<AppState>)) -> FutureHttp
         Some(code) => code,
         None => return Box::new(future::ok(ClientError::new("Missing code field").into())),
     };
+    let code = match hook_code::normalize(&code) {
+        Ok(code) => code,
+        Err(error) => return Box::new(future::ok(ClientError::new(error).into())),
+    };
 
     let title = title.trim().to_string();
     let put_vn = actors::db::PutVn {
         id,
-        title
+        title,
+        original: None,
+        aliases: Vec::new(),
     };
 
     let db = state.db.clone();
-    state.db.send(put_vn).then(move |result| -> FutureHttpResponse { match result {
+    let authorize = auth::authorize(&req, &state.db, Role::Everyone, Some(csrf.unwrap_or_default()));
+    authorize.then(move |result| -> FutureHttpResponse { match result {
+        Ok(identity) => add_hook(db, put_vn, version, code, identity),
+        Err(error) => Box::new(future::ok(auth_error(error))),
+    }}).responder()
+}
+
+///Stores hook, or queues it for review if user is not trusted.
+///
+///VN of queued hook is stored only once moderator approves it.
+fn add_hook(db: self::actix::Addr<actors::db::Db>, put_vn: actors::db::PutVn, version: String, code: String, identity: Identity) -> FutureHttpResponse {
+    let author = identity.author();
+
+    if identity.role() < Role::Trusted {
+        let put_pending = actors::db::PutPendingHook {
+            vn: models::Vn { id: put_vn.id as i64, title: put_vn.title },
+            version: version.trim().to_string(),
+            code,
+            author,
+            //Web has no way to notify submitter, outcome is shown on /pending instead.
+            contact: None
+        };
+
+        let put_pending = db.send(put_pending).then(|result| match result {
+            Ok(Ok(_)) => Ok(redirect_post("/pending")),
+            Ok(Err(error)) => Ok(db_error(error)),
+            Err(error) => Ok(templates::InternalError::new(error).response())
+        });
+
+        return Box::new(put_pending);
+    }
+
+    let put_vn = db.send(put_vn).then(move |result| -> FutureHttpResponse { match result {
         Ok(Ok(vn)) => {
             let put_hook = actors::db::PutHook {
                 vn,
                 version: version.trim().to_string(),
-                code: code.trim().to_string()
+                code,
+                author
             };
 
             let put_hook = db.send(put_hook).then(|result| match result {
                 Ok(Ok(hook)) => Ok(redirect_post(&format!("/vn/{}", hook.vn_id))),
-                Ok(Err(error)) => Ok(templates::InternalError::new(error).response()),
+                Ok(Err(error)) => Ok(db_error(error)),
                 Err(error) => Ok(templates::InternalError::new(error).response())
             });
 
             Box::new(put_hook)
         },
-        Ok(Err(error)) => Box::new(future::ok(templates::InternalError::new(error).response())),
+        Ok(Err(error)) => Box::new(future::ok(db_error(error))),
         Err(error) => Box::new(future::ok(templates::InternalError::new(error).response()))
-    }}).responder()
+    }});
+
+    Box::new(put_vn)
 }
 
-fn remove_hook(id: u64, version: Option<String>, state: State<AppState>) -> FutureHttpResponse {
-    let version = match version {
-        Some(version) => version,
-        None => return Box::new(future::ok(ClientError::new("Missing version field").into())),
-    };
+fn remove_hook(id: u64, version: String, identity: Identity, db: self::actix::Addr<actors::db::Db>) -> FutureHttpResponse {
+    let author = identity.author();
 
     let get_vn = actors::db::GetVn(id);
-    state.db.send(get_vn).then(move |result| -> FutureHttpResponse { match result {
+    let get_vn = db.send(get_vn).then(move |result| -> FutureHttpResponse { match result {
         Ok(Ok(Some(vn))) => {
             let del_hook = actors::db::DelHook {
                 vn,
-                version
+                version,
+                author
             };
 
-            let del_hook = state.db.send(del_hook).then(move |result| match result {
+            let del_hook = db.send(del_hook).then(move |result| match result {
                 Ok(Ok(0)) => Ok(templates::NotFound::new().response()),
                 Ok(Ok(_)) => Ok(redirect_post(&format!("/vn/{}", id))),
-                Ok(Err(error)) => Ok(templates::InternalError::new(error).response()),
+                Ok(Err(error)) => Ok(db_error(error)),
                 Err(error) => Ok(templates::InternalError::new(error).response())
             });
 
             Box::new(del_hook)
         },
         Ok(Ok(None)) => Box::new(future::ok(templates::NotFound::new().response())),
-        Ok(Err(error)) => Box::new(future::ok(templates::InternalError::new(error).response())),
+        Ok(Err(error)) => Box::new(future::ok(db_error(error))),
         Err(error) => Box::new(future::ok(templates::InternalError::new(error).response()))
-    }}).responder()
+    }});
+
+    Box::new(get_vn)
 }
 
-fn remove_hook_get((query, state): (Query<AddHook>, State<AppState>)) -> FutureHttpResponse {
+///Asks to confirm removal.
+fn remove_hook_get((req, query, state): (HttpRequest<AppState>, Query<AddHook>, State<AppState>)) -> FutureHttpResponse {
     let query = query.into_inner();
-    let id = query.id;
-    let version = query.version;
+    let version = match query.version {
+        Some(version) => version,
+        None => return Box::new(future::ok(ClientError::new("Missing version field").into())),
+    };
 
-    remove_hook(id, version, state)
+    let (id, title) = (query.id, query.title);
+    auth::authorize(&req, &state.db, Role::Moderator, None).then(move |result| match result {
+        Ok(identity) => Ok(templates::RemoveHook::new(id, &title, &version, &identity.session.csrf).serve_ok()),
+        Err(error) => Ok(auth_error(error)),
+    }).responder()
 }
 
-fn remove_hook_del((query, state): (Form<AddHook>, State<AppState>)) -> FutureHttpResponse {
+fn remove_hook_post((req, query, state): (HttpRequest<AppState>, Form<AddHook>, State<AppState>)) -> FutureHttpResponse {
     let query = query.into_inner();
     let id = query.id;
-    let version = query.version;
+    let version = match query.version {
+        Some(version) => version,
+        None => return Box::new(future::ok(ClientError::new("Missing version field").into())),
+    };
+
+    let db = state.db.clone();
+    let authorize = auth::authorize(&req, &state.db, Role::Moderator, Some(query.csrf.unwrap_or_default()));
+    authorize.then(move |result| -> FutureHttpResponse { match result {
+        Ok(identity) => remove_hook(id, version, identity, db),
+        Err(error) => Box::new(future::ok(auth_error(error))),
+    }}).responder()
+}
+
+#[derive(Deserialize)]
+struct AddExecutable {
+    id: u64,
+    title: String,
+    name: Option<String>,
+    hash: Option<String>,
+    ///VNDB release, like `r123`.
+    release: Option<String>,
+    csrf: Option<String>,
+}
+
+impl AddExecutable {
+    ///Validates name and optional hash of executable.
+    fn executable(&self) -> Result<(String, Option<String>), ClientError> {
+        let name = match self.name {
+            Some(ref name) => models::executable_name(name).map_err(ClientError::new)?,
+            None => return Err(ClientError::new("Missing name field")),
+        };
+        let hash = match self.hash.as_ref().map(|hash| hash.trim()).filter(|hash| hash.len() > 0) {
+            Some(hash) => Some(models::file_hash(hash).map_err(ClientError::new)?),
+            None => None,
+        };
+
+        Ok((name, hash))
+    }
+}
+
+fn add_executable_get((req, query, state): (HttpRequest<AppState>, Query<AddExecutable>, State<AppState>)) -> FutureHttpResponse {
+    let query = query.into_inner();
+
+    auth::authorize(&req, &state.db, Role::Trusted, None).then(move |result| match result {
+        Ok(identity) => Ok(templates::AddExecutable::new(query.id, &query.title, &identity.session.csrf).serve_ok()),
+        Err(error) => Ok(auth_error(error)),
+    }).responder()
+}
+
+fn add_executable_post((req, form, state): (HttpRequest<AppState>, Form<AddExecutable>, State<AppState>)) -> FutureHttpResponse {
+    let form = form.into_inner();
+    let (name, hash) = match form.executable() {
+        Ok(executable) => executable,
+        Err(error) => return Box::new(future::ok(error.into())),
+    };
+    let release_id = match form.release.as_ref().map(|release| release.trim()).filter(|release| release.len() > 0) {
+        Some(release) => match models::release_ref(release) {
+            Some(release_id) => Some(release_id),
+            None => return Box::new(future::ok(ClientError::new(format!("Invalid release '{}'. Expected r<id>", release)).into())),
+        },
+        None => None,
+    };
+    let id = form.id;
+
+    let db = state.db.clone();
+    let authorize = auth::authorize(&req, &state.db, Role::Trusted, Some(form.csrf.unwrap_or_default()));
+    authorize.then(move |result| -> FutureHttpResponse { match result {
+        Ok(identity) => {
+            let author = identity.author();
+
+            let get_vn = db.send(actors::db::GetVn(id)).then(move |result| -> FutureHttpResponse { match result {
+                Ok(Ok(Some(vn))) => {
+                    let put_executable = actors::db::PutExecutable {
+                        vn,
+                        name,
+                        hash,
+                        release_id,
+                        author
+                    };
+
+                    let put_executable = db.send(put_executable).then(move |result| match result {
+                        Ok(Ok(_)) => Ok(redirect_post(&format!("/vn/{}", id))),
+                        Ok(Err(actors::db::Error::Invalid(error))) => Ok(ClientError::new(error).into()),
+                        Ok(Err(error)) => Ok(db_error(error)),
+                        Err(error) => Ok(templates::InternalError::new(error).response())
+                    });
+
+                    Box::new(put_executable)
+                },
+                Ok(Ok(None)) => Box::new(future::ok(templates::NotFound::new().response())),
+                Ok(Err(error)) => Box::new(future::ok(db_error(error))),
+                Err(error) => Box::new(future::ok(templates::InternalError::new(error).response()))
+            }});
+
+            Box::new(get_vn)
+        },
+        Err(error) => Box::new(future::ok(auth_error(error))),
+    }}).responder()
+}
+
+///Asks to confirm removal.
+fn remove_executable_get((req, query, state): (HttpRequest<AppState>, Query<AddExecutable>, State<AppState>)) -> FutureHttpResponse {
+    let query = query.into_inner();
+    let name = match query.name {
+        Some(name) => name,
+        None => return Box::new(future::ok(ClientError::new("Missing name field").into())),
+    };
+    let hash = query.hash.unwrap_or_default();
+
+    let (id, title) = (query.id, query.title);
+    auth::authorize(&req, &state.db, Role::Moderator, None).then(move |result| match result {
+        Ok(identity) => Ok(templates::RemoveExecutable::new(id, &title, &name, &hash, &identity.session.csrf).serve_ok()),
+        Err(error) => Ok(auth_error(error)),
+    }).responder()
+}
+
+fn remove_executable_post((req, form, state): (HttpRequest<AppState>, Form<AddExecutable>, State<AppState>)) -> FutureHttpResponse {
+    let form = form.into_inner();
+    let (name, hash) = match form.executable() {
+        Ok(executable) => executable,
+        Err(error) => return Box::new(future::ok(error.into())),
+    };
+    let id = form.id;
+
+    let db = state.db.clone();
+    let authorize = auth::authorize(&req, &state.db, Role::Moderator, Some(form.csrf.unwrap_or_default()));
+    authorize.then(move |result| -> FutureHttpResponse { match result {
+        Ok(identity) => {
+            let author = identity.author();
+
+            let get_vn = db.send(actors::db::GetVn(id)).then(move |result| -> FutureHttpResponse { match result {
+                Ok(Ok(Some(vn))) => {
+                    let del_executable = actors::db::DelExecutable {
+                        vn,
+                        name,
+                        hash,
+                        author
+                    };
+
+                    let del_executable = db.send(del_executable).then(move |result| match result {
+                        Ok(Ok(0)) => Ok(templates::NotFound::new().response()),
+                        Ok(Ok(_)) => Ok(redirect_post(&format!("/vn/{}", id))),
+                        Ok(Err(error)) => Ok(db_error(error)),
+                        Err(error) => Ok(templates::InternalError::new(error).response())
+                    });
+
+                    Box::new(del_executable)
+                },
+                Ok(Ok(None)) => Box::new(future::ok(templates::NotFound::new().response())),
+                Ok(Err(error)) => Box::new(future::ok(db_error(error))),
+                Err(error) => Box::new(future::ok(templates::InternalError::new(error).response()))
+            }});
+
+            Box::new(get_vn)
+        },
+        Err(error) => Box::new(future::ok(auth_error(error))),
+    }}).responder()
+}
+
+///Lists submissions awaiting review for moderators, or own submissions for others.
+fn pending((req, state): (HttpRequest<AppState>, State<AppState>)) -> FutureHttpResponse {
+    const SUBMISSIONS_LIMIT: i64 = 50;
+    let db = state.db.clone();
+
+    auth::authorize(&req, &state.db, Role::Everyone, None).then(move |result| -> FutureHttpResponse { match result {
+        Ok(ref identity) if identity.role() >= Role::Moderator => {
+            let csrf = identity.session.csrf.clone();
+            let get_pending = db.send(actors::db::GetPendingHooks).then(move |result| match result {
+                Ok(Ok(hooks)) => Ok(templates::Pending::review(&csrf, hooks).serve_ok()),
+                Ok(Err(error)) => Ok(db_error(error)),
+                Err(error) => Ok(templates::InternalError::new(error).response()),
+            });
+
+            Box::new(get_pending)
+        },
+        Ok(identity) => {
+            let get_submissions = actors::db::GetSubmissions {
+                author: identity.author(),
+                limit: SUBMISSIONS_LIMIT
+            };
+
+            let get_submissions = db.send(get_submissions).then(|result| match result {
+                Ok(Ok(hooks)) => Ok(templates::Pending::submissions(hooks).serve_ok()),
+                Ok(Err(error)) => Ok(db_error(error)),
+                Err(error) => Ok(templates::InternalError::new(error).response()),
+            });
+
+            Box::new(get_submissions)
+        },
+        Err(error) => Box::new(future::ok(auth_error(error))),
+    }}).responder()
+}
+
+#[derive(Deserialize)]
+struct Review {
+    id: u64,
+    code: Option<String>,
+    reason: Option<String>,
+    csrf: Option<String>,
+}
+
+fn pending_approve((req, form, state): (HttpRequest<AppState>, Form<Review>, State<AppState>)) -> FutureHttpResponse {
+    let Review{id, code, csrf, ..} = form.into_inner();
+    let code = match code.map(|code| code.trim().to_string()).filter(|code| code.len() > 0) {
+        Some(code) => match hook_code::normalize(&code) {
+            Ok(code) => Some(code),
+            Err(error) => return Box::new(future::ok(ClientError::new(error).into())),
+        },
+        None => None,
+    };
+
+    let db = state.db.clone();
+    let authorize = auth::authorize(&req, &state.db, Role::Moderator, Some(csrf.unwrap_or_default()));
+    authorize.then(move |result| -> FutureHttpResponse { match result {
+        Ok(identity) => {
+            let approve = actors::db::ApprovePendingHook {
+                id,
+                code,
+                reviewer: identity.author()
+            };
+
+            let approve = db.send(approve).then(|result| match result {
+                Ok(Ok(Some(_))) => Ok(redirect_post("/pending")),
+                Ok(Ok(None)) => Ok(templates::NotFound::new().response()),
+                Ok(Err(error)) => Ok(db_error(error)),
+                Err(error) => Ok(templates::InternalError::new(error).response()),
+            });
+
+            Box::new(approve)
+        },
+        Err(error) => Box::new(future::ok(auth_error(error))),
+    }}).responder()
+}
+
+fn pending_reject((req, form, state): (HttpRequest<AppState>, Form<Review>, State<AppState>)) -> FutureHttpResponse {
+    let Review{id, reason, csrf, ..} = form.into_inner();
+    let reason = reason.map(|reason| reason.trim().to_string()).filter(|reason| reason.len() > 0);
+
+    let db = state.db.clone();
+    let authorize = auth::authorize(&req, &state.db, Role::Moderator, Some(csrf.unwrap_or_default()));
+    authorize.then(move |result| -> FutureHttpResponse { match result {
+        Ok(identity) => {
+            let reject = actors::db::RejectPendingHook {
+                id,
+                reason,
+                reviewer: identity.author()
+            };
+
+            let reject = db.send(reject).then(|result| match result {
+                Ok(Ok(Some(_))) => Ok(redirect_post("/pending")),
+                Ok(Ok(None)) => Ok(templates::NotFound::new().response()),
+                Ok(Err(error)) => Ok(db_error(error)),
+                Err(error) => Ok(templates::InternalError::new(error).response()),
+            });
+
+            Box::new(reject)
+        },
+        Err(error) => Box::new(future::ok(auth_error(error))),
+    }}).responder()
+}
+
+fn login_get((req, state): (HttpRequest<AppState>, State<AppState>)) -> FutureHttpResponse {
+    auth::authorize(&req, &state.db, Role::Everyone, None).then(|result| match result {
+        Ok(identity) => Ok(templates::Login::logged_in(&identity.user.name, identity.role().as_str(), &identity.session.csrf).serve_ok()),
+        Err(AuthError::Anonymous) => Ok(templates::Login::new(None).serve_ok()),
+        Err(error) => Ok(auth_error(error)),
+    }).responder()
+}
+
+#[derive(Deserialize)]
+struct Login {
+    name: String,
+    password: String,
+}
+
+fn login_post((req, form, state): (HttpRequest<AppState>, Form<Login>, State<AppState>)) -> FutureHttpResponse {
+    const INVALID: &'static str = "Invalid name or password";
+    let Login{name, password} = form.into_inner();
+    let secure = auth::is_https(&req);
+
+    let db = state.db.clone();
+    state.db.send(actors::db::GetUser(name)).then(move |result| -> FutureHttpResponse { match result {
+        Ok(Ok(Some(user))) => match auth::verify_password(&user.password, &password) {
+            true => {
+                info!("Web: '{}' logged in", user.name);
+                let put_session = db.send(actors::db::PutSession(auth::new_session(&user))).then(move |result| match result {
+                    Ok(Ok(session)) => Ok(HttpResponse::SeeOther().header(header::LOCATION, "/")
+                                                                  .cookie(auth::session_cookie(&session, secure))
+                                                                  .finish()),
+                    Ok(Err(error)) => Ok(db_error(error)),
+                    Err(error) => Ok(templates::InternalError::new(error).response()),
+                });
+
+                Box::new(put_session)
+            },
+            false => Box::new(future::ok(templates::Login::new(Some(INVALID)).serve(StatusCode::UNAUTHORIZED))),
+        },
+        Ok(Ok(None)) => Box::new(future::ok(templates::Login::new(Some(INVALID)).serve(StatusCode::UNAUTHORIZED))),
+        Ok(Err(error)) => Box::new(future::ok(db_error(error))),
+        Err(error) => Box::new(future::ok(templates::InternalError::new(error).response())),
+    }}).responder()
+}
+
+#[derive(Deserialize)]
+struct Logout {
+    csrf: String,
+}
+
+fn logout((req, form, state): (HttpRequest<AppState>, Form<Logout>, State<AppState>)) -> FutureHttpResponse {
+    let db = state.db.clone();
+    let secure = auth::is_https(&req);
+
+    auth::authorize(&req, &state.db, Role::Everyone, Some(form.into_inner().csrf)).then(move |result| -> FutureHttpResponse { match result {
+        Ok(identity) => {
+            let del_session = db.send(actors::db::DelSession(identity.session.id.clone())).then(move |result| match result {
+                Ok(Ok(_)) => Ok(HttpResponse::SeeOther().header(header::LOCATION, "/")
+                                                         .del_cookie(&auth::session_cookie(&identity.session, secure))
+                                                         .finish()),
+                Ok(Err(error)) => Ok(db_error(error)),
+                Err(error) => Ok(templates::InternalError::new(error).response()),
+            });
 
-    remove_hook(id, version, state)
+            Box::new(del_session)
+        },
+        Err(error) => Box::new(future::ok(auth_error(error))),
+    }}).responder()
 }
 
 fn application(state: AppState) -> App<AppState> {
     App::with_state(state).middleware(middleware::DefaultHeaders)
                           .middleware(middleware::Logger)
                           .configure(statics::config)
+                          .configure(api::config)
                           .resource("/", |res| {
                               res.method(Method::GET).h(templates::Index::new("/search", "Search AGTH Hook"));
                               res.route().f(not_allowed);
@@ -241,12 +649,37 @@ fn application(state: AppState) -> App<AppState> {
                               res.method(Method::GET).with_async(search_vndb);
                               res.route().f(not_allowed);
                           }).resource("/add_hook", |res| {
-                              res.method(Method::GET).with(add_hook_get);
+                              res.method(Method::GET).with_async(add_hook_get);
                               res.method(Method::POST).with_async(add_hook_post);
                               res.route().f(not_allowed);
                           }).resource("/remove_hook", |res| {
                               res.method(Method::GET).with_async(remove_hook_get);
-                              res.method(Method::DELETE).with_async(remove_hook_del);
+                              res.method(Method::POST).with_async(remove_hook_post);
+                              res.method(Method::DELETE).with_async(remove_hook_post);
+                              res.route().f(not_allowed);
+                          }).resource("/add_executable", |res| {
+                              res.method(Method::GET).with_async(add_executable_get);
+                              res.method(Method::POST).with_async(add_executable_post);
+                              res.route().f(not_allowed);
+                          }).resource("/remove_executable", |res| {
+                              res.method(Method::GET).with_async(remove_executable_get);
+                              res.method(Method::POST).with_async(remove_executable_post);
+                              res.route().f(not_allowed);
+                          }).resource("/pending", |res| {
+                              res.method(Method::GET).with_async(pending);
+                              res.route().f(not_allowed);
+                          }).resource("/pending/approve", |res| {
+                              res.method(Method::POST).with_async(pending_approve);
+                              res.route().f(not_allowed);
+                          }).resource("/pending/reject", |res| {
+                              res.method(Method::POST).with_async(pending_reject);
+                              res.route().f(not_allowed);
+                          }).resource("/login", |res| {
+                              res.method(Method::GET).with_async(login_get);
+                              res.method(Method::POST).with_async(login_post);
+                              res.route().f(not_allowed);
+                          }).resource("/logout", |res| {
+                              res.method(Method::POST).with_async(logout);
                               res.route().f(not_allowed);
                           }).resource("/vn/{id:[0-9]+}", |res| {
                               res.method(Method::GET).with_async(vn);
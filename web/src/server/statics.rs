@@ -1,15 +1,26 @@
 extern crate actix_web;
 extern crate memmap;
 extern crate etag;
+extern crate futures;
+
+extern crate actors;
 
 use std::io;
 use std::fs;
 use std::path;
 
+use self::futures::{
+    future,
+    Future
+};
 use self::actix_web::{
+    App,
     HttpRequest,
     HttpResponse,
     HttpMessage,
+    AsyncResponder,
+    State,
+    Path,
     http,
     Body
 };
@@ -17,9 +28,12 @@ use self::actix_web::http::{
     Method,
 };
 use self::http::header;
+use self::actors::export::{self, Format};
 
 use ::templates;
 
+use super::{AppState, FutureHttpResponse};
+
 ///Matches given ETag against If-None-Match header.
 ///
 ///Returns true if matching value found in header.
@@ -115,8 +129,50 @@ fn db_dump<S>(req: &HttpRequest<S>) -> actix_web::Either<HttpResponse, templates
     }
 }
 
+///Serves hooks in requested format as attachment.
+fn serve_export(format: Format, data: &[actors::db::VnData]) -> HttpResponse {
+    let mut rsp = HttpResponse::Ok();
+    rsp.content_type(format.content_type())
+       .content_encoding(header::ContentEncoding::Auto)
+       .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", format.file_name()));
+
+    match format {
+        Format::Json => rsp.json(data.iter().map(super::api::VnData::from).collect::<Vec<_>>()),
+        Format::Csv => rsp.body(export::csv(data)),
+        Format::Textractor => rsp.body(export::textractor(data)),
+        Format::Ithvnr => rsp.body(export::ithvnr(data)),
+    }
+}
+
+fn export_all((path, state): (Path<String>, State<AppState>)) -> FutureHttpResponse {
+    let format = match Format::from_file_name(&path.into_inner()) {
+        Some(format) => format,
+        None => return Box::new(future::ok(templates::NotFound::new().response())),
+    };
 
-pub fn config<S: 'static>(app: actix_web::App<S>) -> actix_web::App<S> {
+    state.db.send(actors::db::GetAllVnData).then(move |result| match result {
+        Ok(Ok(data)) => Ok(serve_export(format, &data)),
+        Ok(Err(error)) => Ok(super::db_error(error)),
+        Err(error) => Ok(templates::InternalError::new(error).response()),
+    }).responder()
+}
+
+fn export_vn((path, state): (Path<(u64, String)>, State<AppState>)) -> FutureHttpResponse {
+    let (id, file_name) = path.into_inner();
+    let format = match Format::from_file_name(&file_name) {
+        Some(format) => format,
+        None => return Box::new(future::ok(templates::NotFound::new().response())),
+    };
+
+    state.db.send(actors::db::GetVnData(id)).then(move |result| match result {
+        Ok(Ok(Some(data))) => Ok(serve_export(format, &[data])),
+        Ok(Ok(None)) => Ok(templates::NotFound::new().response()),
+        Ok(Err(error)) => Ok(super::db_error(error)),
+        Err(error) => Ok(templates::InternalError::new(error).response()),
+    }).responder()
+}
+
+pub fn config(app: App<AppState>) -> App<AppState> {
     app.resource("/app.bundle.css", |res| {
         res.method(Method::GET).f(app_bundle_css);
         res.route().f(super::not_allowed);
@@ -133,6 +189,12 @@ pub fn config<S: 'static>(app: actix_web::App<S>) -> actix_web::App<S> {
         }).resource("/roseline.db", |res| {
             res.method(Method::GET).f(db_dump);
             res.route().f(super::not_allowed);
+        }).resource("/hooks/{file}", |res| {
+            res.method(Method::GET).with_async(export_all);
+            res.route().f(super::not_allowed);
+        }).resource("/vn/{id:[0-9]+}/{file}", |res| {
+            res.method(Method::GET).with_async(export_vn);
+            res.route().f(super::not_allowed);
         }).default_resource(|res| {
             res.route().h(templates::NotFound::new());
         })
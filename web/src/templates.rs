@@ -56,22 +56,57 @@ impl<S> Handler<S> for Index {
     }
 }
 
+///Hooks of single release.
+pub struct HookGroup {
+    release: Option<models::Release>,
+    hooks: Vec<models::Hook>,
+}
+
+impl HookGroup {
+    pub fn caption(&self) -> String {
+        match self.release {
+            Some(ref release) => format!("{}", release),
+            None => "Hooks".to_owned()
+        }
+    }
+}
+
 #[derive(Template)]
 #[template(path="vn.html")]
 pub struct Vn<'a> {
     _parent: Base,
     id: u64,
     title: &'a str,
-    hooks: Vec<models::Hook>
+    groups: Vec<HookGroup>,
+    executables: Vec<models::Executable>,
 }
 
 impl<'a> Vn<'a> {
-    pub fn new(id:u64, title: &'a str, hooks: Vec<models::Hook>) -> Self {
+    ///Creates page with hooks grouped by release.
+    ///
+    ///Hooks without release go last.
+    pub fn new(id:u64, title: &'a str, mut hooks: Vec<models::Hook>, releases: Vec<models::Release>, executables: Vec<models::Executable>) -> Self {
+        let mut groups = Vec::with_capacity(releases.len() + 1);
+
+        for release in releases {
+            let (release_hooks, rest): (Vec<_>, Vec<_>) = hooks.into_iter().partition(|hook| hook.release_id == Some(release.id));
+            hooks = rest;
+
+            if release_hooks.len() > 0 {
+                groups.push(HookGroup { release: Some(release), hooks: release_hooks });
+            }
+        }
+
+        if hooks.len() > 0 || groups.len() == 0 {
+            groups.push(HookGroup { release: None, hooks });
+        }
+
         Self {
             _parent: Base {},
             id,
             title,
-            hooks
+            groups,
+            executables
         }
     }
 }
@@ -133,6 +168,12 @@ impl<S: fmt::Display> InternalError<S> {
     pub fn response(&self) -> HttpResponse {
         self.serve(http::StatusCode::INTERNAL_SERVER_ERROR)
     }
+
+    #[inline]
+    ///Serves error with specified status, when it is known to be more specific.
+    pub fn response_with(&self, status: http::StatusCode) -> HttpResponse {
+        self.serve(status)
+    }
 }
 
 impl<S: fmt::Display> Responder for InternalError<S> {
@@ -144,6 +185,28 @@ impl<S: fmt::Display> Responder for InternalError<S> {
     }
 }
 
+#[derive(Template)]
+#[template(path="403.html")]
+pub struct Forbidden<S: fmt::Display> {
+    _parent: Base,
+    description: S
+}
+
+impl<S: fmt::Display> Forbidden<S> {
+    #[inline]
+    pub fn new(description: S) -> Self {
+        Self {
+            _parent: Base {},
+            description
+        }
+    }
+
+    #[inline]
+    pub fn response(&self) -> HttpResponse {
+        self.serve(http::StatusCode::FORBIDDEN)
+    }
+}
+
 #[derive(Template)]
 #[template(path="search.html")]
 pub struct Search<'a> {
@@ -186,18 +249,155 @@ pub struct AddHook<'a> {
     _parent: Base,
     id: u64,
     title: &'a str,
+    csrf: &'a str,
     pub version: Option<&'a str>,
-    pub code: Option<&'a str>
+    pub code: Option<&'a str>,
+    ///Whether hook is going to be reviewed.
+    pub review: bool,
 }
 
 impl<'a> AddHook<'a> {
-    pub fn new(id: u64, title: &'a str) -> Self {
+    pub fn new(id: u64, title: &'a str, csrf: &'a str) -> Self {
         Self {
             _parent: Base {},
             id,
             title,
+            csrf,
             version: None,
-            code: None
+            code: None,
+            review: false
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path="remove_hook.html")]
+pub struct RemoveHook<'a> {
+    _parent: Base,
+    id: u64,
+    title: &'a str,
+    version: &'a str,
+    csrf: &'a str,
+}
+
+impl<'a> RemoveHook<'a> {
+    pub fn new(id: u64, title: &'a str, version: &'a str, csrf: &'a str) -> Self {
+        Self {
+            _parent: Base {},
+            id,
+            title,
+            version,
+            csrf
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path="add_executable.html")]
+pub struct AddExecutable<'a> {
+    _parent: Base,
+    id: u64,
+    title: &'a str,
+    csrf: &'a str,
+}
+
+impl<'a> AddExecutable<'a> {
+    pub fn new(id: u64, title: &'a str, csrf: &'a str) -> Self {
+        Self {
+            _parent: Base {},
+            id,
+            title,
+            csrf
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path="remove_executable.html")]
+pub struct RemoveExecutable<'a> {
+    _parent: Base,
+    id: u64,
+    title: &'a str,
+    name: &'a str,
+    ///Empty if executable has no hash.
+    hash: &'a str,
+    csrf: &'a str,
+}
+
+impl<'a> RemoveExecutable<'a> {
+    pub fn new(id: u64, title: &'a str, name: &'a str, hash: &'a str, csrf: &'a str) -> Self {
+        Self {
+            _parent: Base {},
+            id,
+            title,
+            name,
+            hash,
+            csrf
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path="pending.html")]
+pub struct Pending<'a> {
+    _parent: Base,
+    moderator: bool,
+    csrf: &'a str,
+    hooks: Vec<models::PendingHook>,
+}
+
+impl<'a> Pending<'a> {
+    ///Creates list of submissions awaiting review.
+    pub fn review(csrf: &'a str, hooks: Vec<models::PendingHook>) -> Self {
+        Self {
+            _parent: Base {},
+            moderator: true,
+            csrf,
+            hooks
+        }
+    }
+
+    ///Creates list of user's own submissions.
+    pub fn submissions(hooks: Vec<models::PendingHook>) -> Self {
+        Self {
+            _parent: Base {},
+            moderator: false,
+            csrf: "",
+            hooks
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path="login.html")]
+pub struct Login<'a> {
+    _parent: Base,
+    user: Option<&'a str>,
+    role: &'a str,
+    csrf: &'a str,
+    error: Option<&'a str>,
+}
+
+impl<'a> Login<'a> {
+    ///Creates login form.
+    pub fn new(error: Option<&'a str>) -> Self {
+        Self {
+            _parent: Base {},
+            user: None,
+            role: "",
+            csrf: "",
+            error
+        }
+    }
+
+    ///Creates page of logged in user.
+    pub fn logged_in(user: &'a str, role: &'a str, csrf: &'a str) -> Self {
+        Self {
+            _parent: Base {},
+            user: Some(user),
+            role,
+            csrf,
+            error: None
         }
     }
 }